@@ -2,6 +2,7 @@ use crate::backend::BackendEvent;
 use ratatui::crossterm::event::KeyEvent;
 
 #[derive(Debug, Clone)]
+#[allow(clippy::enum_variant_names)]
 pub enum Action {
     // --- Eventos de Sistema ---
     Tick,
@@ -14,6 +15,24 @@ pub enum Action {
     SwitchTabNext,
     SwitchTabPrev,
     ToggleFocus, // Alternar entre Lista e Detalhes
+    ToggleDependencies, // Detalhes <-> Dependências no painel da direita
+    OpenDependency,     // Enter: pula para o pacote selecionado nas dependências
+    ToggleSelection,       // Espaço: marca/desmarca o pacote atual
+    ToggleVisualSelection, // 'v': abre/fecha uma seleção por faixa
+    ClearSelection,
+    ToggleQueue, // Mostra a fila de jobs no painel da direita
+    MoveJobUp,
+    MoveJobDown,
+    DropJob,
+    CancelJob, // SIGINT no apt-get, só antes do dpkg começar
+    ToggleLog,        // Saída completa do job no painel da direita
+    NextLogMatch,     // 'n': próxima ocorrência da busca no log
+    JumpToFirstError, // 'E': primeira linha de erro do log
+    ToggleConffiles,    // Leftovers de config (.dpkg-dist, .dpkg-old...) com o diff
+    AdoptConffile,      // 't': o leftover entra no lugar do arquivo em uso
+    DiscardConffile,    // 'o': fica o arquivo em uso, o leftover é apagado
+    KeepBothConffiles,  // 's': deixa os dois arquivos como estão
+    ToggleConffileDiff, // 'u': diff -u ou lado a lado
     EnterSearchMode,
     ExitSearchMode,
     TriggerOnlineSearch,
@@ -23,11 +42,30 @@ pub enum Action {
     // --- Comandos de Negócio (Disparados pelo usuário) ---
     ConfirmAction, // Enter/y no popup
     CancelAction,  // Esc/n no popup
+    ScrollPopupDown,
+    ScrollPopupUp,
     DismissNotification,
+    ApplyRemedy, // Enter numa notificação que oferece uma correção
+    PasswordInput(char),
+    PasswordBackspace,
+    SubmitPassword,
+    CancelPassword,
+    DebconfNext,
+    DebconfPrev,
+    DebconfToggle, // Espaço no multiselect
+    DebconfInput(char),
+    DebconfBackspace,
+    DebconfSubmit,
+    DebconfBack, // Esc: volta para a pergunta anterior
     RequestInstall,
     RequestUninstall,
     RequestReinstall,
+    RequestPurge,
     RequestUpgradeSystem,
+    RequestAutoremove,
+    MarkAuto,   // apt-mark auto
+    MarkManual, // apt-mark manual
+    ToggleHold, // apt-mark hold/unhold
     RefreshRepos,
 
     // --- Eventos do Backend (Respostas) ---
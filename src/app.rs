@@ -1,8 +1,20 @@
 use crate::action::Action;
+use crate::apt::AptError;
+use crate::conffile::{Conffile, ConffileChoice};
 use crate::backend::{BackendCommand, BackendEvent};
-use crate::pkg::Package;
+use crate::debconf::{Question, QuestionId, QuestionKind};
+use crate::deps::{Dependency, Relation, RelationKind, WhyInstalled};
+use crate::joblog::JobLog;
+use crate::lock::LockHolder;
+use crate::pkg::{Package, PackageId, PackageMark, PackageStatus};
+use crate::privilege::{Password, Privilege};
+use crate::progress::Progress;
+use crate::scheduler::{Job, JobId, JobState};
+use crate::simulation::Simulation;
+use crate::transaction::{Transaction, TransactionKind};
 use fuzzy_matcher::{skim::SkimMatcherV2, FuzzyMatcher};
 use ratatui::widgets::ListState;
+use std::collections::HashMap;
 use tokio::sync::mpsc::UnboundedSender;
 use strum::{Display, EnumIter, FromRepr};
 use tachyonfx::{fx, Duration as FxDuration, EffectManager, Interpolation};
@@ -17,6 +29,10 @@ pub enum SelectedTab {
     Upgradable,
     #[strum(to_string = "Online")]
     Online,
+    #[strum(to_string = "Orphans")]
+    Orphans,
+    #[strum(to_string = "Held")]
+    Held,
 }
 
 impl SelectedTab {
@@ -26,7 +42,7 @@ impl SelectedTab {
     }
     pub fn previous(self) -> Self {
         let i = self as usize;
-        Self::from_repr(i.saturating_sub(1)).unwrap_or(Self::Online)
+        Self::from_repr(i.saturating_sub(1)).unwrap_or(Self::Held)
     }
 }
 
@@ -37,23 +53,141 @@ pub enum Panel {
     Details,
 }
 
+/// What the right-hand panel shows for the selected package.
+#[derive(Default, Clone, Copy, PartialEq, Debug)]
+pub enum DetailsView {
+    #[default]
+    Info,
+    Dependencies,
+    Queue,
+    Log,
+    Conffiles,
+}
+
+/// One row of the Dependencies view.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DependencyEntry {
+    pub relation: Relation,
+    pub installed: bool, // Alguma das alternativas está instalada
+}
+
 #[derive(Default)]
 pub struct Popup {
     pub visible: bool,
     pub title: String,
     pub message: String,
     pub command_to_confirm: Option<BackendCommand>, // Guardamos o comando pronto
+    pub simulation: Option<Simulation>,             // Prévia do apt-get -s
+    pub scroll: u16,
+}
+
+/// Masked sudo password entry, shown before the first job that needs root.
+/// No Debug, so the password never ends up in a log or a test failure.
+#[derive(Default, Clone)]
+pub struct PasswordPrompt {
+    pub input: String,
+    pub error: Option<String>, // Tentativa anterior recusada
+}
+
+/// A debconf question the running job waits on. No Debug: `input` may be
+/// a password.
+#[derive(Clone)]
+pub struct DebconfPrompt {
+    pub id: QuestionId,
+    pub question: Question,
+    pub selected: usize,    // Opção destacada (select, multiselect, boolean)
+    pub checked: Vec<bool>, // Marcadas no multiselect
+    pub input: String,      // string e password
+}
+
+impl DebconfPrompt {
+    pub fn new(id: QuestionId, question: Question) -> Self {
+        let defaults = crate::debconf::split_choices(&question.default);
+        let checked = question.choices.iter().map(|c| defaults.contains(c)).collect();
+        let selected = match question.kind {
+            QuestionKind::Boolean if question.default == "false" => 1,
+            _ => question.choices.iter().position(|c| *c == question.default).unwrap_or(0),
+        };
+        let input = match question.kind {
+            QuestionKind::String => question.default.clone(),
+            _ => String::new(),
+        };
+        Self {
+            id,
+            question,
+            selected,
+            checked,
+            input,
+        }
+    }
+
+    /// What is listed to pick from: the choices, or Yes/No.
+    pub fn options(&self) -> Vec<String> {
+        match self.question.kind {
+            QuestionKind::Boolean => vec!["Yes".to_string(), "No".to_string()],
+            QuestionKind::Select | QuestionKind::Multiselect => self.question.choices.clone(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// The answer in debconf's format.
+    pub fn value(&self) -> String {
+        match self.question.kind {
+            QuestionKind::Select => self.question.choices.get(self.selected).cloned().unwrap_or_default(),
+            QuestionKind::Multiselect => self
+                .question
+                .choices
+                .iter()
+                .zip(&self.checked)
+                .filter(|(_, checked)| **checked)
+                .map(|(choice, _)| choice.replace(',', "\\,"))
+                .collect::<Vec<_>>()
+                .join(", "),
+            QuestionKind::Boolean => (self.selected == 0).to_string(),
+            QuestionKind::String | QuestionKind::Password => self.input.clone(),
+            QuestionKind::Note | QuestionKind::Text | QuestionKind::Error => String::new(),
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct Notification {
     pub message: String,
     pub kind: NotificationKind,
+    pub remedy: Option<Remedy>, // Ação oferecida no Enter
+}
+
+/// A command that gets past a failure, offered right on its notification.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Remedy {
+    pub label: String,
+    pub command: BackendCommand,
+}
+
+impl Remedy {
+    /// Retry for a held lock, refresh for stale lists, a repair command
+    /// for a broken dpkg state; nothing lapt can do about missing root.
+    pub fn for_error(error: &AptError, failed: &BackendCommand) -> Option<Self> {
+        let (label, command) = match error {
+            AptError::LockHeld => ("Retry".to_string(), failed.clone()),
+            AptError::PackageNotFound(_) | AptError::HashSumMismatch => {
+                (BackendCommand::RefreshRepos.label(), BackendCommand::RefreshRepos)
+            }
+            AptError::PermissionDenied => return None,
+            _ => {
+                let repair = error.repair()?;
+                (repair.to_string(), BackendCommand::Repair(repair))
+            }
+        };
+        Some(Self { label, command })
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum NotificationKind {
     Error,
+    Warning,
+    Info,
 }
 
 impl Notification {
@@ -61,6 +195,28 @@ impl Notification {
         Self {
             message,
             kind: NotificationKind::Error,
+            remedy: None,
+        }
+    }
+
+    pub fn warning(message: String) -> Self {
+        Self {
+            message,
+            kind: NotificationKind::Warning,
+            remedy: None,
+        }
+    }
+
+    pub fn with_remedy(mut self, remedy: Option<Remedy>) -> Self {
+        self.remedy = remedy;
+        self
+    }
+
+    pub fn info(message: String) -> Self {
+        Self {
+            message,
+            kind: NotificationKind::Info,
+            remedy: None,
         }
     }
 }
@@ -74,12 +230,24 @@ pub struct App {
     pub installed_packages: Vec<Package>,
     pub upgradable_packages: Vec<Package>,
     pub online_packages: Vec<Package>,
+    pub orphan_packages: Vec<Package>, // Automáticos que o apt autoremove levaria
+    pub held_packages: Vec<Package>,   // Instalados com apt-mark hold
     pub filtered_packages: Vec<Package>,
 
     // UI State
     pub selected_tab: SelectedTab,
     pub active_panel: Panel,
     pub list_state: ListState,
+    pub selection: Vec<PackageId>,     // Pacotes marcados com espaço/visual
+    pub visual_anchor: Option<usize>,  // Início da seleção visual ('v')
+    pub details_view: DetailsView,
+    pub dependency_state: ListState,
+    pub jobs: Vec<Job>, // Fila de operações do backend
+    pub queue_state: ListState,
+    pub job_logs: HashMap<JobId, JobLog>, // Saída completa de cada job da fila
+    pub log_job: Option<JobId>,           // Job mostrado no painel de log
+    pub log_cursor: Option<usize>,        // Linha destacada; None acompanha o fim
+    pub log_query: String,
     pub popup: Popup,
     pub notification_queue: Vec<Notification>,
 
@@ -87,10 +255,31 @@ pub struct App {
     pub search_query: String,
     pub is_searching: bool,
     pub matcher: SkimMatcherV2,
+    pub pending_online_query: Option<String>, // Última busca online enviada
 
     // Loading
     pub is_loading: bool,
     pub loading_msg: String,
+    pub progress: Option<Progress>, // Último registro do APT::Status-Fd
+    pub lock_holder: Option<LockHolder>, // Quem segura o lock do dpkg enquanto esperamos
+
+    // Privilégios
+    pub privilege: Privilege,
+    pub authenticated: bool, // Senha do sudo aceita nesta sessão
+    pub password_prompt: Option<PasswordPrompt>,
+    pub pending_privileged: Option<BackendCommand>, // Espera a autenticação
+
+    // Pergunta do debconf feita no meio de um job
+    pub debconf_prompt: Option<DebconfPrompt>,
+
+    // Conffiles que o dpkg manteve (--force-confold), esperando revisão
+    pub conffiles: Vec<Conffile>,
+    pub conffile_state: ListState,
+    pub conffile_diff: Option<(std::path::PathBuf, String)>, // (leftover, diff -u)
+    pub conffile_scroll: u16,
+    pub conffile_unified: bool, // Diff -u em vez de lado a lado
+    conffiles_kept_both: Vec<std::path::PathBuf>, // Fora da lista até o lapt reiniciar
+    new_conffiles: usize, // Desde o último aviso
 
     // Canal para mandar coisas pro Backend
     pub tx_cmd: UnboundedSender<BackendCommand>,
@@ -104,22 +293,50 @@ impl App {
             installed_packages: vec![],
             upgradable_packages: vec![],
             online_packages: vec![],
+            orphan_packages: vec![],
+            held_packages: vec![],
             filtered_packages: vec![],
             selected_tab: SelectedTab::Installed,
             active_panel: Panel::PackageList,
             list_state: ListState::default(),
+            selection: Vec::new(),
+            visual_anchor: None,
+            details_view: DetailsView::Info,
+            dependency_state: ListState::default(),
+            jobs: Vec::new(),
+            queue_state: ListState::default(),
+            job_logs: HashMap::new(),
+            log_job: None,
+            log_cursor: None,
+            log_query: String::new(),
             popup: Popup::default(),
             notification_queue: Vec::new(),
             search_query: String::new(),
             is_searching: false,
             matcher: SkimMatcherV2::default(),
+            pending_online_query: None,
             is_loading: false,
             loading_msg: String::new(),
+            progress: None,
+            lock_holder: None,
+            privilege: Privilege::Root,
+            authenticated: false,
+            password_prompt: None,
+            pending_privileged: None,
+            debconf_prompt: None,
+            conffiles: Vec::new(),
+            conffile_state: ListState::default(),
+            conffile_diff: None,
+            conffile_scroll: 0,
+            conffile_unified: false,
+            conffiles_kept_both: Vec::new(),
+            new_conffiles: 0,
             tx_cmd,
         };
         // Inicializa buscando dados
         app.dispatch(BackendCommand::ListInstalled);
         app.dispatch(BackendCommand::ListUpgradable);
+        app.dispatch(BackendCommand::ListOrphans);
         app
     }
 
@@ -172,7 +389,133 @@ impl App {
                 )));
             }
 
+            Action::ToggleDependencies => {
+                self.details_view = match self.details_view {
+                    DetailsView::Dependencies => DetailsView::Info,
+                    _ => DetailsView::Dependencies,
+                };
+                self.dependency_state.select(Some(0));
+            }
+            Action::ToggleQueue => {
+                self.details_view = match self.details_view {
+                    DetailsView::Queue => DetailsView::Info,
+                    _ => DetailsView::Queue,
+                };
+                if self.queue_state.selected().is_none() {
+                    self.queue_state.select(Some(0));
+                }
+            }
+            Action::ToggleLog => {
+                if self.details_view == DetailsView::Log {
+                    self.details_view = DetailsView::Info;
+                } else {
+                    // O job escolhido na fila, senão o que está rodando ou o último
+                    let job = match self.details_view {
+                        DetailsView::Queue => self.selected_job(),
+                        _ => None,
+                    }
+                    .or_else(|| self.jobs.iter().find(|j| j.state == JobState::Running))
+                    .or(self.jobs.last());
+                    self.log_job = job.map(|j| j.id);
+                    self.log_cursor = None;
+                    self.details_view = DetailsView::Log;
+                }
+            }
+            Action::ToggleConffiles => {
+                if self.details_view == DetailsView::Conffiles {
+                    self.details_view = DetailsView::Info;
+                } else {
+                    self.details_view = DetailsView::Conffiles;
+                    if self.conffile_state.selected().is_none() {
+                        self.conffile_state.select(Some(0));
+                    }
+                    self.show_selected_conffile();
+                    // Também o que sobrou de antes do lapt
+                    self.dispatch(BackendCommand::ScanConffiles);
+                }
+            }
+            Action::AdoptConffile => self.resolve_selected_conffile(ConffileChoice::Adopt),
+            Action::DiscardConffile => self.resolve_selected_conffile(ConffileChoice::Discard),
+            Action::KeepBothConffiles if self.details_view == DetailsView::Conffiles => {
+                if let Some(conffile) = self.selected_conffile().cloned() {
+                    let leftover = conffile.leftover_path();
+                    self.push_notification(Notification::info(format!(
+                        "{} and {} both stay as they are",
+                        conffile.path.display(),
+                        leftover.display()
+                    )));
+                    self.forget_conffile(&leftover);
+                    self.conffiles_kept_both.push(leftover);
+                }
+            }
+            Action::ToggleConffileDiff => self.conffile_unified = !self.conffile_unified,
+            Action::NextLogMatch => {
+                if let Some(log) = self.shown_log() {
+                    let from = self.log_cursor.unwrap_or(log.len().saturating_sub(1));
+                    if let Some(i) = log.find_next(&self.log_query, from) {
+                        self.log_cursor = Some(i);
+                    }
+                }
+            }
+            Action::JumpToFirstError => {
+                if let Some(log) = self.shown_log() {
+                    match log.first_error() {
+                        Some(i) => self.log_cursor = Some(i),
+                        None => self.push_notification(Notification::info("No errors in this log".into())),
+                    }
+                }
+            }
+            // K/J rolam o diff na vista de conffiles
+            Action::MoveJobUp if self.details_view == DetailsView::Conffiles => {
+                self.conffile_scroll = self.conffile_scroll.saturating_sub(1);
+            }
+            Action::MoveJobDown if self.details_view == DetailsView::Conffiles => {
+                self.conffile_scroll = self.conffile_scroll.saturating_add(1);
+            }
+            Action::MoveJobUp => self.control_selected_job(BackendCommand::MoveJobUp),
+            Action::MoveJobDown => self.control_selected_job(BackendCommand::MoveJobDown),
+            Action::DropJob => self.control_selected_job(BackendCommand::DropJob),
+            Action::CancelJob => {
+                // Vai direto: o backend recusa se não houver o que cancelar
+                let _ = self.tx_cmd.send(BackendCommand::Cancel);
+            }
+            Action::OpenDependency => self.open_selected_dependency(),
+
+            // --- Seleção múltipla ---
+            Action::ToggleSelection => {
+                if let Some(pkg) = self.get_selected_pkg() {
+                    let id = pkg.id.clone();
+                    match self.selection.iter().position(|s| *s == id) {
+                        Some(i) => {
+                            self.selection.remove(i);
+                        }
+                        None => self.selection.push(id),
+                    }
+                    self.next_item();
+                }
+            }
+            Action::ToggleVisualSelection => {
+                if self.visual_anchor.is_some() {
+                    for id in self.visual_range_ids() {
+                        if !self.selection.contains(&id) {
+                            self.selection.push(id);
+                        }
+                    }
+                    self.visual_anchor = None;
+                } else {
+                    self.visual_anchor = self.list_state.selected();
+                }
+            }
+            Action::ClearSelection => {
+                self.selection.clear();
+                self.visual_anchor = None;
+            }
+
             // --- Busca ---
+            Action::EnterSearchMode if self.browsing_log() => {
+                self.is_searching = true;
+                self.log_query.clear();
+            }
             Action::EnterSearchMode => {
                 self.is_searching = true;
                 self.search_query.clear();
@@ -190,70 +533,134 @@ impl App {
             Action::TriggerOnlineSearch => {
                 let query = self.search_query.clone();
                 if !query.is_empty() {
+                    self.pending_online_query = Some(query.clone());
                     self.dispatch(BackendCommand::Search(query));
                 }
                 self.is_searching = false;
             }
+            Action::UpdateSearchQuery(c) if self.browsing_log() => {
+                self.log_query.push(c);
+                self.search_log();
+            }
+            Action::DeleteSearchChar if self.browsing_log() => {
+                self.log_query.pop();
+                self.search_log();
+            }
             Action::UpdateSearchQuery(c) => {
                 self.search_query.push(c);
                 self.perform_search();
+                self.search_online_as_you_type();
             }
             Action::DeleteSearchChar => {
                 self.search_query.pop();
                 self.perform_search();
+                self.search_online_as_you_type();
             }
 
             // --- Ações de Negócio (Popups) ---
-            Action::RequestInstall => {
-                if let Some(pkg) = self.get_selected_pkg() {
-                    self.open_popup(
-                        "Confirm Installation",
-                        &format!("Install {}?", pkg.name),
-                        Some(BackendCommand::Install(pkg.id.clone())),
-                    );
-                }
+            Action::RequestInstall => self.request(TransactionKind::Install),
+            Action::RequestUninstall => self.request(TransactionKind::Remove),
+            Action::RequestReinstall => self.request(TransactionKind::Reinstall),
+            Action::RequestPurge => self.request(TransactionKind::Purge),
+            Action::RequestUpgradeSystem => {
+                self.preview(BackendCommand::UpgradeSystem);
             }
-            Action::RequestUninstall => {
-                if let Some(pkg) = self.get_selected_pkg() {
-                    self.open_popup(
-                        "Confirm Uninstall",
-                        &format!("Remove {}?", pkg.name),
-                        Some(BackendCommand::Remove(pkg.id.clone())),
-                    );
-                }
+            Action::RequestAutoremove => {
+                self.preview(BackendCommand::Autoremove);
             }
-            Action::RequestReinstall => {
-                if let Some(pkg) = self.get_selected_pkg() {
-                    self.open_popup(
-                        "Confirm Reinstall",
-                        &format!("Reinstall {}?", pkg.name),
-                        Some(BackendCommand::Reinstall(pkg.id.clone())),
-                    );
-                }
-            }
-            Action::RequestUpgradeSystem => {
-                self.open_popup(
-                    "System Upgrade",
-                    "Update full system?",
-                    Some(BackendCommand::UpgradeSystem),
-                );
+            Action::MarkAuto => self.mark_selected(PackageMark::Auto),
+            Action::MarkManual => self.mark_selected(PackageMark::Manual),
+            Action::ToggleHold => {
+                let held = self.get_selected_pkg().is_some_and(|p| p.status.held);
+                self.mark_selected(if held { PackageMark::Unhold } else { PackageMark::Hold });
             }
             Action::RefreshRepos => {
-                self.dispatch(BackendCommand::RefreshRepos);
+                self.dispatch_privileged(BackendCommand::RefreshRepos);
             }
             Action::ConfirmAction => {
-                if let Some(cmd) = self.popup.command_to_confirm.take() {
-                    self.dispatch(cmd);
+                let cmd = self.popup.command_to_confirm.take();
+                self.close_popup();
+                if let Some(cmd) = cmd {
+                    self.dispatch_privileged(cmd);
                 }
-                self.popup.visible = false;
             }
-            Action::CancelAction => {
-                self.popup.visible = false;
-                self.popup.command_to_confirm = None;
+            Action::CancelAction => self.close_popup(),
+            Action::ScrollPopupDown => {
+                self.popup.scroll = self.popup.scroll.saturating_add(1);
+            }
+            Action::ScrollPopupUp => {
+                self.popup.scroll = self.popup.scroll.saturating_sub(1);
             }
             Action::DismissNotification => {
                 self.dismiss_notification();
             }
+            Action::ApplyRemedy => {
+                if let Some(remedy) = self.notification_queue.first().and_then(|n| n.remedy.clone()) {
+                    self.dismiss_notification();
+                    self.dispatch_privileged(remedy.command);
+                }
+            }
+
+            // --- Senha do sudo ---
+            Action::PasswordInput(c) => {
+                if let Some(prompt) = self.password_prompt.as_mut() {
+                    prompt.input.push(c);
+                }
+            }
+            Action::PasswordBackspace => {
+                if let Some(prompt) = self.password_prompt.as_mut() {
+                    prompt.input.pop();
+                }
+            }
+            Action::SubmitPassword => {
+                if let Some(prompt) = self.password_prompt.take() {
+                    // Sem dispatch(): a tarefa pendente só sai depois do Authenticated
+                    let _ = self.tx_cmd.send(BackendCommand::Authenticate(Password::new(prompt.input)));
+                    self.is_loading = true;
+                    self.loading_msg = "Authenticating...".into();
+                }
+            }
+            Action::CancelPassword => {
+                self.password_prompt = None;
+                self.pending_privileged = None;
+            }
+
+            action @ (Action::DebconfNext | Action::DebconfPrev) => {
+                if let Some(prompt) = self.debconf_prompt.as_mut() {
+                    let len = prompt.options().len();
+                    if len > 0 {
+                        prompt.selected = if matches!(action, Action::DebconfNext) {
+                            (prompt.selected + 1) % len
+                        } else {
+                            (prompt.selected + len - 1) % len
+                        };
+                    }
+                }
+            }
+            Action::DebconfToggle => {
+                if let Some(prompt) = self.debconf_prompt.as_mut()
+                    && let Some(checked) = prompt.checked.get_mut(prompt.selected)
+                {
+                    *checked = !*checked;
+                }
+            }
+            Action::DebconfInput(c) => {
+                if let Some(prompt) = self.debconf_prompt.as_mut() {
+                    prompt.input.push(c);
+                }
+            }
+            Action::DebconfBackspace => {
+                if let Some(prompt) = self.debconf_prompt.as_mut() {
+                    prompt.input.pop();
+                }
+            }
+            action @ (Action::DebconfSubmit | Action::DebconfBack) => {
+                if let Some(prompt) = self.debconf_prompt.take() {
+                    let value = matches!(action, Action::DebconfSubmit).then(|| prompt.value());
+                    // Sem dispatch(): o job continua rodando, sem loading novo
+                    let _ = self.tx_cmd.send(BackendCommand::AnswerDebconf(prompt.id, value));
+                }
+            }
 
             Action::BackendResponse(event) => self.handle_backend_event(event),
 
@@ -268,74 +675,220 @@ impl App {
                 self.is_loading = true;
                 self.loading_msg = msg;
             }
+            BackendEvent::Progress(progress) => {
+                // Se o apt já mostra progresso, ele pegou o lock
+                self.is_loading = true;
+                self.lock_holder = None;
+                self.progress = Some(progress);
+            }
+            BackendEvent::WaitingForLock(holder) => {
+                let message = format!(
+                    "The dpkg lock is held by {}.\nlapt will wait for it to finish.",
+                    holder.describe()
+                );
+                let cancel = Remedy {
+                    label: "Cancel".to_string(),
+                    command: BackendCommand::Cancel,
+                };
+                self.push_notification(Notification::warning(message).with_remedy(Some(cancel)));
+                self.lock_holder = Some(holder);
+            }
+            BackendEvent::Output(line) => {
+                // A fila roda um job por vez: a saída é do que está rodando
+                if let Some(job) = self.jobs.iter().find(|j| j.state == JobState::Running) {
+                    self.job_logs.entry(job.id).or_default().push(line);
+                }
+            }
             BackendEvent::InstalledPackagesFound(pkgs) => {
                 self.installed_packages = pkgs;
-                if self.selected_tab == SelectedTab::Installed {
+                self.sync_held();
+                if matches!(self.selected_tab, SelectedTab::Installed | SelectedTab::Held) {
                     self.perform_search();
                 }
             }
             BackendEvent::UpgradablePackagesFound(pkgs) => {
                 self.upgradable_packages = pkgs;
+                self.sync_held();
                 if self.selected_tab == SelectedTab::Upgradable {
                     self.perform_search();
                 }
             }
-            BackendEvent::SearchResultsFound(pkgs) => {
+            BackendEvent::OrphanPackagesFound(pkgs) => {
+                self.orphan_packages = pkgs;
+                if self.selected_tab == SelectedTab::Orphans {
+                    self.perform_search();
+                }
+            }
+            BackendEvent::SearchResultsFound(query, pkgs) => {
+                // Results of a query the user has already typed past
+                if self.pending_online_query.as_ref().is_some_and(|q| *q != query) {
+                    return;
+                }
                 self.online_packages = pkgs;
+                self.mark_installed_online_packages();
                 if self.selected_tab == SelectedTab::Online {
                     self.perform_search();
                 }
             }
-            BackendEvent::PackageDetailsFound(details) => {
-                // Update in all lists
-                for p in self.installed_packages.iter_mut() {
-                    if p.id == details.id {
-                        p.update_details(
-                            &details.description,
-                            &details.license,
-                            details.size,
-                            &details.url,
-                        );
+            BackendEvent::SimulationFound(cmd, simulation) => {
+                let (title, mut question) = confirmation_text(&cmd);
+                if *cmd == BackendCommand::UpgradeSystem {
+                    let held: Vec<&str> = self
+                        .upgradable_packages
+                        .iter()
+                        .filter(|p| p.status.held)
+                        .map(|p| p.id.name.as_str())
+                        .collect();
+                    if !held.is_empty() {
+                        question.push_str(&format!(" Held back: {}", held.join(", ")));
                     }
                 }
-                for p in self.upgradable_packages.iter_mut() {
-                    if p.id == details.id {
-                        p.update_details(
-                            &details.description,
-                            &details.license,
-                            details.size,
-                            &details.url,
-                        );
+                self.open_popup(title, &question, Some(*cmd));
+                self.popup.simulation = Some(*simulation);
+            }
+            BackendEvent::QueueUpdated(jobs) => {
+                // A seleção acompanha o job, não a posição
+                let selected = self.selected_job().map(|j| j.id);
+                self.jobs = jobs;
+                let i = selected
+                    .and_then(|id| self.jobs.iter().position(|j| j.id == id))
+                    .unwrap_or(0);
+                self.queue_state.select(Some(i.min(self.jobs.len().saturating_sub(1))));
+
+                // Logs vão embora junto com o histórico da fila
+                let ids: Vec<JobId> = self.jobs.iter().map(|j| j.id).collect();
+                self.job_logs.retain(|id, _| ids.contains(id));
+                if self.log_job().is_none() {
+                    self.log_job = self.jobs.iter().find(|j| j.state == JobState::Running).map(|j| j.id);
+                    self.log_cursor = None;
+                }
+            }
+            BackendEvent::PackageMarked(id, mark) => {
+                let lists = [
+                    &mut self.installed_packages,
+                    &mut self.upgradable_packages,
+                    &mut self.online_packages,
+                    &mut self.orphan_packages,
+                    &mut self.filtered_packages,
+                ];
+                for p in lists.into_iter().flatten() {
+                    if p.id.name == id.name && p.id.arch == id.arch {
+                        mark.apply(&mut p.status);
                     }
                 }
-                for p in self.filtered_packages.iter_mut() {
-                    if p.id == details.id {
-                        p.update_details(
-                            &details.description,
-                            &details.license,
-                            details.size,
-                            &details.url,
-                        );
+                match mark {
+                    PackageMark::Hold | PackageMark::Unhold => {
+                        self.sync_held();
+                        if self.selected_tab == SelectedTab::Held {
+                            self.perform_search();
+                        }
+                    }
+                    // The autoremove set depends on the auto/manual flags
+                    PackageMark::Auto | PackageMark::Manual => {
+                        self.dispatch(BackendCommand::ListOrphans);
                     }
                 }
             }
+            BackendEvent::PackageDetailsFound(details) => self.apply_details(&details),
             BackendEvent::TaskFinished(cmd) => {
                 self.is_loading = false;
+                self.progress = None;
+                self.lock_holder = None;
+                if cmd.is_mutating() {
+                    // Uma pergunta sem resposta morreu com o job
+                    self.debconf_prompt = None;
+                }
+                if let BackendCommand::Apply(_) = cmd {
+                    self.selection.clear();
+                }
+                if cmd.is_mutating() && self.new_conffiles > 0 {
+                    let message = format!(
+                        "{} config file(s) you changed got a new version from the maintainer.\n\
+                         Yours were kept; press C to compare and choose.",
+                        std::mem::take(&mut self.new_conffiles)
+                    );
+                    self.push_notification(Notification::info(message));
+                }
+                if let BackendCommand::ResolveConffile(path, _) = &cmd {
+                    self.forget_conffile(path);
+                    // Adotar um .dpkg-old deixa um .dpkg-dist no lugar
+                    self.dispatch(BackendCommand::ScanConffiles);
+                }
                 match cmd {
                     BackendCommand::Install(_)
                     | BackendCommand::Remove(_)
                     | BackendCommand::Reinstall(_)
-                    | BackendCommand::UpgradeSystem => {
+                    | BackendCommand::Apply(_)
+                    | BackendCommand::UpgradeSystem
+                    | BackendCommand::Autoremove
+                    | BackendCommand::Repair(_) => {
                         self.dispatch(BackendCommand::ListInstalled);
                         self.dispatch(BackendCommand::ListUpgradable);
+                        self.dispatch(BackendCommand::ListOrphans);
                     }
                     _ => {}
                 }
             }
+            BackendEvent::Cancelled(cmd) => {
+                self.push_notification(Notification::info(format!("{} was cancelled", cmd.label())));
+            }
+            BackendEvent::CancelRefused(reason) => {
+                self.push_notification(Notification::warning(reason));
+            }
+            BackendEvent::Authenticated => {
+                self.is_loading = false;
+                self.authenticated = true;
+                if let Some(cmd) = self.pending_privileged.take() {
+                    self.dispatch(cmd);
+                }
+            }
+            BackendEvent::AuthenticationFailed(reason) => {
+                self.is_loading = false;
+                self.password_prompt = Some(PasswordPrompt {
+                    input: String::new(),
+                    error: Some(reason),
+                });
+            }
+            BackendEvent::ConffileKept(conffile) => {
+                if !self.conffiles.contains(&conffile) {
+                    self.conffiles.push(conffile);
+                    self.new_conffiles += 1;
+                }
+            }
+            BackendEvent::ConffilesFound(found) => {
+                self.conffiles = found
+                    .into_iter()
+                    .filter(|c| !self.conffiles_kept_both.contains(&c.leftover_path()))
+                    .collect();
+                let len = self.conffiles.len();
+                self.conffile_state
+                    .select((len > 0).then(|| self.conffile_state.selected().unwrap_or(0).min(len - 1)));
+                if self.details_view == DetailsView::Conffiles {
+                    self.show_selected_conffile();
+                }
+            }
+            BackendEvent::ConffileDiff(path, diff) => {
+                self.conffile_scroll = 0;
+                self.conffile_diff = Some((path, diff));
+            }
+            BackendEvent::DebconfQuestion(id, question) => {
+                self.debconf_prompt = Some(DebconfPrompt::new(id, question));
+            }
+            BackendEvent::Warning(message) => {
+                self.push_notification(Notification::warning(message));
+            }
             BackendEvent::Error(err) => {
                 self.is_loading = false;
+                self.progress = None;
                 self.push_notification(Notification::error(err));
             }
+            BackendEvent::Failed(cmd, error, message) => {
+                self.is_loading = false;
+                self.progress = None;
+                let message = format!("{}\n\n{}", message, error.suggestion());
+                let remedy = Remedy::for_error(&error, &cmd);
+                self.push_notification(Notification::error(message).with_remedy(remedy));
+            }
         }
     }
 
@@ -344,26 +897,211 @@ impl App {
             SelectedTab::Installed => &self.installed_packages,
             SelectedTab::Upgradable => &self.upgradable_packages,
             SelectedTab::Online => &self.online_packages,
+            SelectedTab::Orphans => &self.orphan_packages,
+            SelectedTab::Held => &self.held_packages,
         };
-        if self.search_query.is_empty() {
+        // Online results are already matched and ranked by the backend index
+        if self.search_query.is_empty() || self.selected_tab == SelectedTab::Online {
             self.filtered_packages = source.clone();
         } else {
             let mut matches: Vec<(&Package, i64)> = source
                 .iter()
                 .filter_map(|pkg| {
                     self.matcher
-                        .fuzzy_match(&pkg.name, &self.search_query)
+                        .fuzzy_match(&pkg.id.name, &self.search_query)
                         .map(|score| (pkg, score))
                 })
                 .collect();
-            matches.sort_by(|a, b| b.1.cmp(&a.1));
+            matches.sort_by_key(|m| std::cmp::Reverse(m.1));
             self.filtered_packages = matches.into_iter().map(|(p, _)| p.clone()).collect();
         }
         self.list_state.select(Some(0));
-        self.request_details_for_selected();
+        self.visual_anchor = None; // Índices da faixa visual não valem mais
+        self.on_selection_changed();
+    }
+
+    /// Flags Online hits that are already installed, or newer than the
+    /// installed version, so they read like the other tabs.
+    fn mark_installed_online_packages(&mut self) {
+        for pkg in self.online_packages.iter_mut() {
+            let installed = self
+                .installed_packages
+                .iter()
+                .find(|p| p.id.name == pkg.id.name && p.id.arch == pkg.id.arch);
+            if let Some(installed) = installed
+                && pkg.id.version >= installed.id.version
+            {
+                pkg.status = PackageStatus {
+                    upgradable_from: (pkg.id.version > installed.id.version)
+                        .then(|| installed.id.version.clone()),
+                    ..installed.status.clone()
+                };
+            }
+        }
+    }
+
+    /// Holds live in dpkg's status file, which `apt list --upgradable` does
+    /// not report, so they are copied over from the installed packages.
+    fn sync_held(&mut self) {
+        for pkg in self.upgradable_packages.iter_mut() {
+            pkg.status.held = self
+                .installed_packages
+                .iter()
+                .any(|p| p.id.name == pkg.id.name && p.id.arch == pkg.id.arch && p.status.held);
+        }
+        self.held_packages = self
+            .installed_packages
+            .iter()
+            .filter(|p| p.status.held)
+            .cloned()
+            .collect();
+    }
+
+    /// Queries the backend index on every keystroke while searching the
+    /// Online tab, without the loading overlay getting in the way of typing.
+    fn search_online_as_you_type(&mut self) {
+        if self.selected_tab != SelectedTab::Online || self.search_query.chars().count() < 2 {
+            return;
+        }
+        self.pending_online_query = Some(self.search_query.clone());
+        let _ = self.tx_cmd.send(BackendCommand::Search(self.search_query.clone()));
+    }
+
+    fn browsing_dependencies(&self) -> bool {
+        self.active_panel == Panel::Details && self.details_view == DetailsView::Dependencies
+    }
+
+    /// Fills in the details fetched for one package in every list showing it.
+    fn apply_details(&mut self, details: &Package) {
+        let lists = [
+            &mut self.installed_packages,
+            &mut self.upgradable_packages,
+            &mut self.online_packages,
+            &mut self.orphan_packages,
+            &mut self.filtered_packages,
+        ];
+        for p in lists.into_iter().flatten() {
+            if p.id == details.id {
+                p.update_details(&details.description, &details.license, details.size, &details.url);
+                if p.relations.is_empty() {
+                    p.relations = details.relations.clone();
+                }
+            }
+        }
+    }
+
+    fn browsing_conffiles(&self) -> bool {
+        self.active_panel == Panel::Details && self.details_view == DetailsView::Conffiles
+    }
+
+    pub fn selected_conffile(&self) -> Option<&Conffile> {
+        self.conffile_state.selected().and_then(|i| self.conffiles.get(i))
+    }
+
+    /// Asks for the diff of the selected conffile unless it is already shown.
+    fn show_selected_conffile(&mut self) {
+        let Some(path) = self.selected_conffile().map(Conffile::leftover_path) else {
+            self.conffile_diff = None;
+            return;
+        };
+        if self.conffile_diff.as_ref().is_none_or(|(shown, _)| *shown != path) {
+            self.dispatch(BackendCommand::DiffConffile(path));
+        }
+    }
+
+    fn resolve_selected_conffile(&mut self, choice: ConffileChoice) {
+        if self.details_view != DetailsView::Conffiles {
+            return;
+        }
+        if let Some(conffile) = self.selected_conffile() {
+            let cmd = BackendCommand::ResolveConffile(conffile.leftover_path(), choice);
+            self.dispatch_privileged(cmd);
+        }
+    }
+
+    /// Drops the entry for the leftover at `path`.
+    fn forget_conffile(&mut self, path: &std::path::Path) {
+        self.conffiles.retain(|c| c.leftover_path() != path);
+        if self.conffile_diff.as_ref().is_some_and(|(shown, _)| shown == path) {
+            self.conffile_diff = None;
+        }
+        let len = self.conffiles.len();
+        self.conffile_state.select((len > 0).then(|| self.conffile_state.selected().unwrap_or(0).min(len - 1)));
+        if self.details_view == DetailsView::Conffiles {
+            self.show_selected_conffile();
+        }
+    }
+
+    fn browsing_queue(&self) -> bool {
+        self.active_panel == Panel::Details && self.details_view == DetailsView::Queue
+    }
+
+    pub fn browsing_log(&self) -> bool {
+        self.active_panel == Panel::Details && self.details_view == DetailsView::Log
+    }
+
+    /// The job whose output the log pane shows.
+    pub fn log_job(&self) -> Option<&Job> {
+        self.log_job.and_then(|id| self.jobs.iter().find(|j| j.id == id))
+    }
+
+    pub fn shown_log(&self) -> Option<&JobLog> {
+        self.log_job.and_then(|id| self.job_logs.get(&id))
+    }
+
+    /// Incremental search: moves to the first match at or after the cursor.
+    fn search_log(&mut self) {
+        if let Some(log) = self.shown_log()
+            && !log.is_empty()
+        {
+            let cursor = self.log_cursor.unwrap_or(0);
+            let from = (cursor + log.len() - 1) % log.len();
+            if let Some(i) = log.find_next(&self.log_query, from) {
+                self.log_cursor = Some(i);
+            }
+        }
+    }
+
+    fn scroll_log(&mut self, down: bool) {
+        let Some(len) = self.shown_log().map(JobLog::len).filter(|len| *len > 0) else {
+            return;
+        };
+        let cursor = self.log_cursor.unwrap_or(len - 1);
+        self.log_cursor = Some(if down {
+            (cursor + 1).min(len - 1)
+        } else {
+            cursor.saturating_sub(1)
+        });
     }
 
     fn next_item(&mut self) {
+        if self.browsing_log() {
+            self.scroll_log(true);
+            return;
+        }
+        if self.browsing_conffiles() {
+            if !self.conffiles.is_empty() {
+                let i = self.conffile_state.selected().map_or(0, |i| (i + 1) % self.conffiles.len());
+                self.conffile_state.select(Some(i));
+                self.show_selected_conffile();
+            }
+            return;
+        }
+        if self.browsing_queue() {
+            if !self.jobs.is_empty() {
+                let i = self.queue_state.selected().map_or(0, |i| (i + 1) % self.jobs.len());
+                self.queue_state.select(Some(i));
+            }
+            return;
+        }
+        if self.browsing_dependencies() {
+            let len = self.dependency_entries().len();
+            if len > 0 {
+                let i = self.dependency_state.selected().map_or(0, |i| (i + 1) % len);
+                self.dependency_state.select(Some(i));
+            }
+            return;
+        }
         if self.filtered_packages.is_empty() {
             return;
         }
@@ -378,10 +1116,39 @@ impl App {
             None => 0,
         };
         self.list_state.select(Some(i));
-        self.request_details_for_selected();
+        self.on_selection_changed();
     }
 
     fn prev_item(&mut self) {
+        if self.browsing_log() {
+            self.scroll_log(false);
+            return;
+        }
+        if self.browsing_conffiles() {
+            let len = self.conffiles.len();
+            if len > 0 {
+                let i = self.conffile_state.selected().map_or(0, |i| (i + len - 1) % len);
+                self.conffile_state.select(Some(i));
+                self.show_selected_conffile();
+            }
+            return;
+        }
+        if self.browsing_queue() {
+            let len = self.jobs.len();
+            if len > 0 {
+                let i = self.queue_state.selected().map_or(0, |i| (i + len - 1) % len);
+                self.queue_state.select(Some(i));
+            }
+            return;
+        }
+        if self.browsing_dependencies() {
+            let len = self.dependency_entries().len();
+            if len > 0 {
+                let i = self.dependency_state.selected().map_or(0, |i| (i + len - 1) % len);
+                self.dependency_state.select(Some(i));
+            }
+            return;
+        }
         if self.filtered_packages.is_empty() {
             return;
         }
@@ -396,11 +1163,129 @@ impl App {
             None => 0,
         };
         self.list_state.select(Some(i));
+        self.on_selection_changed();
+    }
+
+    /// Relationship fields of the selected package followed by the installed
+    /// packages that depend on or recommend it (or something it provides).
+    pub fn dependency_entries(&self) -> Vec<DependencyEntry> {
+        let Some(pkg) = self.get_selected_pkg() else {
+            return Vec::new();
+        };
+        let is_satisfied = |dep: &Dependency| {
+            self.installed_packages
+                .iter()
+                .any(|p| {
+                    (p.id.name == dep.name && dep.accepts(&p.id.version))
+                        || (p.id.name != dep.name && p.provides(&dep.name))
+                })
+        };
+
+        let mut entries: Vec<DependencyEntry> = pkg
+            .relations
+            .iter()
+            .map(|relation| DependencyEntry {
+                installed: relation.alternatives.iter().any(is_satisfied),
+                relation: relation.clone(),
+            })
+            .collect();
+
+        let provided: Vec<&str> = pkg
+            .relations
+            .iter()
+            .filter(|r| r.kind == RelationKind::Provides)
+            .flat_map(|r| r.names())
+            .chain(std::iter::once(pkg.id.name.as_str()))
+            .collect();
+        for other in &self.installed_packages {
+            if other.id.name == pkg.id.name {
+                continue;
+            }
+            // Depends vence Recommends quando o outro pacote declara os dois
+            let kinds: Vec<RelationKind> = other
+                .relations
+                .iter()
+                .filter(|r| r.kind.is_strong() && r.names().any(|n| provided.contains(&n)))
+                .map(|r| r.kind)
+                .collect();
+            let kind = if kinds.iter().any(|k| *k != RelationKind::Recommends) {
+                RelationKind::ReverseDepends
+            } else if !kinds.is_empty() {
+                RelationKind::ReverseRecommends
+            } else {
+                continue;
+            };
+            entries.push(DependencyEntry {
+                relation: Relation {
+                    kind,
+                    alternatives: vec![Dependency {
+                        name: other.id.name.clone(),
+                        arch: None,
+                        constraint: None,
+                    }],
+                },
+                installed: true,
+            });
+        }
+        entries
+    }
+
+    /// "Why is this installed?" for the selected Installed/Orphans entry.
+    pub fn why_installed(&self) -> Option<WhyInstalled> {
+        if !matches!(self.selected_tab, SelectedTab::Installed | SelectedTab::Orphans) {
+            return None;
+        }
+        let pkg = self.get_selected_pkg()?;
+        Some(crate::deps::why_installed(&pkg.id.name, &self.installed_packages))
+    }
+
+    /// Jumps to the package named by the selected dependency: the installed
+    /// one (or installed provider) if any, otherwise an Online search for it.
+    fn open_selected_dependency(&mut self) {
+        if self.details_view != DetailsView::Dependencies {
+            return;
+        }
+        let entries = self.dependency_entries();
+        let Some(entry) = self.dependency_state.selected().and_then(|i| entries.get(i)) else {
+            return;
+        };
+        let names: Vec<&str> = entry.relation.names().collect();
+        let installed = names.iter().find_map(|name| {
+            self.installed_packages.iter().position(|p| p.provides(name))
+        });
+
+        self.is_searching = false;
+        self.search_query.clear();
+        match installed {
+            Some(i) => {
+                self.selected_tab = SelectedTab::Installed;
+                self.perform_search();
+                self.list_state.select(Some(i));
+                self.on_selection_changed();
+            }
+            None => {
+                let name = names.first().map(|n| n.to_string()).unwrap_or_default();
+                self.selected_tab = SelectedTab::Online;
+                self.online_packages.clear();
+                self.perform_search();
+                self.pending_online_query = Some(name.clone());
+                self.dispatch(BackendCommand::Search(name));
+            }
+        }
+        self.dependency_state.select(Some(0));
+        self.trigger_tab_effect();
+    }
+
+    fn on_selection_changed(&mut self) {
+        self.dependency_state.select(Some(0));
         self.request_details_for_selected();
     }
 
     fn request_details_for_selected(&mut self) {
-        if let Some(pkg) = self.get_selected_pkg() {
+        // Packages read from the dpkg database already carry their details.
+        if let Some(pkg) = self.get_selected_pkg()
+            && pkg.description.is_empty()
+        {
             let _ = self.tx_cmd.send(BackendCommand::GetDetails(pkg.id.clone()));
         }
     }
@@ -411,15 +1296,146 @@ impl App {
             .and_then(|i| self.filtered_packages.get(i))
     }
 
+    fn mark_selected(&mut self, mark: PackageMark) {
+        if let Some(pkg) = self.get_selected_pkg()
+            && pkg.status.is_installed()
+        {
+            self.dispatch_privileged(BackendCommand::Mark(pkg.id.clone(), mark));
+        }
+    }
+
+    /// Sends a command that may need root: read-only mode refuses it, and
+    /// with sudo the first one waits for the password.
+    fn dispatch_privileged(&mut self, cmd: BackendCommand) {
+        if !cmd.is_mutating() {
+            self.dispatch(cmd);
+        } else if !self.privilege.can_modify() {
+            self.push_notification(Notification::warning(format!(
+                "lapt is read-only: it is not running as root and found neither sudo nor pkexec.\n\n{}",
+                AptError::PermissionDenied.suggestion()
+            )));
+        } else if self.privilege.needs_password() && !self.authenticated {
+            self.pending_privileged = Some(cmd);
+            self.password_prompt = Some(PasswordPrompt::default());
+        } else {
+            self.dispatch(cmd);
+        }
+    }
+
+    pub fn selected_job(&self) -> Option<&Job> {
+        self.queue_state.selected().and_then(|i| self.jobs.get(i))
+    }
+
+    /// Sends a queue control command for the selected job; only jobs that
+    /// have not started can be moved or dropped.
+    fn control_selected_job(&mut self, control: fn(JobId) -> BackendCommand) {
+        if self.details_view != DetailsView::Queue {
+            return;
+        }
+        if let Some(job) = self.selected_job()
+            && job.state == JobState::Pending
+        {
+            // Sem dispatch(): mexer na fila não é uma tarefa com loading
+            let _ = self.tx_cmd.send(control(job.id));
+        }
+    }
+
+    /// Rows between the visual anchor and the cursor, inclusive.
+    pub fn visual_range(&self) -> Option<std::ops::RangeInclusive<usize>> {
+        let anchor = self.visual_anchor?;
+        let cursor = self.list_state.selected()?;
+        Some(anchor.min(cursor)..=anchor.max(cursor))
+    }
+
+    fn visual_range_ids(&self) -> Vec<PackageId> {
+        self.visual_range()
+            .map(|range| {
+                self.filtered_packages
+                    .iter()
+                    .skip(*range.start())
+                    .take(range.end() - range.start() + 1)
+                    .map(|p| p.id.clone())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Whether row `index` of the list is part of the selection, either
+    /// toggled with space or inside the active visual range.
+    pub fn is_selected(&self, index: usize) -> bool {
+        self.visual_range().is_some_and(|range| range.contains(&index))
+            || self
+                .filtered_packages
+                .get(index)
+                .is_some_and(|p| self.selection.contains(&p.id))
+    }
+
+    /// Previews `kind` for the selection as one transaction, or for the
+    /// package under the cursor when nothing is selected.
+    fn request(&mut self, kind: TransactionKind) {
+        let mut packages = self.selection.clone();
+        for id in self.visual_range_ids() {
+            if !packages.contains(&id) {
+                packages.push(id);
+            }
+        }
+        if !packages.is_empty() {
+            self.visual_anchor = None;
+            self.preview(BackendCommand::Apply(Transaction::new(kind, packages)));
+            return;
+        }
+
+        let Some(id) = self.get_selected_pkg().map(|p| p.id.clone()) else {
+            return;
+        };
+        let cmd = match kind {
+            TransactionKind::Install => BackendCommand::Install(id),
+            TransactionKind::Remove => BackendCommand::Remove(id),
+            TransactionKind::Reinstall => BackendCommand::Reinstall(id),
+            TransactionKind::Purge => BackendCommand::Apply(Transaction::new(kind, vec![id])),
+        };
+        self.preview(cmd);
+    }
+
+    /// Asks the backend for an `apt-get -s` dry run of `cmd`; the
+    /// confirmation popup opens once the simulation comes back.
+    fn preview(&mut self, cmd: BackendCommand) {
+        self.dispatch(BackendCommand::Simulate(Box::new(cmd)));
+    }
+
     fn open_popup(&mut self, title: &str, msg: &str, cmd: Option<BackendCommand>) {
         self.popup.visible = true;
         self.popup.title = title.into();
         self.popup.message = msg.into();
         self.popup.command_to_confirm = cmd;
+        self.popup.simulation = None;
+        self.popup.scroll = 0;
+    }
+
+    fn close_popup(&mut self) {
+        self.popup.visible = false;
+        self.popup.command_to_confirm = None;
+        self.popup.simulation = None;
     }
 
     fn trigger_tab_effect(&mut self) {
         self.effects
             .add_effect(fx::coalesce(FxDuration::from_millis(300)));
     }
-}
\ No newline at end of file
+}
+
+/// Popup title and question for a command awaiting confirmation.
+fn confirmation_text(cmd: &BackendCommand) -> (&'static str, String) {
+    match cmd {
+        BackendCommand::Install(id) => ("Confirm Installation", format!("Install {}?", id.name)),
+        BackendCommand::Remove(id) => ("Confirm Uninstall", format!("Remove {}?", id.name)),
+        BackendCommand::Reinstall(id) => ("Confirm Reinstall", format!("Reinstall {}?", id.name)),
+        BackendCommand::UpgradeSystem => ("System Upgrade", "Update full system?".to_string()),
+        BackendCommand::Autoremove => (
+            "Confirm Autoremove",
+            "Remove packages that are no longer needed?".to_string(),
+        ),
+        BackendCommand::Apply(transaction) => ("Confirm Transaction", format!("{}?", transaction.summary())),
+        _ => ("Confirm", "Proceed?".to_string()),
+    }
+}
@@ -1,7 +1,12 @@
-use crate::pkg::Package;
-use anyhow::Result;
-use std::process::{Command, Stdio};
-use tokio::process::Command as TokioCommand;
+use crate::deps::Relation;
+use crate::pkg::{Package, PackageId, PackageMark, PackageState, PackageStatus};
+use crate::runner::{CommandRunner, CommandSpec, RunningCommand};
+use crate::simulation::Simulation;
+use crate::transaction::{Transaction, TransactionKind};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use strum::Display;
 
 #[derive(Debug, Default, PartialEq)]
 pub struct AptDetails {
@@ -9,136 +14,297 @@ pub struct AptDetails {
     pub license: String,
     pub size: u64,
     pub url: String,
+    pub relations: Vec<Relation>,
 }
 
-pub fn get_package_details(package_name: &str) -> Result<AptDetails> {
-    let output = Command::new("apt")
-        .args(&["show", package_name])
-        .output()?;
+/// Runs a query command and returns its stdout, failing on a non-zero exit.
+fn run_query(runner: &dyn CommandRunner, spec: CommandSpec) -> Result<String> {
+    let output = runner.output(&spec)?;
+    if !output.success() {
+        anyhow::bail!("`{}` failed: {}", spec.argv().join(" "), output.stderr.trim());
+    }
+    Ok(output.stdout)
+}
 
-    let stdout = String::from_utf8(output.stdout)?;
+pub fn get_package_details(runner: &dyn CommandRunner, id: &PackageId) -> Result<AptDetails> {
+    let stdout = run_query(runner, CommandSpec::new("apt").args(["show", &id.apt_target()]))?;
     Ok(parse_apt_show(&stdout))
 }
 
-pub fn list_installed() -> Result<Vec<Package>> {
-    let output = Command::new("apt")
-        .args(&["list", "--installed"])
-        .output()?;
+/// Locations of the on-disk package databases lapt reads directly.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AptPaths {
+    pub dpkg_status: PathBuf,
+    pub extended_states: PathBuf,
+    pub lists_dir: PathBuf,
+    pub dpkg_lock: PathBuf, // lock-frontend, tomado pelo apt antes do dpkg
+    pub proc_dir: PathBuf,
+}
 
-    let stdout = String::from_utf8(output.stdout)?;
-    Ok(parse_apt_list(&stdout, "Installed"))
+impl Default for AptPaths {
+    fn default() -> Self {
+        Self {
+            dpkg_status: PathBuf::from("/var/lib/dpkg/status"),
+            extended_states: PathBuf::from("/var/lib/apt/extended_states"),
+            lists_dir: PathBuf::from("/var/lib/apt/lists"),
+            dpkg_lock: PathBuf::from("/var/lib/dpkg/lock-frontend"),
+            proc_dir: PathBuf::from("/proc"),
+        }
+    }
 }
 
-pub fn list_upgradable() -> Result<Vec<Package>> {
-    let output = Command::new("apt")
-        .args(&["list", "--upgradable"])
-        .output()?;
+pub fn list_installed(paths: &AptPaths) -> Result<Vec<Package>> {
+    let status = std::fs::read_to_string(&paths.dpkg_status)
+        .with_context(|| format!("Failed to read {}", paths.dpkg_status.display()))?;
+    // A fresh system may not have extended_states yet: nothing is auto then.
+    let extended_states = std::fs::read_to_string(&paths.extended_states).unwrap_or_default();
 
-    let stdout = String::from_utf8(output.stdout)?;
-    Ok(parse_apt_list(&stdout, "Update"))
+    let auto_installed = crate::dpkg::parse_extended_states(&extended_states);
+    Ok(crate::dpkg::parse_status(&status, &auto_installed))
 }
 
-pub fn search_packages(query: &str) -> Result<Vec<Package>> {
-    let output = Command::new("apt")
-        .args(&["search", query])
-        .output()?;
+pub fn list_upgradable(runner: &dyn CommandRunner) -> Result<Vec<Package>> {
+    let stdout = run_query(runner, CommandSpec::new("apt").args(["list", "--upgradable"]))?;
+    Ok(parse_apt_list(&stdout))
+}
 
-    let stdout = String::from_utf8(output.stdout)?;
-    Ok(parse_apt_search(&stdout))
+pub fn mark(runner: &dyn CommandRunner, id: &PackageId, mark: PackageMark) -> Result<()> {
+    run_query(runner, CommandSpec::new("apt-mark").args([mark.as_arg(), &id.apt_name()]))?;
+    Ok(())
 }
 
-pub fn spawn_install(package_name: &str) -> Result<tokio::process::Child> {
-    Ok(TokioCommand::new("apt-get")
-        .env("DEBIAN_FRONTEND", "noninteractive")
-        .args(&["install", "-y", package_name])
-        .stdout(Stdio::piped())
-        .stderr(Stdio::piped())
-        .spawn()?)
+/// Automatically installed packages nothing needs anymore, exactly as apt's
+/// autoremover sees them (`apt-get -s autoremove`), with the details read
+/// from the dpkg database.
+pub fn list_orphans(runner: &dyn CommandRunner, paths: &AptPaths) -> Result<Vec<Package>> {
+    let simulation = simulate(runner, &autoremove_spec())?;
+    let installed = list_installed(paths)?;
+    Ok(installed
+        .into_iter()
+        .filter(|pkg| {
+            simulation
+                .remove
+                .iter()
+                .any(|c| c.name == pkg.id.name && (c.arch.is_empty() || c.arch == pkg.id.arch))
+        })
+        .collect())
 }
 
-pub fn spawn_remove(package_name: &str) -> Result<tokio::process::Child> {
-    Ok(TokioCommand::new("apt-get")
+fn apt_get<const N: usize>(args: [&str; N]) -> CommandSpec {
+    CommandSpec::new("apt-get")
         .env("DEBIAN_FRONTEND", "noninteractive")
-        .args(&["remove", "-y", package_name])
-        .stdout(Stdio::piped())
-        .stderr(Stdio::piped())
-        .spawn()?)
+        .args(args)
 }
 
-pub fn spawn_reinstall(package_name: &str) -> Result<tokio::process::Child> {
-    Ok(TokioCommand::new("apt-get")
-        .env("DEBIAN_FRONTEND", "noninteractive")
-        .args(&["install", "--reinstall", "-y", package_name])
-        .stdout(Stdio::piped())
-        .stderr(Stdio::piped())
-        .spawn()?)
+pub fn install_spec(id: &PackageId) -> CommandSpec {
+    apt_get(["install", "-y", &id.apt_target()])
 }
 
-pub fn spawn_update() -> Result<tokio::process::Child> {
-    Ok(TokioCommand::new("apt-get")
-        .env("DEBIAN_FRONTEND", "noninteractive")
-        .args(&["update"])
-        .stdout(Stdio::piped())
-        .stderr(Stdio::piped())
-        .spawn()?)
+// Removal targets whatever version is installed, so only the arch is pinned
+pub fn remove_spec(id: &PackageId) -> CommandSpec {
+    apt_get(["remove", "-y", &id.apt_name()])
 }
 
-pub fn spawn_upgrade() -> Result<tokio::process::Child> {
-    Ok(TokioCommand::new("apt-get")
-        .env("DEBIAN_FRONTEND", "noninteractive")
-        .args(&["dist-upgrade", "-y"])
-        .stdout(Stdio::piped())
-        .stderr(Stdio::piped())
-        .spawn()?)
+pub fn reinstall_spec(id: &PackageId) -> CommandSpec {
+    apt_get(["install", "--reinstall", "-y", &id.apt_target()])
 }
 
-fn parse_apt_search(output: &str) -> Vec<Package> {
-    let mut packages = Vec::new();
-    let mut lines = output.lines().peekable();
-
-    while let Some(line) = lines.next() {
-        if line.is_empty()
-            || line.starts_with("Sorting...")
-            || line.starts_with("Full Text Search...")
-            || line.starts_with("WARNING:")
-        {
-            continue;
-        }
+pub fn transaction_spec(transaction: &Transaction) -> CommandSpec {
+    let spec = match transaction.kind {
+        TransactionKind::Install => apt_get(["install", "-y"]),
+        TransactionKind::Remove => apt_get(["remove", "-y"]),
+        TransactionKind::Reinstall => apt_get(["install", "--reinstall", "-y"]),
+        TransactionKind::Purge => apt_get(["purge", "-y"]),
+    };
+    let targets = transaction.packages.iter().map(|id| match transaction.kind {
+        TransactionKind::Install | TransactionKind::Reinstall => id.apt_target(),
+        TransactionKind::Remove | TransactionKind::Purge => id.apt_name(),
+    });
+    spec.args(targets)
+}
 
-        // Format: package/release version architecture
-        // Example: acr/noble 2.1.2-1 all
-        let parts: Vec<&str> = line.split_whitespace().collect();
-        if parts.len() < 3 {
-            continue;
-        }
+pub fn upgrade_spec() -> CommandSpec {
+    apt_get(["dist-upgrade", "-y"])
+}
 
-        let name_release = parts[0];
-        let version = parts[1];
-        let arch = parts[2];
+pub fn autoremove_spec() -> CommandSpec {
+    apt_get(["autoremove", "-y"])
+}
+
+/// How long a mutating apt-get waits for another package manager to release
+/// the dpkg lock before giving up.
+pub const LOCK_TIMEOUT_SECS: u64 = 600;
+
+/// Spawns a mutating apt-get with machine-readable progress: status records
+/// (`dlstatus:`/`pmstatus:`) are written to stdout next to the usual
+/// output, and dpkg runs without a pty so no terminal progress bar is mixed
+/// in. A held dpkg lock is waited for rather than failing right away, and
+/// locally changed conffiles are kept, with the new version saved as
+/// `.dpkg-dist` for lapt to offer afterwards.
+fn spawn_with_status(runner: &dyn CommandRunner, mut spec: CommandSpec) -> Result<RunningCommand> {
+    let options = [
+        "-o".to_string(),
+        "APT::Status-Fd=1".to_string(),
+        "-o".to_string(),
+        "Dpkg::Use-Pty=0".to_string(),
+        "-o".to_string(),
+        format!("DPkg::Lock::Timeout={}", LOCK_TIMEOUT_SECS),
+        "-o".to_string(),
+        "Dpkg::Options::=--force-confdef".to_string(),
+        "-o".to_string(),
+        "Dpkg::Options::=--force-confold".to_string(),
+    ];
+    spec.args.splice(0..0, options);
+    runner.spawn(&spec)
+}
 
-        let name = name_release.split('/').next().unwrap_or(name_release);
+pub fn spawn_install(runner: &dyn CommandRunner, id: &PackageId) -> Result<RunningCommand> {
+    spawn_with_status(runner, install_spec(id))
+}
 
-        // Peek next line for summary
-        let mut summary = String::new();
-        if let Some(next_line) = lines.peek() {
-            if next_line.starts_with("  ") {
-                summary = next_line.trim().to_string();
-                lines.next(); // Consume the summary line
-            }
+pub fn spawn_remove(runner: &dyn CommandRunner, id: &PackageId) -> Result<RunningCommand> {
+    spawn_with_status(runner, remove_spec(id))
+}
+
+pub fn spawn_reinstall(runner: &dyn CommandRunner, id: &PackageId) -> Result<RunningCommand> {
+    spawn_with_status(runner, reinstall_spec(id))
+}
+
+pub fn spawn_transaction(runner: &dyn CommandRunner, transaction: &Transaction) -> Result<RunningCommand> {
+    spawn_with_status(runner, transaction_spec(transaction))
+}
+
+pub fn spawn_autoremove(runner: &dyn CommandRunner) -> Result<RunningCommand> {
+    spawn_with_status(runner, autoremove_spec())
+}
+
+pub fn spawn_update(runner: &dyn CommandRunner) -> Result<RunningCommand> {
+    spawn_with_status(runner, apt_get(["update"]))
+}
+
+pub fn spawn_upgrade(runner: &dyn CommandRunner) -> Result<RunningCommand> {
+    spawn_with_status(runner, upgrade_spec())
+}
+
+/// Housekeeping commands that get a broken apt/dpkg state going again.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display, Serialize, Deserialize)]
+pub enum Repair {
+    #[strum(to_string = "Fix broken packages")]
+    FixBroken, // apt-get install -f
+    #[strum(to_string = "Configure pending packages")]
+    ConfigurePending, // dpkg --configure -a
+    #[strum(to_string = "Clean package cache")]
+    CleanCache, // apt-get clean
+}
+
+pub fn spawn_repair(runner: &dyn CommandRunner, repair: Repair) -> Result<RunningCommand> {
+    match repair {
+        Repair::FixBroken => spawn_with_status(runner, apt_get(["install", "-f", "-y"])),
+        // dpkg doesn't take apt's -o options
+        Repair::ConfigurePending => runner.spawn(
+            &CommandSpec::new("dpkg")
+                .env("DEBIAN_FRONTEND", "noninteractive")
+                .args(["--force-confdef", "--force-confold", "--configure", "-a"]),
+        ),
+        Repair::CleanCache => runner.spawn(&apt_get(["clean"])),
+    }
+}
+
+/// Failure modes apt reports often enough to name, each with a next step.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AptError {
+    LockHeld,                // Outro apt/dpkg está rodando
+    PackageNotFound(String), // Nome pedido
+    UnmetDependencies,
+    DpkgInterrupted, // Precisa de dpkg --configure -a
+    DiskFull,
+    PermissionDenied, // O apt rodou sem root (elevação recusada ou ausente)
+    HashSumMismatch,  // Mirror no meio de uma sincronização
+}
+
+impl AptError {
+    /// Recognises one line of apt or dpkg error output.
+    pub fn classify(line: &str) -> Option<Self> {
+        let line = line.trim();
+        let lower = line.to_lowercase();
+        // "Could not open lock file ... (13: Permission denied)" is about
+        // root, not about someone else holding the lock
+        if lower.contains("permission denied") || lower.contains("are you root?") {
+            Some(Self::PermissionDenied)
+        } else if lower.contains("could not get lock") || lower.contains("unable to acquire the dpkg frontend lock") {
+            Some(Self::LockHeld)
+        } else if lower.contains("dpkg was interrupted") {
+            Some(Self::DpkgInterrupted)
+        } else if let Some(name) = line.split("Unable to locate package ").nth(1) {
+            Some(Self::PackageNotFound(name.trim().to_string()))
+        } else if lower.contains("unmet dependencies") || lower.contains("held broken packages") {
+            Some(Self::UnmetDependencies)
+        } else if lower.contains("enough free space") || lower.contains("no space left on device") {
+            Some(Self::DiskFull)
+        } else if lower.contains("hash sum mismatch") || lower.contains("hashes of expected file") {
+            Some(Self::HashSumMismatch)
+        } else {
+            None
         }
+    }
 
-        // Reconstruct ID: name;version;arch;data
-        let id = format!("{};{};{};{}", name, version, arch, "apt");
+    /// The first recognised failure in a block of output.
+    pub fn find(output: &str) -> Option<Self> {
+        output.lines().find_map(Self::classify)
+    }
 
-        let mut pkg = Package::from_packagekit(&id, "Available", &summary);
-        pkg.status = "Available".to_string();
-        packages.push(pkg);
+    /// What the user can do about it.
+    pub fn suggestion(&self) -> String {
+        match self {
+            Self::LockHeld => {
+                "Another package manager is running. Wait for it to finish, then retry.".to_string()
+            }
+            Self::PackageNotFound(name) => format!(
+                "'{}' is not in the package lists. Refreshing the repositories may bring it in.",
+                name
+            ),
+            Self::UnmetDependencies => {
+                "Some dependencies cannot be satisfied. Fixing broken packages (apt-get install -f) may resolve them.".to_string()
+            }
+            Self::DpkgInterrupted => {
+                "A previous installation was interrupted. Configure the pending packages (dpkg --configure -a) first.".to_string()
+            }
+            Self::DiskFull => {
+                "There is not enough free disk space. Cleaning the package cache (apt-get clean) frees the downloaded archives.".to_string()
+            }
+            Self::PermissionDenied => {
+                "apt did not get root. Retry and authenticate when lapt asks through sudo or pkexec; \
+                 if lapt is read-only, set LAPT_ELEVATE to sudo or pkexec or start the lapt helper."
+                    .to_string()
+            }
+            Self::HashSumMismatch => {
+                "Downloaded files did not match the repository index, usually while a mirror syncs. Refresh the repositories and retry.".to_string()
+            }
+        }
     }
 
-    packages
+    /// The repair command that addresses this failure, if there is one.
+    pub fn repair(&self) -> Option<Repair> {
+        match self {
+            Self::UnmetDependencies => Some(Repair::FixBroken),
+            Self::DpkgInterrupted => Some(Repair::ConfigurePending),
+            Self::DiskFull => Some(Repair::CleanCache),
+            _ => None,
+        }
+    }
 }
 
-fn parse_apt_list(output: &str, status: &str) -> Vec<Package> {
+/// Runs `spec` (an `apt-get` invocation) with `-s`: apt resolves the whole
+/// transaction and prints what it would do without touching the system,
+/// which also works without root.
+pub fn simulate(runner: &dyn CommandRunner, spec: &CommandSpec) -> Result<Simulation> {
+    let mut simulated = spec.clone();
+    simulated.args.insert(0, "-s".to_string());
+    let stdout = run_query(runner, simulated)?;
+    Ok(crate::simulation::parse(&stdout))
+}
+
+fn parse_apt_list(output: &str) -> Vec<Package> {
     let mut packages = Vec::new();
 
     for line in output.lines() {
@@ -157,19 +323,51 @@ fn parse_apt_list(output: &str, status: &str) -> Vec<Package> {
         let version = parts[1];
         let arch = parts[2];
 
-        let name = name_release.split('/').next().unwrap_or(name_release);
+        // "noble-updates,now" -> origin "noble-updates"
+        let (name, releases) = name_release.split_once('/').unwrap_or((name_release, ""));
+        let origin = releases.split(',').next().unwrap_or("");
 
-        // Reconstruct ID: name;version;arch;data
-        let id = format!("{};{};{};{}", name, version, arch, "apt");
+        // The flags may contain spaces ("upgradable from: 1.0"), so take them
+        // from the raw line rather than the split parts
+        let flags = line
+            .split_once('[')
+            .and_then(|(_, rest)| rest.split_once(']'))
+            .map(|(flags, _)| flags)
+            .unwrap_or("");
+        let status = parse_apt_list_flags(flags);
 
-        let mut pkg = Package::from_packagekit(&id, status, "");
-        pkg.status = status.to_string(); // Ensure status matches what we passed
-        packages.push(pkg);
+        packages.push(Package::new(PackageId::new(name, version, arch, origin), status, ""));
     }
 
     packages
 }
 
+/// Maps the bracketed flags of `apt list` (`installed,automatic`,
+/// `upgradable from: 1.0`, `residual-config`...) onto [`PackageStatus`].
+fn parse_apt_list_flags(flags: &str) -> PackageStatus {
+    let mut status = PackageStatus::default();
+    for flag in flags.split(',').map(str::trim) {
+        match flag {
+            "installed" | "local" => status.state = PackageState::Installed,
+            "automatic" => {
+                status.state = PackageState::Installed;
+                status.automatic = true;
+            }
+            "residual-config" => status.state = PackageState::ConfigFiles,
+            _ => {
+                if let Some(from) = flag.strip_prefix("upgradable from:") {
+                    // The listed version is the candidate, the installed one is older
+                    status.state = PackageState::Installed;
+                    status.upgradable_from = Some(from.trim().into());
+                } else if flag.starts_with("upgradable to:") {
+                    status.state = PackageState::Installed;
+                }
+            }
+        }
+    }
+    status
+}
+
 fn parse_apt_show(output: &str) -> AptDetails {
     let mut details = AptDetails::default();
     let mut in_description = false;
@@ -199,10 +397,15 @@ fn parse_apt_show(output: &str) -> AptDetails {
          details.license = "Unknown".to_string();
     }
 
+    // Only the first stanza: `apt show` prints one per version
+    if let Some(paragraph) = crate::deb822::parse(output).first() {
+        details.relations = crate::deps::relations_from(paragraph);
+    }
+
     details
 }
 
-fn parse_size(size_str: &str) -> u64 {
+pub fn parse_size(size_str: &str) -> u64 {
     let parts: Vec<&str> = size_str.split_whitespace().collect();
     if parts.is_empty() { return 0; }
     
@@ -223,12 +426,54 @@ fn parse_size(size_str: &str) -> u64 {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_classify_errors() {
+        let cases = [
+            (
+                "E: Could not get lock /var/lib/dpkg/lock-frontend. It is held by process 4242 (apt-get)",
+                AptError::LockHeld,
+            ),
+            (
+                "E: Could not open lock file /var/lib/dpkg/lock-frontend - open (13: Permission denied)",
+                AptError::PermissionDenied,
+            ),
+            ("E: Unable to locate package ghost", AptError::PackageNotFound("ghost".into())),
+            (
+                "E: Unable to correct problems, you have held broken packages.",
+                AptError::UnmetDependencies,
+            ),
+            (
+                "E: dpkg was interrupted, you must manually run 'dpkg --configure -a' to correct the problem.",
+                AptError::DpkgInterrupted,
+            ),
+            (
+                "E: You don't have enough free space in /var/cache/apt/archives/.",
+                AptError::DiskFull,
+            ),
+            (
+                "E: Failed to fetch http://deb.debian.org/debian/dists/bookworm/main/binary-amd64/Packages.xz  Hash Sum mismatch",
+                AptError::HashSumMismatch,
+            ),
+        ];
+        for (line, expected) in cases {
+            assert_eq!(AptError::classify(line), Some(expected), "{}", line);
+        }
+        assert_eq!(AptError::classify("W: Target Packages is configured multiple times"), None);
+
+        let stderr = "W: Some index files failed to download\nE: Unmet dependencies. Try 'apt --fix-broken install'\n";
+        assert_eq!(AptError::find(stderr), Some(AptError::UnmetDependencies));
+        assert_eq!(AptError::UnmetDependencies.repair(), Some(Repair::FixBroken));
+        assert_eq!(AptError::LockHeld.repair(), None);
+    }
+
     #[test]
     fn test_parse_apt_show() {
         let output = r#"Package: vim
 Version: 2:9.1.0016-1ubuntu7.9
 Installed-Size: 4230 kB
 Homepage: https://www.vim.org/
+Depends: vim-common (= 2:9.1.0016-1ubuntu7.9), vim-runtime (= 2:9.1.0016-1ubuntu7.9), libacl1 (>= 2.2.23)
+Suggests: ctags, vim-doc, vim-scripts
 Description: Vi IMproved - enhanced vi editor
  Vim is an almost compatible version of the UNIX editor Vi.
  .
@@ -239,6 +484,8 @@ Description: Vi IMproved - enhanced vi editor
         assert_eq!(details.size, 4331520); // 4230 * 1024
         assert!(details.description.contains("Vi IMproved - enhanced vi editor"));
         assert!(details.description.contains("Many new features have been added"));
+        assert_eq!(details.relations.len(), 6);
+        assert_eq!(details.relations[0].alternatives[0].name, "vim-common");
     }
 
     #[test]
@@ -248,32 +495,35 @@ Listing...
 adduser/noble,now 3.137ubuntu1 all [installed,automatic]
 alsa-base/noble,now 1.0.25+dfsg-0ubuntu7 all [installed]
 "#;
-        let pkgs = parse_apt_list(output, "Installed");
+        let pkgs = parse_apt_list(output);
         assert_eq!(pkgs.len(), 2);
-        assert_eq!(pkgs[0].name, "adduser");
-        assert_eq!(pkgs[0].version, "3.137ubuntu1");
-        assert_eq!(pkgs[0].arch, "all");
-        assert_eq!(pkgs[0].status, "Installed");
-
-        assert_eq!(pkgs[1].name, "alsa-base");
+        assert_eq!(pkgs[0].id.name, "adduser");
+        assert_eq!(pkgs[0].id.version, "3.137ubuntu1");
+        assert_eq!(pkgs[0].id.arch, "all");
+        assert_eq!(pkgs[0].status.state, PackageState::Installed);
+        assert!(pkgs[0].status.automatic);
+        assert_eq!(pkgs[0].id.origin, "noble");
+
+        assert_eq!(pkgs[1].id.name, "alsa-base");
+        assert!(!pkgs[1].status.automatic);
     }
 
     #[test]
-    fn test_parse_apt_search() {
-        let output = r#"
-Sorting...
-Full Text Search...
-acr/noble 2.1.2-1 all
-  autoconf like tool
-
-aerc/noble-updates 0.17.0-1 amd64
-  Pretty Good Email Client
+    fn test_parse_apt_list_upgradable() {
+        let output = r#"Listing...
+vim/noble-updates 2:9.1.0016-1ubuntu7.9 amd64 [upgradable from: 2:9.1.0016-1ubuntu7.8]
+oldconf/noble 1.0 all [residual-config]
+newpkg/noble 2.0 amd64
 "#;
-        let pkgs = parse_apt_search(output);
-        assert_eq!(pkgs.len(), 2);
-        assert_eq!(pkgs[0].name, "acr");
-        assert_eq!(pkgs[0].summary, "autoconf like tool");
-        assert_eq!(pkgs[1].name, "aerc");
-        assert_eq!(pkgs[1].summary, "Pretty Good Email Client");
+        let pkgs = parse_apt_list(output);
+        assert_eq!(pkgs.len(), 3);
+        assert_eq!(pkgs[0].id.version, "2:9.1.0016-1ubuntu7.9");
+        assert_eq!(pkgs[0].status.state, PackageState::Installed);
+        assert_eq!(
+            pkgs[0].status.upgradable_from,
+            Some("2:9.1.0016-1ubuntu7.8".into())
+        );
+        assert_eq!(pkgs[1].status.state, PackageState::ConfigFiles);
+        assert_eq!(pkgs[2].status, PackageStatus::default());
     }
 }
@@ -1,259 +1,1122 @@
-use crate::pkg::Package;
+use crate::apt::{AptError, AptPaths, Repair};
+use crate::conffile::{Conffile, ConffileChoice, ConffileTracker, Leftovers};
+use crate::debconf::{Frontend, PassthroughRunner, Question, QuestionId};
+use crate::index::PackageIndex;
+use crate::joblog::LogLine;
+use crate::lock::LockHolder;
+use crate::pkg::{Package, PackageId, PackageMark, PackageStatus};
+use crate::privilege::{ElevatedRunner, Password, Privilege};
+use crate::progress::{Progress, ProgressPhase, SpeedMeter};
+use crate::runner::{CommandRunner, RunningCommand, SystemRunner};
+use crate::scheduler::{Job, JobId, JobQueue, JobState};
+use crate::simulation::Simulation;
+use crate::transaction::Transaction;
 use anyhow::Result;
-use tokio::sync::mpsc::UnboundedSender;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender};
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum BackendCommand {
     ListInstalled,
     ListUpgradable,
-    #[allow(dead_code)]
+    ListOrphans,
     Search(String),
-    GetDetails(String),
-    Install(String),
-    Remove(String),
-    Reinstall(String),
+    GetDetails(PackageId),
+    Install(PackageId),
+    Remove(PackageId),
+    Reinstall(PackageId),
+    Mark(PackageId, PackageMark),
+    Apply(Transaction), // Vários pacotes numa única chamada do apt-get
     RefreshRepos,
     UpgradeSystem,
+    Autoremove,
+    Repair(Repair), // Sugerido quando o apt falha de um jeito conhecido
+    Simulate(Box<BackendCommand>), // Dry-run (apt-get -s) de uma operação acima
+    Cancel,                        // SIGINT no apt-get em execução
+    Authenticate(Password),        // Senha do sudo, guardada para a sessão
+    AnswerDebconf(QuestionId, Option<String>), // None volta para a pergunta anterior
+    ScanConffiles,                             // Leftovers .dpkg-dist/.dpkg-old/... no /etc
+    DiffConffile(std::path::PathBuf),          // Leftover contra o arquivo em uso
+    ResolveConffile(std::path::PathBuf, ConffileChoice), // (leftover, escolha)
+
+    // Controle da fila, tratados pelo run_backend
+    MoveJobUp(JobId),
+    MoveJobDown(JobId),
+    DropJob(JobId),
 }
 
-#[derive(Debug, Clone, PartialEq)]
+impl BackendCommand {
+    /// Short human description: "Install vim".
+    pub fn label(&self) -> String {
+        match self {
+            Self::Install(id) => format!("Install {}", id.name),
+            Self::Remove(id) => format!("Remove {}", id.name),
+            Self::Reinstall(id) => format!("Reinstall {}", id.name),
+            Self::Mark(id, mark) => format!("Mark {} {}", id.name, mark.as_arg()),
+            Self::Apply(transaction) => transaction.summary(),
+            Self::RefreshRepos => "Refresh repositories".to_string(),
+            Self::UpgradeSystem => "Upgrade system".to_string(),
+            Self::Autoremove => "Autoremove".to_string(),
+            Self::Repair(repair) => repair.to_string(),
+            Self::Authenticate(_) => "Authenticate".to_string(),
+            Self::AnswerDebconf(..) => "Answer debconf question".to_string(),
+            Self::ResolveConffile(path, choice) => format!("{} {}", choice, path.display()),
+            other => format!("{:?}", other),
+        }
+    }
+
+    /// Refuses package ids apt could take for anything but a package; the
+    /// helper runs whatever a client sends as root.
+    pub fn validate(&self) -> Result<()> {
+        match self {
+            Self::GetDetails(id)
+            | Self::Install(id)
+            | Self::Remove(id)
+            | Self::Reinstall(id)
+            | Self::Mark(id, _) => id.validate(),
+            Self::Apply(transaction) => transaction.packages.iter().try_for_each(PackageId::validate),
+            Self::Simulate(cmd) => cmd.validate(),
+            _ => Ok(()),
+        }
+    }
+
+    /// Commands that touch the dpkg/apt locks; these go through the job
+    /// queue one at a time, everything else runs right away.
+    pub fn is_mutating(&self) -> bool {
+        matches!(
+            self,
+            Self::Install(_)
+                | Self::Remove(_)
+                | Self::Reinstall(_)
+                | Self::Mark(..)
+                | Self::Apply(_)
+                | Self::RefreshRepos
+                | Self::UpgradeSystem
+                | Self::Autoremove
+                | Self::Repair(_)
+                | Self::ResolveConffile(..)
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum BackendEvent {
     InstalledPackagesFound(Vec<Package>),
     UpgradablePackagesFound(Vec<Package>),
-    SearchResultsFound(Vec<Package>),
-    PackageDetailsFound(Package),
+    OrphanPackagesFound(Vec<Package>),
+    SearchResultsFound(String, Vec<Package>), // (query, resultados)
+    PackageDetailsFound(Box<Package>),
+    PackageMarked(PackageId, PackageMark),
+    SimulationFound(Box<BackendCommand>, Box<Simulation>), // (comando simulado, resultado)
+    QueueUpdated(Vec<Job>),
+    Progress(Progress), // Registros dlstatus/pmstatus do APT::Status-Fd
+    Output(LogLine),    // Saída completa do comando em execução
+    WaitingForLock(LockHolder), // Outro gerenciador segura o lock do dpkg
+    Cancelled(BackendCommand),
+    CancelRefused(String), // Motivo
     TaskStarted(String),
     TaskFinished(BackendCommand),
+    Authenticated,
+    AuthenticationFailed(String), // Motivo dado pelo sudo
+    DebconfQuestion(QuestionId, Question), // O script do mantenedor espera a resposta
+    ConffileKept(Conffile),                // --force-confold guardou a versão nova ao lado
+    ConffilesFound(Vec<Conffile>),
+    ConffileDiff(std::path::PathBuf, String), // (leftover, diff -u)
+    Warning(String), // Algo deu errado, mas o comando seguiu em frente
     Error(String),
+    Failed(BackendCommand, AptError, String), // (comando, causa reconhecida, mensagem)
 }
 
-pub struct AptBackend {}
+/// A package manager that can service every `BackendCommand`.
+///
+/// Each operation reports its results through `BackendEvent`s on `tx` rather
+/// than return values, so the UI stays decoupled from whichever backend is
+/// plugged in behind the worker.
+pub trait PackageBackend: Send + Sync + 'static {
+    fn list_installed(
+        &self,
+        tx: UnboundedSender<BackendEvent>,
+    ) -> impl Future<Output = Result<()>> + Send;
 
-impl AptBackend {
-    pub async fn new() -> Result<Self> {
-        Ok(Self {})
-    }
+    fn list_upgradable(
+        &self,
+        tx: UnboundedSender<BackendEvent>,
+    ) -> impl Future<Output = Result<()>> + Send;
 
-    async fn stream_command_output(
+    fn list_orphans(
         &self,
-        mut child: tokio::process::Child,
-        cmd_context: BackendCommand,
         tx: UnboundedSender<BackendEvent>,
-    ) {
+    ) -> impl Future<Output = Result<()>> + Send;
+
+    fn search(
+        &self,
+        query: String,
+        tx: UnboundedSender<BackendEvent>,
+    ) -> impl Future<Output = Result<()>> + Send;
+
+    fn get_details(
+        &self,
+        pkg_id: PackageId,
+        tx: UnboundedSender<BackendEvent>,
+    ) -> impl Future<Output = Result<()>> + Send;
+
+    fn install(
+        &self,
+        pkg_id: PackageId,
+        tx: UnboundedSender<BackendEvent>,
+    ) -> impl Future<Output = Result<()>> + Send;
+
+    fn remove(
+        &self,
+        pkg_id: PackageId,
+        tx: UnboundedSender<BackendEvent>,
+    ) -> impl Future<Output = Result<()>> + Send;
+
+    fn reinstall(
+        &self,
+        pkg_id: PackageId,
+        tx: UnboundedSender<BackendEvent>,
+    ) -> impl Future<Output = Result<()>> + Send;
+
+    fn mark(
+        &self,
+        pkg_id: PackageId,
+        mark: PackageMark,
+        tx: UnboundedSender<BackendEvent>,
+    ) -> impl Future<Output = Result<()>> + Send;
+
+    fn apply(
+        &self,
+        transaction: Transaction,
+        tx: UnboundedSender<BackendEvent>,
+    ) -> impl Future<Output = Result<()>> + Send;
+
+    fn refresh_repos(
+        &self,
+        tx: UnboundedSender<BackendEvent>,
+    ) -> impl Future<Output = Result<()>> + Send;
+
+    fn upgrade_system(
+        &self,
+        tx: UnboundedSender<BackendEvent>,
+    ) -> impl Future<Output = Result<()>> + Send;
+
+    fn autoremove(
+        &self,
+        tx: UnboundedSender<BackendEvent>,
+    ) -> impl Future<Output = Result<()>> + Send;
+
+    fn repair(
+        &self,
+        repair: Repair,
+        tx: UnboundedSender<BackendEvent>,
+    ) -> impl Future<Output = Result<()>> + Send;
+
+    fn simulate(
+        &self,
+        cmd: BackendCommand,
+        tx: UnboundedSender<BackendEvent>,
+    ) -> impl Future<Output = Result<()>> + Send;
+
+    fn cancel(
+        &self,
+        tx: UnboundedSender<BackendEvent>,
+    ) -> impl Future<Output = Result<()>> + Send;
+
+    fn authenticate(
+        &self,
+        password: Password,
+        tx: UnboundedSender<BackendEvent>,
+    ) -> impl Future<Output = Result<()>> + Send;
+
+    fn answer_debconf(
+        &self,
+        id: QuestionId,
+        value: Option<String>,
+        tx: UnboundedSender<BackendEvent>,
+    ) -> impl Future<Output = Result<()>> + Send;
+
+    fn scan_conffiles(
+        &self,
+        tx: UnboundedSender<BackendEvent>,
+    ) -> impl Future<Output = Result<()>> + Send;
+
+    fn diff_conffile(
+        &self,
+        path: std::path::PathBuf,
+        tx: UnboundedSender<BackendEvent>,
+    ) -> impl Future<Output = Result<()>> + Send;
+
+    fn resolve_conffile(
+        &self,
+        path: std::path::PathBuf,
+        choice: ConffileChoice,
+        tx: UnboundedSender<BackendEvent>,
+    ) -> impl Future<Output = Result<()>> + Send;
+
+    fn handle_command(
+        &self,
+        cmd: BackendCommand,
+        tx: UnboundedSender<BackendEvent>,
+    ) -> impl Future<Output = Result<()>> + Send {
+        async move {
+            cmd.validate()?;
+            match cmd {
+                BackendCommand::ListInstalled => self.list_installed(tx).await,
+                BackendCommand::ListUpgradable => self.list_upgradable(tx).await,
+                BackendCommand::ListOrphans => self.list_orphans(tx).await,
+                BackendCommand::Search(query) => self.search(query, tx).await,
+                BackendCommand::GetDetails(pkg_id) => self.get_details(pkg_id, tx).await,
+                BackendCommand::Install(pkg_id) => self.install(pkg_id, tx).await,
+                BackendCommand::Remove(pkg_id) => self.remove(pkg_id, tx).await,
+                BackendCommand::Reinstall(pkg_id) => self.reinstall(pkg_id, tx).await,
+                BackendCommand::Mark(pkg_id, mark) => self.mark(pkg_id, mark, tx).await,
+                BackendCommand::Apply(transaction) => self.apply(transaction, tx).await,
+                BackendCommand::RefreshRepos => self.refresh_repos(tx).await,
+                BackendCommand::UpgradeSystem => self.upgrade_system(tx).await,
+                BackendCommand::Autoremove => self.autoremove(tx).await,
+                BackendCommand::Repair(repair) => self.repair(repair, tx).await,
+                BackendCommand::Simulate(cmd) => self.simulate(*cmd, tx).await,
+                BackendCommand::Cancel => self.cancel(tx).await,
+                BackendCommand::Authenticate(password) => self.authenticate(password, tx).await,
+                BackendCommand::AnswerDebconf(id, value) => self.answer_debconf(id, value, tx).await,
+                BackendCommand::ScanConffiles => self.scan_conffiles(tx).await,
+                BackendCommand::DiffConffile(path) => self.diff_conffile(path, tx).await,
+                BackendCommand::ResolveConffile(path, choice) => self.resolve_conffile(path, choice, tx).await,
+                // Queue control never reaches the backend itself
+                BackendCommand::MoveJobUp(_)
+                | BackendCommand::MoveJobDown(_)
+                | BackendCommand::DropJob(_) => Ok(()),
+            }
+        }
+    }
+}
+
+/// A command and the sender its events go back to.
+pub type Request = (BackendCommand, UnboundedSender<BackendEvent>);
+
+/// Worker loop for a single client: every event goes to `tx`. See
+/// `run_shared_backend`.
+pub async fn run_backend<B: PackageBackend>(
+    backend: B,
+    mut rx: UnboundedReceiver<BackendCommand>,
+    tx: UnboundedSender<BackendEvent>,
+) {
+    let (req_tx, req_rx) = tokio::sync::mpsc::unbounded_channel::<Request>();
+    tokio::spawn(async move {
+        while let Some(cmd) = rx.recv().await {
+            if req_tx.send((cmd, tx.clone())).is_err() {
+                break;
+            }
+        }
+    });
+    run_shared_backend(backend, req_rx).await;
+}
+
+/// Worker loop: read-only commands go straight to `backend`, mutating ones
+/// are queued and run one at a time, each starting once the previous one
+/// reports `TaskFinished`. A command's events, failures included as
+/// `BackendEvent::Error`, go back only to the sender it came with; every
+/// queue change is published as `QueueUpdated` to all senders, so each
+/// client sees what its jobs wait behind. Jobs can only be moved, dropped,
+/// cancelled or answered by the client that queued them.
+pub async fn run_shared_backend<B: PackageBackend>(backend: B, mut rx: UnboundedReceiver<Request>) {
+    let mut queue = JobQueue::default();
+    let mut owners: HashMap<JobId, UnboundedSender<BackendEvent>> = HashMap::new();
+    let mut clients: Vec<UnboundedSender<BackendEvent>> = Vec::new();
+    let (done_tx, mut done_rx) = tokio::sync::mpsc::unbounded_channel::<(JobId, JobState)>();
+
+    loop {
+        tokio::select! {
+            // Commands first, so a reorder or drop already sent wins over
+            // starting the next job
+            biased;
+            request = rx.recv() => {
+                let Some((cmd, tx)) = request else { break };
+                if !clients.iter().any(|c| c.same_channel(&tx)) {
+                    clients.push(tx.clone());
+                }
+                let owns = |id: JobId| owners.get(&id).is_some_and(|o| o.same_channel(&tx));
+                let foreign_running = queue.running().is_some_and(|j| !owns(j.id));
+                let changed = match cmd {
+                    BackendCommand::MoveJobUp(id) => owns(id) && queue.move_job(id, true),
+                    BackendCommand::MoveJobDown(id) => owns(id) && queue.move_job(id, false),
+                    BackendCommand::DropJob(id) => owns(id) && queue.drop_job(id),
+                    BackendCommand::Cancel if foreign_running => {
+                        let _ = tx.send(BackendEvent::CancelRefused(
+                            "The running job belongs to another lapt".into(),
+                        ));
+                        false
+                    }
+                    // As perguntas do debconf só foram para o dono do job
+                    BackendCommand::AnswerDebconf(..) if foreign_running => false,
+                    cmd if cmd.is_mutating() => {
+                        owners.insert(queue.push(cmd), tx);
+                        true
+                    }
+                    cmd => {
+                        if let Err(e) = backend.handle_command(cmd, tx.clone()).await {
+                            let _ = tx.send(BackendEvent::Error(e.to_string()));
+                        }
+                        false
+                    }
+                };
+                if changed {
+                    publish_queue(&mut clients, &queue);
+                }
+            }
+            Some((id, state)) = done_rx.recv() => {
+                queue.finish(id, state);
+                owners.remove(&id);
+                publish_queue(&mut clients, &queue);
+            }
+        }
+
+        // Quem desconectou não vai ver os próprios jobs rodarem
+        let abandoned: Vec<JobId> = queue
+            .jobs()
+            .iter()
+            .filter(|j| j.state == JobState::Pending && owners.get(&j.id).is_none_or(|o| o.is_closed()))
+            .map(|j| j.id)
+            .collect();
+        for id in &abandoned {
+            queue.drop_job(*id);
+            owners.remove(id);
+        }
+        if !abandoned.is_empty() {
+            publish_queue(&mut clients, &queue);
+        }
+
+        if let Some(job) = queue.start_next() {
+            publish_queue(&mut clients, &queue);
+            let tx = owners[&job.id].clone();
+            let job_tx = watch_job(job.id, tx.clone(), done_tx.clone());
+            if let Err(e) = backend.handle_command(job.command, job_tx).await {
+                let _ = tx.send(BackendEvent::Error(e.to_string()));
+            }
+        }
+    }
+}
+
+/// Sends the queue to every client still listening, forgetting the rest.
+fn publish_queue(clients: &mut Vec<UnboundedSender<BackendEvent>>, queue: &JobQueue) {
+    clients.retain(|c| c.send(BackendEvent::QueueUpdated(queue.jobs().to_vec())).is_ok());
+}
+
+/// Forwards a job's events to `tx` and reports on `done` how the job ended:
+/// at its `TaskFinished` (failed if an `Error` came first, cancelled after
+/// `Cancelled`), or failed when the backend drops the sender without
+/// finishing.
+fn watch_job(
+    id: JobId,
+    tx: UnboundedSender<BackendEvent>,
+    done: UnboundedSender<(JobId, JobState)>,
+) -> UnboundedSender<BackendEvent> {
+    let (job_tx, mut job_rx) = tokio::sync::mpsc::unbounded_channel::<BackendEvent>();
+    tokio::spawn(async move {
+        let mut state = JobState::Done;
+        let mut reported = false;
+        while let Some(event) = job_rx.recv().await {
+            match event {
+                BackendEvent::Cancelled(_) => state = JobState::Cancelled,
+                BackendEvent::Error(_) | BackendEvent::Failed(..) if state == JobState::Done => {
+                    state = JobState::Failed
+                }
+                _ => {}
+            }
+            let finished = matches!(event, BackendEvent::TaskFinished(_));
+            let _ = tx.send(event);
+            if finished && !reported {
+                let _ = done.send((id, state));
+                reported = true;
+            }
+        }
+        if !reported {
+            let _ = done.send((id, JobState::Failed));
+        }
+    });
+    job_tx
+}
+
+/// The apt-get currently streaming its output, kept so it can be cancelled.
+#[derive(Debug, Default)]
+struct ActiveCommand {
+    pid: Option<u32>,
+    dpkg_started: bool, // Depois disso o SIGINT pode deixar pacotes pela metade
+    cancel_requested: bool,
+}
+
+#[derive(Clone)]
+pub struct AptBackend {
+    runner: Arc<dyn CommandRunner>,
+    elevated: Arc<ElevatedRunner>, // Para os comandos que mexem no sistema
+    paths: AptPaths,
+    // Built on the first search, dropped whenever the lists change
+    index: Arc<Mutex<Option<Arc<PackageIndex>>>>,
+    active: Arc<Mutex<Option<ActiveCommand>>>,
+    debconf: Option<Arc<Frontend>>, // Perguntas do debconf na TUI, quando ligado
+    debconf_socket: Arc<Mutex<Option<std::path::PathBuf>>>, // O do dpkg que está rodando
+    leftovers: Arc<Leftovers>,      // Os únicos conffiles que diff/resolve aceitam
+}
+
+impl AptBackend {
+    pub async fn new(privilege: Privilege) -> Result<Self> {
+        Ok(Self::with_runner(Arc::new(SystemRunner), AptPaths::default()).with_privilege(privilege))
+    }
+
+    /// Builds a backend whose apt invocations all go through `runner` and
+    /// which reads the package databases from `paths`. It assumes root
+    /// until told otherwise with `with_privilege`.
+    pub fn with_runner(runner: Arc<dyn CommandRunner>, paths: AptPaths) -> Self {
+        Self {
+            elevated: Arc::new(ElevatedRunner::new(runner.clone(), Privilege::Root)),
+            leftovers: Arc::new(Leftovers::new(runner.clone(), crate::conffile::CONFIG_DIR)),
+            runner,
+            paths,
+            index: Arc::new(Mutex::new(None)),
+            active: Arc::new(Mutex::new(None)),
+            debconf: None,
+            debconf_socket: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// Runs the commands that change the system through `privilege`'s
+    /// helper; queries keep running as the user.
+    pub fn with_privilege(mut self, privilege: Privilege) -> Self {
+        self.elevated = Arc::new(ElevatedRunner::new(self.runner.clone(), privilege));
+        self
+    }
+
+    /// Asks debconf questions through lapt, over sockets in `dir`, instead
+    /// of answering them with their defaults.
+    pub fn with_debconf(mut self, dir: &std::path::Path) -> Self {
+        self.debconf = Some(Arc::new(Frontend::new(dir)));
+        self
+    }
+
+    /// The runner for a command that runs dpkg, and with it maintainer
+    /// scripts: the elevated one, with debconf talking to lapt if enabled.
+    fn dpkg_runner(&self, tx: &UnboundedSender<BackendEvent>) -> Arc<dyn CommandRunner> {
+        let Some(frontend) = &self.debconf else {
+            return self.elevated.clone();
+        };
+        match frontend.listen(tx.clone()) {
+            Ok(socket) => {
+                *self.debconf_socket.lock().unwrap() = Some(socket.clone());
+                Arc::new(PassthroughRunner::new(self.elevated.clone(), socket))
+            }
+            Err(e) => {
+                // O job segue com os valores padrão: não é uma falha dele
+                let _ = tx.send(BackendEvent::Warning(format!(
+                    "Cannot listen for debconf questions, using their defaults: {:#}",
+                    e
+                )));
+                self.elevated.clone()
+            }
+        }
+    }
+
+    /// Returns the package index, building it from the apt lists if needed.
+    /// Blocking: call from `spawn_blocking`.
+    fn package_index(&self, tx: &UnboundedSender<BackendEvent>) -> Result<Arc<PackageIndex>> {
+        let mut cached = self.index.lock().unwrap();
+        if let Some(index) = cached.as_ref() {
+            return Ok(index.clone());
+        }
+        let _ = tx.send(BackendEvent::TaskStarted("Indexing package lists...".into()));
+        let index = Arc::new(PackageIndex::load(self.runner.as_ref(), &self.paths.lists_dir)?);
+        if !index.skipped().is_empty() {
+            let _ = tx.send(BackendEvent::Warning(format!(
+                "Some package lists could not be read and were left out of the search:\n{}",
+                index.skipped().join("\n")
+            )));
+        }
+        *cached = Some(index.clone());
+        Ok(index)
+    }
+
+    fn invalidate_index(&self) {
+        *self.index.lock().unwrap() = None;
+    }
+
+    /// Tells the UI who holds the dpkg lock, if anyone, before a mutating
+    /// command starts waiting for it.
+    fn announce_lock_holder(&self, tx: &UnboundedSender<BackendEvent>) {
+        if let Some(holder) = crate::lock::find_holder(&self.paths.dpkg_lock, &self.paths.proc_dir) {
+            let _ = tx.send(BackendEvent::WaitingForLock(holder));
+        }
+    }
+
+    /// Registers `child` as the command `Cancel` applies to.
+    fn track(&self, child: &RunningCommand) {
+        *self.active.lock().unwrap() = Some(ActiveCommand {
+            pid: child.id(),
+            ..Default::default()
+        });
+    }
+
+    /// Forgets the active command; true if it was cancelled.
+    fn untrack(&self) -> bool {
+        if let Some(frontend) = &self.debconf
+            && let Some(socket) = self.debconf_socket.lock().unwrap().take()
+        {
+            frontend.close(&socket);
+        }
+        self.active
+            .lock()
+            .unwrap()
+            .take()
+            .is_some_and(|active| active.cancel_requested)
+    }
+
+    /// Tracks `child`, forwards its output as events and waits for it to
+    /// exit. The caller untracks the child and reports the outcome.
+    async fn run_streaming(&self, mut child: RunningCommand, tx: &UnboundedSender<BackendEvent>) -> StreamedExit {
         use tokio::io::{AsyncBufReadExt, BufReader};
 
+        self.track(&child);
         let stdout = child.stdout.take().unwrap();
         let stderr = child.stderr.take().unwrap();
 
         let tx_clone = tx.clone();
-        tokio::spawn(async move {
+        let active = self.active.clone();
+        let stdout_task = tokio::spawn(async move {
             let mut reader = BufReader::new(stdout).lines();
+            let mut meter = SpeedMeter::default();
+            let mut conffiles = ConffileTracker::default();
             while let Ok(Some(line)) = reader.next_line().await {
-                if let Some(msg) = parse_apt_signal(&line) {
+                if let Some(conffile) = conffiles.line(&line) {
+                    let _ = tx_clone.send(BackendEvent::ConffileKept(conffile));
+                }
+                let progress = crate::progress::parse_status_line(&line);
+                let dpkg_running = is_dpkg_line(&line)
+                    || progress.as_ref().is_some_and(|p| p.phase == ProgressPhase::Install);
+                if dpkg_running && let Some(active) = active.lock().unwrap().as_mut() {
+                    active.dpkg_started = true;
+                }
+
+                // O log guarda tudo, inclusive os registros de status
+                let _ = tx_clone.send(BackendEvent::Output(LogLine::stdout(line.as_str())));
+                if let Some(mut progress) = progress {
+                    meter.update(&mut progress);
+                    let _ = tx_clone.send(BackendEvent::Progress(progress));
+                    continue;
+                }
+                if let Some(bytes) = crate::progress::parse_need_to_get(&line) {
+                    meter.set_total(bytes);
+                } else if let Some(msg) = parse_apt_signal(&line) {
                     let _ = tx_clone.send(BackendEvent::TaskStarted(msg));
                 }
             }
         });
 
         let tx_clone = tx.clone();
-        tokio::spawn(async move {
+        let stderr_task = tokio::spawn(async move {
             let mut reader = BufReader::new(stderr).lines();
+            let mut tail = std::collections::VecDeque::with_capacity(STDERR_TAIL);
+            let mut error = None;
             while let Ok(Some(line)) = reader.next_line().await {
                 if let Some(msg) = parse_apt_signal(&line) {
                     let _ = tx_clone.send(BackendEvent::TaskStarted(format!("Error: {}", msg)));
                 }
+                if error.is_none() {
+                    error = AptError::classify(&line);
+                }
+                let _ = tx_clone.send(BackendEvent::Output(LogLine::stderr(line.as_str())));
+                if !line.trim().is_empty() {
+                    if tail.len() == STDERR_TAIL {
+                        tail.pop_front();
+                    }
+                    tail.push_back(line);
+                }
+            }
+            (Vec::from(tail), error)
+        });
+
+        let status = child.wait().await;
+        // Drain both pipes before reporting, so no output lands after TaskFinished.
+        let (_, stderr) = tokio::join!(stdout_task, stderr_task);
+        let (stderr_tail, error) = stderr.unwrap_or_default();
+        StreamedExit {
+            status,
+            stderr_tail,
+            error,
+        }
+    }
+
+    async fn stream_command_output(
+        &self,
+        child: RunningCommand,
+        cmd_context: BackendCommand,
+        tx: UnboundedSender<BackendEvent>,
+    ) {
+        let exit = self.run_streaming(child, &tx).await;
+        if self.untrack() {
+            let _ = tx.send(BackendEvent::Cancelled(cmd_context.clone()));
+            let _ = tx.send(BackendEvent::TaskFinished(cmd_context));
+            return;
+        }
+        report_exit(exit, &cmd_context, &tx);
+        let _ = tx.send(BackendEvent::TaskFinished(cmd_context));
+    }
+
+    fn spawn_streaming(
+        &self,
+        child: RunningCommand,
+        cmd_context: BackendCommand,
+        tx: UnboundedSender<BackendEvent>,
+    ) {
+        let backend = self.clone();
+        tokio::spawn(async move {
+            backend.stream_command_output(child, cmd_context, tx).await;
+        });
+    }
+}
+
+impl PackageBackend for AptBackend {
+    async fn list_installed(&self, tx: UnboundedSender<BackendEvent>) -> Result<()> {
+        let _ = tx.send(BackendEvent::TaskStarted(
+            "Listing installed packages...".into(),
+        ));
+        let paths = self.paths.clone();
+        tokio::task::spawn_blocking(move || {
+            match crate::apt::list_installed(&paths) {
+                Ok(pkgs) => {
+                    let _ = tx.send(BackendEvent::InstalledPackagesFound(pkgs));
+                }
+                Err(e) => {
+                    let _ = tx.send(BackendEvent::Error(format!(
+                        "Failed to list installed: {}",
+                        e
+                    )));
+                }
             }
+            let _ = tx.send(BackendEvent::TaskFinished(BackendCommand::ListInstalled));
         });
+        Ok(())
+    }
 
-        match child.wait().await {
-            Ok(status) => {
-                if !status.success() {
+    async fn list_upgradable(&self, tx: UnboundedSender<BackendEvent>) -> Result<()> {
+        let _ = tx.send(BackendEvent::TaskStarted("Checking for updates...".into()));
+        let runner = self.runner.clone();
+        tokio::task::spawn_blocking(move || {
+            match crate::apt::list_upgradable(runner.as_ref()) {
+                Ok(pkgs) => {
+                    let _ = tx.send(BackendEvent::UpgradablePackagesFound(pkgs));
+                }
+                Err(e) => {
                     let _ = tx.send(BackendEvent::Error(format!(
-                        "Command failed with status: {}",
-                        status
+                        "Failed to list upgradable: {}",
+                        e
                     )));
                 }
             }
-            Err(e) => {
-                let _ = tx.send(BackendEvent::Error(format!("Wait failed: {}", e)));
+            let _ = tx.send(BackendEvent::TaskFinished(BackendCommand::ListUpgradable));
+        });
+        Ok(())
+    }
+
+    async fn list_orphans(&self, tx: UnboundedSender<BackendEvent>) -> Result<()> {
+        let _ = tx.send(BackendEvent::TaskStarted("Looking for orphaned packages...".into()));
+        let runner = self.runner.clone();
+        let paths = self.paths.clone();
+        tokio::task::spawn_blocking(move || {
+            match crate::apt::list_orphans(runner.as_ref(), &paths) {
+                Ok(pkgs) => {
+                    let _ = tx.send(BackendEvent::OrphanPackagesFound(pkgs));
+                }
+                Err(e) => {
+                    let _ = tx.send(BackendEvent::Error(format!(
+                        "Failed to list orphans: {}",
+                        e
+                    )));
+                }
             }
-        }
-        let _ = tx.send(BackendEvent::TaskFinished(cmd_context));
+            let _ = tx.send(BackendEvent::TaskFinished(BackendCommand::ListOrphans));
+        });
+        Ok(())
+    }
+
+    async fn search(&self, query: String, tx: UnboundedSender<BackendEvent>) -> Result<()> {
+        let backend = self.clone();
+        tokio::task::spawn_blocking(move || {
+            match backend.package_index(&tx) {
+                Ok(index) => {
+                    let pkgs = index.search(&query);
+                    let _ = tx.send(BackendEvent::SearchResultsFound(query.clone(), pkgs));
+                }
+                Err(e) => {
+                    let _ = tx.send(BackendEvent::Error(format!("Search failed: {}", e)));
+                }
+            }
+            let _ = tx.send(BackendEvent::TaskFinished(BackendCommand::Search(query)));
+        });
+        Ok(())
+    }
+
+    async fn get_details(&self, pkg_id: PackageId, tx: UnboundedSender<BackendEvent>) -> Result<()> {
+        let runner = self.runner.clone();
+        tokio::task::spawn_blocking(move || {
+            match crate::apt::get_package_details(runner.as_ref(), &pkg_id) {
+                Ok(details) => {
+                    let mut pkg = Package::new(pkg_id.clone(), PackageStatus::default(), "");
+                    pkg.update_details(
+                        &details.description,
+                        &details.license,
+                        details.size,
+                        &details.url,
+                    );
+                    let _ = tx.send(BackendEvent::PackageDetailsFound(Box::new(pkg)));
+                }
+                Err(e) => {
+                    let _ = tx.send(BackendEvent::Error(format!(
+                        "Failed to fetch details for '{}': {}",
+                        pkg_id.name, e
+                    )));
+                }
+            }
+            let _ = tx.send(BackendEvent::TaskFinished(BackendCommand::GetDetails(pkg_id)));
+        });
+        Ok(())
+    }
+
+    async fn install(&self, pkg_id: PackageId, tx: UnboundedSender<BackendEvent>) -> Result<()> {
+        self.announce_lock_holder(&tx);
+        let child = crate::apt::spawn_install(self.dpkg_runner(&tx).as_ref(), &pkg_id)?;
+        self.spawn_streaming(child, BackendCommand::Install(pkg_id), tx);
+        Ok(())
+    }
+
+    async fn remove(&self, pkg_id: PackageId, tx: UnboundedSender<BackendEvent>) -> Result<()> {
+        self.announce_lock_holder(&tx);
+        let child = crate::apt::spawn_remove(self.dpkg_runner(&tx).as_ref(), &pkg_id)?;
+        self.spawn_streaming(child, BackendCommand::Remove(pkg_id), tx);
+        Ok(())
+    }
+
+    async fn reinstall(&self, pkg_id: PackageId, tx: UnboundedSender<BackendEvent>) -> Result<()> {
+        self.announce_lock_holder(&tx);
+        let child = crate::apt::spawn_reinstall(self.dpkg_runner(&tx).as_ref(), &pkg_id)?;
+        self.spawn_streaming(child, BackendCommand::Reinstall(pkg_id), tx);
+        Ok(())
     }
 
-    pub async fn handle_command(
+    async fn apply(&self, transaction: Transaction, tx: UnboundedSender<BackendEvent>) -> Result<()> {
+        self.announce_lock_holder(&tx);
+        let child = crate::apt::spawn_transaction(self.dpkg_runner(&tx).as_ref(), &transaction)?;
+        self.spawn_streaming(child, BackendCommand::Apply(transaction), tx);
+        Ok(())
+    }
+
+    async fn mark(
         &self,
-        cmd: BackendCommand,
+        pkg_id: PackageId,
+        mark: PackageMark,
         tx: UnboundedSender<BackendEvent>,
     ) -> Result<()> {
-        let cmd_context = cmd.clone();
-
-        match &cmd {
-            BackendCommand::ListInstalled => {
-                let _ = tx.send(BackendEvent::TaskStarted(
-                    "Listing installed packages...".into(),
-                ));
-                let tx_clone = tx.clone();
-                let cmd_context = cmd_context.clone();
-                tokio::task::spawn_blocking(move || {
-                    match crate::apt::list_installed() {
-                        Ok(pkgs) => {
-                            let _ = tx_clone.send(BackendEvent::InstalledPackagesFound(pkgs));
-                        }
-                        Err(e) => {
-                            let _ = tx_clone.send(BackendEvent::Error(format!(
-                                "Failed to list installed: {}",
-                                e
-                            )));
-                        }
-                    }
-                    let _ = tx_clone.send(BackendEvent::TaskFinished(cmd_context));
-                });
+        let runner = self.elevated.clone();
+        tokio::task::spawn_blocking(move || {
+            match crate::apt::mark(runner.as_ref(), &pkg_id, mark) {
+                Ok(()) => {
+                    let _ = tx.send(BackendEvent::PackageMarked(pkg_id.clone(), mark));
+                }
+                Err(e) => {
+                    let _ = tx.send(BackendEvent::Error(format!(
+                        "Failed to mark '{}' as {}: {}",
+                        pkg_id.name,
+                        mark.as_arg(),
+                        e
+                    )));
+                }
             }
-            BackendCommand::ListUpgradable => {
-                let _ = tx.send(BackendEvent::TaskStarted("Checking for updates...".into()));
-                let tx_clone = tx.clone();
-                let cmd_context = cmd_context.clone();
-                tokio::task::spawn_blocking(move || {
-                    match crate::apt::list_upgradable() {
-                        Ok(pkgs) => {
-                            let _ = tx_clone.send(BackendEvent::UpgradablePackagesFound(pkgs));
-                        }
-                        Err(e) => {
-                            let _ = tx_clone.send(BackendEvent::Error(format!(
-                                "Failed to list upgradable: {}",
-                                e
-                            )));
-                        }
-                    }
-                    let _ = tx_clone.send(BackendEvent::TaskFinished(cmd_context));
-                });
+            let _ = tx.send(BackendEvent::TaskFinished(BackendCommand::Mark(pkg_id, mark)));
+        });
+        Ok(())
+    }
+
+    async fn refresh_repos(&self, tx: UnboundedSender<BackendEvent>) -> Result<()> {
+        let backend = self.clone();
+        tokio::spawn(async move {
+            let cmd_context = BackendCommand::RefreshRepos;
+            let _ = tx.send(BackendEvent::TaskStarted("Refreshing repositories...".into()));
+            backend.announce_lock_holder(&tx);
+            match crate::apt::spawn_update(backend.elevated.as_ref()) {
+                Ok(child) => {
+                    backend.stream_command_output(child, cmd_context, tx).await;
+                    backend.invalidate_index();
+                }
+                Err(e) => {
+                    let _ = tx.send(BackendEvent::Error(format!("Refresh failed: {}", e)));
+                    let _ = tx.send(BackendEvent::TaskFinished(cmd_context));
+                }
             }
-            BackendCommand::Search(query) => {
-                let _ = tx.send(BackendEvent::TaskStarted(format!("Searching '{}'...", query)));
-                let tx_clone = tx.clone();
-                let cmd_context = cmd_context.clone();
-                let query = query.clone();
-                tokio::task::spawn_blocking(move || {
-                    match crate::apt::search_packages(&query) {
-                        Ok(pkgs) => {
-                            let _ = tx_clone.send(BackendEvent::SearchResultsFound(pkgs));
-                        }
-                        Err(e) => {
-                            let _ = tx_clone.send(BackendEvent::Error(format!("Search failed: {}", e)));
-                        }
+        });
+        Ok(())
+    }
+
+    async fn upgrade_system(&self, tx: UnboundedSender<BackendEvent>) -> Result<()> {
+        let backend = self.clone();
+        tokio::spawn(async move {
+            let cmd_context = BackendCommand::UpgradeSystem;
+            let _ = tx.send(BackendEvent::TaskStarted("Updating repositories...".into()));
+            backend.announce_lock_holder(&tx);
+            match crate::apt::spawn_update(backend.elevated.as_ref()) {
+                Ok(child) => {
+                    let exit = backend.run_streaming(child, &tx).await;
+                    backend.invalidate_index();
+                    if backend.untrack() {
+                        let _ = tx.send(BackendEvent::Cancelled(cmd_context.clone()));
+                        let _ = tx.send(BackendEvent::TaskFinished(cmd_context));
+                        return;
                     }
-                    let _ = tx_clone.send(BackendEvent::TaskFinished(cmd_context));
-                });
-            }
-            BackendCommand::GetDetails(pkg_id) => {
-                let name = pkg_id.split(';').next().unwrap_or("").to_string();
-                let tx_clone = tx.clone();
-                let id_clone = pkg_id.clone();
-                let cmd_context = cmd_context.clone();
-
-                tokio::task::spawn_blocking(move || {
-                    match crate::apt::get_package_details(&name) {
-                        Ok(details) => {
-                            let mut pkg = Package::from_packagekit(&id_clone, "", "");
-                            pkg.update_details(
-                                &details.description,
-                                &details.license,
-                                details.size,
-                                &details.url,
-                            );
-                            let _ = tx_clone.send(BackendEvent::PackageDetailsFound(pkg));
+                    // Sem listas novas o dist-upgrade trabalharia com as velhas
+                    if !report_exit(exit, &cmd_context, &tx) {
+                        let _ = tx.send(BackendEvent::TaskFinished(cmd_context));
+                        return;
+                    }
+                    let _ = tx.send(BackendEvent::TaskStarted("Upgrading system...".into()));
+                    match crate::apt::spawn_upgrade(backend.dpkg_runner(&tx).as_ref()) {
+                        Ok(child) => {
+                            backend.stream_command_output(child, cmd_context, tx).await;
                         }
                         Err(e) => {
-                            let _ = tx_clone.send(BackendEvent::Error(format!(
-                                "Failed to fetch details for '{}': {}",
-                                name, e
-                            )));
+                            let _ = tx.send(BackendEvent::Error(format!("Upgrade failed: {}", e)));
+                            let _ = tx.send(BackendEvent::TaskFinished(cmd_context));
                         }
                     }
-                    let _ = tx_clone.send(BackendEvent::TaskFinished(cmd_context));
-                });
+                }
+                Err(e) => {
+                    let _ = tx.send(BackendEvent::Error(format!("Update failed: {}", e)));
+                    let _ = tx.send(BackendEvent::TaskFinished(cmd_context));
+                }
             }
-            BackendCommand::Install(pkg_id) => {
-                let name = pkg_id.split(';').next().unwrap_or("").to_string();
-                let child = crate::apt::spawn_install(&name)?;
-                let tx_clone = tx.clone();
-                let cmd_context = cmd_context.clone();
-                tokio::spawn(async move {
-                    let backend = AptBackend {};
-                    backend.stream_command_output(child, cmd_context, tx_clone).await;
-                });
+        });
+        Ok(())
+    }
+
+    async fn autoremove(&self, tx: UnboundedSender<BackendEvent>) -> Result<()> {
+        self.announce_lock_holder(&tx);
+        let child = crate::apt::spawn_autoremove(self.dpkg_runner(&tx).as_ref())?;
+        self.spawn_streaming(child, BackendCommand::Autoremove, tx);
+        Ok(())
+    }
+
+    async fn repair(&self, repair: Repair, tx: UnboundedSender<BackendEvent>) -> Result<()> {
+        let _ = tx.send(BackendEvent::TaskStarted(format!("{}...", repair)));
+        self.announce_lock_holder(&tx);
+        let child = crate::apt::spawn_repair(self.dpkg_runner(&tx).as_ref(), repair)?;
+        self.spawn_streaming(child, BackendCommand::Repair(repair), tx);
+        Ok(())
+    }
+
+    async fn authenticate(&self, password: Password, tx: UnboundedSender<BackendEvent>) -> Result<()> {
+        let _ = tx.send(BackendEvent::TaskStarted("Authenticating...".into()));
+        let elevated = self.elevated.clone();
+        tokio::task::spawn_blocking(move || {
+            let _ = tx.send(match elevated.authenticate(password) {
+                Ok(()) => BackendEvent::Authenticated,
+                Err(e) => BackendEvent::AuthenticationFailed(e.to_string()),
+            });
+        });
+        Ok(())
+    }
+
+    async fn answer_debconf(&self, id: QuestionId, value: Option<String>, _tx: UnboundedSender<BackendEvent>) -> Result<()> {
+        if let Some(frontend) = &self.debconf {
+            frontend.answer(id, value);
+        }
+        Ok(())
+    }
+
+    async fn scan_conffiles(&self, tx: UnboundedSender<BackendEvent>) -> Result<()> {
+        let (leftovers, paths) = (self.leftovers.clone(), self.paths.clone());
+        tokio::task::spawn_blocking(move || {
+            // Sem o status do dpkg os leftovers aparecem sem dono
+            let status = std::fs::read_to_string(&paths.dpkg_status).unwrap_or_default();
+            let owners = crate::dpkg::parse_conffile_owners(&status);
+            match leftovers.scan(&owners) {
+                Ok(conffiles) => {
+                    let _ = tx.send(BackendEvent::ConffilesFound(conffiles));
+                }
+                Err(e) => {
+                    let _ = tx.send(BackendEvent::Error(format!("Cannot look for leftover config files: {}", e)));
+                }
             }
-            BackendCommand::Remove(pkg_id) => {
-                let name = pkg_id.split(';').next().unwrap_or("").to_string();
-                let child = crate::apt::spawn_remove(&name)?;
-                let tx_clone = tx.clone();
-                let cmd_context = cmd_context.clone();
-                tokio::spawn(async move {
-                    let backend = AptBackend {};
-                    backend.stream_command_output(child, cmd_context, tx_clone).await;
-                });
+            let _ = tx.send(BackendEvent::TaskFinished(BackendCommand::ScanConffiles));
+        });
+        Ok(())
+    }
+
+    async fn diff_conffile(&self, path: std::path::PathBuf, tx: UnboundedSender<BackendEvent>) -> Result<()> {
+        let (runner, elevated, leftovers) = (self.runner.clone(), self.elevated.clone(), self.leftovers.clone());
+        tokio::task::spawn_blocking(move || {
+            // Alguns arquivos do /etc só o root lê
+            let diff = leftovers
+                .diff(runner.as_ref(), &path)
+                .or_else(|_| leftovers.diff(elevated.as_ref(), &path));
+            match diff {
+                Ok(diff) => {
+                    let _ = tx.send(BackendEvent::ConffileDiff(path.clone(), diff));
+                }
+                Err(e) => {
+                    let _ = tx.send(BackendEvent::Error(format!("Cannot diff {}: {}", path.display(), e)));
+                }
             }
-            BackendCommand::Reinstall(pkg_id) => {
-                let name = pkg_id.split(';').next().unwrap_or("").to_string();
-                let child = crate::apt::spawn_reinstall(&name)?;
-                let tx_clone = tx.clone();
-                let cmd_context = cmd_context.clone();
-                tokio::spawn(async move {
-                    let backend = AptBackend {};
-                    backend.stream_command_output(child, cmd_context, tx_clone).await;
-                });
+            let _ = tx.send(BackendEvent::TaskFinished(BackendCommand::DiffConffile(path)));
+        });
+        Ok(())
+    }
+
+    async fn resolve_conffile(
+        &self,
+        path: std::path::PathBuf,
+        choice: ConffileChoice,
+        tx: UnboundedSender<BackendEvent>,
+    ) -> Result<()> {
+        let (runner, leftovers) = (self.elevated.clone(), self.leftovers.clone());
+        tokio::task::spawn_blocking(move || {
+            if let Err(e) = leftovers.resolve(runner.as_ref(), &path, choice) {
+                let _ = tx.send(BackendEvent::Error(format!("Failed to update {}: {}", path.display(), e)));
             }
-            BackendCommand::RefreshRepos => {
-                let tx_clone = tx.clone();
-                let cmd_context = cmd_context.clone();
-                tokio::spawn(async move {
-                    let backend = AptBackend {};
-                    let _ = tx_clone.send(BackendEvent::TaskStarted("Refreshing repositories...".into()));
-                    match crate::apt::spawn_update() {
-                        Ok(child) => {
-                            backend.stream_command_output(child, cmd_context, tx_clone).await;
-                        }
-                        Err(e) => {
-                            let _ = tx_clone.send(BackendEvent::Error(format!("Refresh failed: {}", e)));
-                            let _ = tx_clone.send(BackendEvent::TaskFinished(cmd_context));
-                        }
-                    }
-                });
+            let _ = tx.send(BackendEvent::TaskFinished(BackendCommand::ResolveConffile(path, choice)));
+        });
+        Ok(())
+    }
+
+    async fn cancel(&self, tx: UnboundedSender<BackendEvent>) -> Result<()> {
+        let pid = match self.active.lock().unwrap().as_mut() {
+            None => Err("Nothing is running that can be cancelled"),
+            Some(active) if active.dpkg_started => Err(
+                "dpkg is already unpacking packages; interrupting it could leave them \
+                 half-configured, so the operation will run to completion",
+            ),
+            Some(ActiveCommand { pid: None, .. }) => Err("The running command has no process id"),
+            Some(active) => {
+                active.cancel_requested = true;
+                Ok(active.pid.unwrap_or_default())
             }
-            BackendCommand::UpgradeSystem => {
-                let tx_clone = tx.clone();
-                let cmd_context = cmd_context.clone();
-                tokio::spawn(async move {
-                    let backend = AptBackend {};
-                    let _ = tx_clone.send(BackendEvent::TaskStarted("Updating repositories...".into()));
-                    match crate::apt::spawn_update() {
-                        Ok(mut child) => {
-                            let _ = child.wait().await;
-                            let _ = tx_clone.send(BackendEvent::TaskStarted("Upgrading system...".into()));
-                            match crate::apt::spawn_upgrade() {
-                                Ok(child) => {
-                                    backend.stream_command_output(child, cmd_context, tx_clone).await;
-                                }
-                                Err(e) => {
-                                    let _ = tx_clone.send(BackendEvent::Error(format!("Upgrade failed: {}", e)));
-                                    let _ = tx_clone.send(BackendEvent::TaskFinished(cmd_context));
-                                }
-                            }
-                        }
-                        Err(e) => {
-                            let _ = tx_clone.send(BackendEvent::Error(format!("Update failed: {}", e)));
-                            let _ = tx_clone.send(BackendEvent::TaskFinished(cmd_context));
-                        }
-                    }
-                });
+        };
+        let pid = match pid {
+            Ok(pid) => pid,
+            Err(reason) => {
+                let _ = tx.send(BackendEvent::CancelRefused(reason.to_string()));
+                return Ok(());
+            }
+        };
+
+        let _ = tx.send(BackendEvent::TaskStarted("Cancelling...".into()));
+        // O apt roda como root (sudo/pkexec): o kill também precisa
+        if let Err(e) = crate::runner::interrupt(self.elevated.as_ref(), pid) {
+            if let Some(active) = self.active.lock().unwrap().as_mut() {
+                active.cancel_requested = false;
             }
+            let _ = tx.send(BackendEvent::Error(format!("Failed to cancel: {}", e)));
         }
+        Ok(())
+    }
+
+    async fn simulate(&self, cmd: BackendCommand, tx: UnboundedSender<BackendEvent>) -> Result<()> {
+        let spec = match &cmd {
+            BackendCommand::Install(id) => crate::apt::install_spec(id),
+            BackendCommand::Remove(id) => crate::apt::remove_spec(id),
+            BackendCommand::Reinstall(id) => crate::apt::reinstall_spec(id),
+            BackendCommand::Apply(transaction) => crate::apt::transaction_spec(transaction),
+            BackendCommand::UpgradeSystem => crate::apt::upgrade_spec(),
+            BackendCommand::Autoremove => crate::apt::autoremove_spec(),
+            other => anyhow::bail!("{:?} cannot be simulated", other),
+        };
 
+        let _ = tx.send(BackendEvent::TaskStarted("Simulating transaction...".into()));
+        let backend = self.clone();
+        tokio::task::spawn_blocking(move || {
+            match crate::apt::simulate(backend.runner.as_ref(), &spec) {
+                Ok(mut simulation) => {
+                    // Sizes are a nicety: a missing index or status file
+                    // shouldn't keep the user from seeing the preview
+                    if !simulation.is_empty()
+                        && let Ok(index) = backend.package_index(&tx)
+                    {
+                        let installed = crate::apt::list_installed(&backend.paths).unwrap_or_default();
+                        simulation.estimate_sizes(|name, arch| index.get(name, arch), &installed);
+                    }
+                    let _ = tx.send(BackendEvent::SimulationFound(
+                        Box::new(cmd.clone()),
+                        Box::new(simulation),
+                    ));
+                }
+                Err(e) => {
+                    let message = format!("Simulation failed: {}", e);
+                    let simulated = BackendCommand::Simulate(Box::new(cmd.clone()));
+                    let _ = tx.send(match AptError::find(&message) {
+                        Some(error) => BackendEvent::Failed(simulated, error, message),
+                        None => BackendEvent::Error(message),
+                    });
+                }
+            }
+            let _ = tx.send(BackendEvent::TaskFinished(BackendCommand::Simulate(Box::new(cmd))));
+        });
         Ok(())
     }
 }
 
+/// How a streamed command ended.
+struct StreamedExit {
+    status: std::io::Result<Option<i32>>,
+    stderr_tail: Vec<String>,
+    error: Option<AptError>, // Primeira falha reconhecida no stderr
+}
+
+/// Reports how a streamed command ended on `tx` as `Failed` (a recognised
+/// apt error) or `Error`; false when it did not succeed.
+fn report_exit(exit: StreamedExit, cmd_context: &BackendCommand, tx: &UnboundedSender<BackendEvent>) -> bool {
+    let failure = match exit.status {
+        Ok(Some(0)) => return true,
+        Ok(Some(code)) => format!("Command failed with status: exit status: {}", code),
+        Ok(None) => "Command failed: terminated by signal".to_string(),
+        Err(e) => {
+            let _ = tx.send(BackendEvent::Error(format!("Wait failed: {}", e)));
+            return false;
+        }
+    };
+    let message = with_stderr_tail(failure, &exit.stderr_tail);
+    let _ = tx.send(match exit.error {
+        Some(error) => BackendEvent::Failed(cmd_context.clone(), error, message),
+        None => BackendEvent::Error(message),
+    });
+    false
+}
+
+/// How many stderr lines a failed command's error carries.
+const STDERR_TAIL: usize = 5;
+
+/// Appends the last stderr lines, which usually say why apt gave up.
+fn with_stderr_tail(message: String, tail: &[String]) -> String {
+    if tail.is_empty() {
+        message
+    } else {
+        format!("{}\n{}", message, tail.join("\n"))
+    }
+}
+
+/// Lines dpkg prints once it starts changing the system; from here on the
+/// operation must not be interrupted.
+fn is_dpkg_line(line: &str) -> bool {
+    let line = line.trim_start();
+    [
+        "(Reading database",
+        "Selecting previously unselected",
+        "Preparing to unpack",
+        "Unpacking",
+        "Setting up",
+        "Removing",
+        "Purging",
+    ]
+    .iter()
+    .any(|prefix| line.starts_with(prefix))
+}
+
 pub fn parse_apt_signal(line: &str) -> Option<String> {
     let line = line.trim();
     if line.is_empty() {
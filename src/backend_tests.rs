@@ -1,157 +1,1013 @@
 #[cfg(test)]
 mod tests {
-    use crate::backend::{AptBackend, BackendCommand, BackendEvent};
+    use crate::apt::{AptError, AptPaths, Repair};
+    use crate::backend::{AptBackend, BackendCommand, BackendEvent, PackageBackend};
+    use crate::joblog::LogLine;
+    use crate::pkg::{PackageId, PackageMark, PackageState};
+    use crate::runner::{CommandOutput, ReplayRunner};
+    use crate::transaction::{Transaction, TransactionKind};
+    use std::sync::Arc;
     use tokio::sync::mpsc;
 
     #[tokio::test]
     async fn test_backend_initialization() {
-        let backend = AptBackend::new().await;
+        let backend = AptBackend::new(crate::privilege::Privilege::Root).await;
         assert!(backend.is_ok());
     }
 
+    fn ok(stdout: &str) -> CommandOutput {
+        CommandOutput {
+            code: Some(0),
+            stdout: stdout.to_string(),
+            stderr: String::new(),
+        }
+    }
+
+    /// argv prefix of the apt-get runs that stream status records.
+    fn apt_get(args: &[&'static str]) -> Vec<&'static str> {
+        [
+            "apt-get",
+            "-o",
+            "APT::Status-Fd=1",
+            "-o",
+            "Dpkg::Use-Pty=0",
+            "-o",
+            "DPkg::Lock::Timeout=600",
+            "-o",
+            "Dpkg::Options::=--force-confdef",
+            "-o",
+            "Dpkg::Options::=--force-confold",
+        ]
+        .into_iter()
+        .chain(args.iter().copied())
+        .collect()
+    }
+
+    fn backend_with(runner: ReplayRunner) -> (AptBackend, Arc<ReplayRunner>) {
+        let runner = Arc::new(runner);
+        // Sem lock real: um unattended-upgrades na máquina não muda os testes
+        let paths = AptPaths {
+            dpkg_lock: std::env::temp_dir().join("lapt-no-such-lock"),
+            ..AptPaths::default()
+        };
+        (AptBackend::with_runner(runner.clone(), paths), runner)
+    }
+
+    /// Writes fixture files into a fresh per-test directory.
+    fn fixture_dir(test: &str, files: &[(&str, &str)]) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("lapt-{}-{}", test, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        for (name, content) in files {
+            std::fs::write(dir.join(name), content).unwrap();
+        }
+        dir
+    }
+
+    /// Collects events until the backend reports `TaskFinished`.
+    async fn events_until_finished(rx: &mut mpsc::UnboundedReceiver<BackendEvent>) -> Vec<BackendEvent> {
+        let mut events = Vec::new();
+        loop {
+            let event = tokio::time::timeout(tokio::time::Duration::from_secs(2), rx.recv())
+                .await
+                .expect("timed out waiting for TaskFinished")
+                .expect("backend channel closed");
+            let finished = matches!(event, BackendEvent::TaskFinished(_));
+            events.push(event);
+            if finished {
+                return events;
+            }
+        }
+    }
+
+    const APT_SHOW_ADDUSER: &str = "Package: adduser
+Version: 3.137ubuntu1
+Installed-Size: 612 kB
+Homepage: https://salsa.debian.org/debian/adduser
+Description: add and remove users and groups
+ This package includes the 'adduser' and 'deluser' commands.
+";
+
+    const DPKG_STATUS: &str = "Package: adduser
+Status: install ok installed
+Architecture: all
+Version: 3.137ubuntu1
+Description: add and remove users and groups
+
+Package: vim
+Status: install ok installed
+Architecture: amd64
+Version: 2:9.1.0016-1ubuntu7
+Description: Vi IMproved - enhanced vi editor
+";
+
+    const EXTENDED_STATES: &str = "Package: adduser
+Architecture: all
+Auto-Installed: 1
+";
+
+    const MAIN_PACKAGES: &str = "Package: vim
+Version: 2:9.1.0016-1ubuntu7
+Architecture: amd64
+Description: Vi IMproved - enhanced vi editor
+";
+
+    const UNIVERSE_PACKAGES: &str = "Package: neovim
+Version: 0.9.5-6ubuntu2
+Architecture: amd64
+Description: heavily refactored vim fork
+";
+
     #[tokio::test]
     async fn test_backend_get_details_dispatch() {
-        let backend = AptBackend::new().await.unwrap();
+        let (backend, runner) =
+            backend_with(ReplayRunner::new().respond(&["apt", "show"], ok(APT_SHOW_ADDUSER)));
         let (tx, mut rx) = mpsc::unbounded_channel::<BackendEvent>();
-        
-        // Use a known package like 'adduser'
-        let cmd = BackendCommand::GetDetails("adduser;3.137ubuntu1;all;installed:ubuntu-noble-main".to_string());
+
+        let cmd = BackendCommand::GetDetails(PackageId::new("adduser", "3.137ubuntu1", "all", "installed"));
         backend.handle_command(cmd, tx).await.unwrap();
-        
-        let mut found = false;
-        for _ in 0..20 {
-            if let Ok(event) = rx.try_recv() {
-                if let BackendEvent::PackageDetailsFound(pkg) = event {
-                    assert_eq!(pkg.name, "adduser");
-                    found = true;
-                    break;
-                }
-            }
-            tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
-        }
-        assert!(found, "PackageDetailsFound event not received");
+
+        let events = events_until_finished(&mut rx).await;
+        let pkg = events
+            .iter()
+            .find_map(|e| match e {
+                BackendEvent::PackageDetailsFound(pkg) => Some(pkg),
+                _ => None,
+            })
+            .expect("PackageDetailsFound event not received");
+        assert_eq!(pkg.id.name, "adduser");
+        assert_eq!(pkg.url, "https://salsa.debian.org/debian/adduser");
+        assert_eq!(pkg.id.origin, "installed");
+        assert_eq!(runner.calls()[0].argv(), vec!["apt", "show", "adduser:all=3.137ubuntu1"]);
+    }
+
+    #[tokio::test]
+    async fn test_backend_get_details_failure() {
+        let (backend, _) = backend_with(ReplayRunner::new().respond(
+            &["apt", "show"],
+            CommandOutput {
+                code: Some(100),
+                stdout: String::new(),
+                stderr: "E: No packages found".to_string(),
+            },
+        ));
+        let (tx, mut rx) = mpsc::unbounded_channel::<BackendEvent>();
+
+        backend
+            .handle_command(BackendCommand::GetDetails(PackageId::new("ghost", "1.0", "all", "bookworm")), tx)
+            .await
+            .unwrap();
+
+        let events = events_until_finished(&mut rx).await;
+        assert!(events.iter().any(|e| matches!(
+            e,
+            BackendEvent::Error(msg) if msg.contains("ghost") && msg.contains("No packages found")
+        )));
     }
 
     #[tokio::test]
     async fn test_backend_list_installed() {
-        let backend = AptBackend::new().await.unwrap();
+        let dir = fixture_dir(
+            "list-installed",
+            &[("status", DPKG_STATUS), ("extended_states", EXTENDED_STATES)],
+        );
+        let runner = Arc::new(ReplayRunner::new());
+        let backend = AptBackend::with_runner(
+            runner.clone(),
+            AptPaths {
+                dpkg_status: dir.join("status"),
+                extended_states: dir.join("extended_states"),
+                lists_dir: dir.clone(),
+                ..AptPaths::default()
+            },
+        );
         let (tx, mut rx) = mpsc::unbounded_channel::<BackendEvent>();
 
         backend.handle_command(BackendCommand::ListInstalled, tx).await.unwrap();
 
-        let mut found_packages = false;
-        let mut found_finished = false;
-
-        for _ in 0..20 {
-            while let Ok(event) = rx.try_recv() {
-                match event {
-                    BackendEvent::InstalledPackagesFound(pkgs) => {
-                        assert!(!pkgs.is_empty());
-                        found_packages = true;
-                    }
-                    BackendEvent::TaskFinished(BackendCommand::ListInstalled) => {
-                        found_finished = true;
-                    }
-                    _ => {}
+        let events = events_until_finished(&mut rx).await;
+        let pkgs = events
+            .iter()
+            .find_map(|e| match e {
+                BackendEvent::InstalledPackagesFound(pkgs) => Some(pkgs),
+                _ => None,
+            })
+            .expect("InstalledPackagesFound event not received");
+        assert_eq!(pkgs.len(), 2);
+        assert_eq!(pkgs[1].id.name, "vim");
+        assert!(pkgs[0].status.automatic);
+        assert!(!pkgs[1].status.automatic);
+        assert_eq!(events.last(), Some(&BackendEvent::TaskFinished(BackendCommand::ListInstalled)));
+        assert!(runner.calls().is_empty(), "listing installed packages must not spawn apt");
+    }
+
+    #[tokio::test]
+    async fn test_backend_search() {
+        let dir = fixture_dir(
+            "search",
+            &[
+                ("archive.ubuntu.com_ubuntu_dists_noble_main_binary-amd64_Packages", MAIN_PACKAGES),
+                ("archive.ubuntu.com_ubuntu_dists_noble_universe_binary-amd64_Packages.lz4", ""),
+                ("archive.ubuntu.com_ubuntu_dists_noble_InRelease", "not a Packages file"),
+            ],
+        );
+        let compressed = dir.join("archive.ubuntu.com_ubuntu_dists_noble_universe_binary-amd64_Packages.lz4");
+        let runner = Arc::new(ReplayRunner::new().respond(
+            &["/usr/lib/apt/apt-helper", "cat-file", &compressed.to_string_lossy()],
+            ok(UNIVERSE_PACKAGES),
+        ));
+        let backend = AptBackend::with_runner(
+            runner.clone(),
+            AptPaths {
+                lists_dir: dir.clone(),
+                ..AptPaths::default()
+            },
+        );
+
+        for query in ["vim", "fork"] {
+            let (tx, mut rx) = mpsc::unbounded_channel::<BackendEvent>();
+            backend.handle_command(BackendCommand::Search(query.to_string()), tx).await.unwrap();
+
+            let events = events_until_finished(&mut rx).await;
+            let (found_query, pkgs) = events
+                .iter()
+                .find_map(|e| match e {
+                    BackendEvent::SearchResultsFound(q, pkgs) => Some((q, pkgs)),
+                    _ => None,
+                })
+                .expect("Search results not received");
+            assert_eq!(found_query, query);
+            match query {
+                "vim" => {
+                    assert_eq!(pkgs.len(), 2);
+                    assert_eq!(pkgs[0].id.name, "vim");
+                    assert_eq!(pkgs[0].id.origin, "archive.ubuntu.com/ubuntu noble/main");
+                    assert_eq!(pkgs[1].id.name, "neovim");
+                    assert_eq!(pkgs[1].id.origin, "archive.ubuntu.com/ubuntu noble/universe");
+                }
+                _ => {
+                    assert_eq!(pkgs.len(), 1);
+                    assert_eq!(pkgs[0].summary, "heavily refactored vim fork");
                 }
             }
-            if found_packages && found_finished { break; }
-            tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+            assert!(matches!(events.last(), Some(BackendEvent::TaskFinished(BackendCommand::Search(_)))));
         }
 
-        assert!(found_packages, "InstalledPackagesFound event not received");
-        assert!(found_finished, "TaskFinished event not received");
+        // The index is built once and reused across queries
+        assert_eq!(runner.calls().len(), 1);
     }
 
     #[tokio::test]
-    async fn test_backend_search() {
-        let backend = AptBackend::new().await.unwrap();
+    async fn test_backend_install_streams_output() {
+        let (backend, runner) = backend_with(ReplayRunner::new().respond(
+            &apt_get(&["install"]),
+            ok("Reading package lists...\nUnpacking vim (2:9.1) ...\nSetting up vim (2:9.1) ...\n"),
+        ));
         let (tx, mut rx) = mpsc::unbounded_channel::<BackendEvent>();
 
-        backend.handle_command(BackendCommand::Search("vim".to_string()), tx).await.unwrap();
+        backend
+            .handle_command(BackendCommand::Install(PackageId::new("vim", "2:9.1", "amd64", "bookworm")), tx)
+            .await
+            .unwrap();
 
-        let mut found_packages = false;
-        let mut found_finished = false;
+        let events = events_until_finished(&mut rx).await;
+        assert!(events.contains(&BackendEvent::TaskStarted("Setting up vim (2:9.1) ...".to_string())));
+        assert!(!events.iter().any(|e| matches!(e, BackendEvent::Error(_))));
 
-        for _ in 0..20 {
-            while let Ok(event) = rx.try_recv() {
-                match event {
-                    BackendEvent::SearchResultsFound(pkgs) => {
-                        assert!(!pkgs.is_empty());
-                        found_packages = true;
-                    }
-                    BackendEvent::TaskFinished(BackendCommand::Search(_)) => {
-                        found_finished = true;
-                    }
-                    _ => {}
-                }
-            }
-            if found_packages && found_finished { break; }
-            tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+        let calls = runner.calls();
+        assert_eq!(calls[0].argv(), apt_get(&["install", "-y", "vim:amd64=2:9.1"]));
+        assert!(calls[0]
+            .env
+            .contains(&("DEBIAN_FRONTEND".to_string(), "noninteractive".to_string())));
+    }
+
+    #[tokio::test]
+    async fn test_backend_install_reports_progress() {
+        use crate::progress::ProgressPhase;
+
+        let (backend, _) = backend_with(ReplayRunner::new().respond(
+            &apt_get(&["install"]),
+            ok("Need to get 1,536 kB of archives.\n\
+                dlstatus:1:50:Retrieving file 1 of 1\n\
+                pmstatus:dpkg-exec:0:Running dpkg\n\
+                pmstatus:vim:66.6667:Unpacking vim (amd64)\n\
+                Setting up vim (2:9.1) ...\n"),
+        ));
+        let (tx, mut rx) = mpsc::unbounded_channel::<BackendEvent>();
+
+        backend
+            .handle_command(BackendCommand::Install(PackageId::new("vim", "2:9.1", "amd64", "bookworm")), tx)
+            .await
+            .unwrap();
+
+        let events = events_until_finished(&mut rx).await;
+        let progress: Vec<_> = events
+            .iter()
+            .filter_map(|e| match e {
+                BackendEvent::Progress(p) => Some(p),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(progress.len(), 3);
+        assert_eq!(progress[0].phase, ProgressPhase::Download);
+        assert_eq!(progress[0].message, "Retrieving file 1 of 1");
+        assert_eq!(progress[2].phase, ProgressPhase::Install);
+        assert_eq!(progress[2].package, "vim");
+        assert_eq!(progress[2].message, "Unpacking vim (amd64)");
+
+        // Status records are not echoed as plain messages, but the log keeps
+        // everything apt printed
+        assert!(!events.iter().any(|e| matches!(e, BackendEvent::TaskStarted(m) if m.contains("status:"))));
+        let output: Vec<_> = events
+            .iter()
+            .filter_map(|e| match e {
+                BackendEvent::Output(line) => Some(line.text.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(output, vec![
+            "Need to get 1,536 kB of archives.",
+            "dlstatus:1:50:Retrieving file 1 of 1",
+            "pmstatus:dpkg-exec:0:Running dpkg",
+            "pmstatus:vim:66.6667:Unpacking vim (amd64)",
+            "Setting up vim (2:9.1) ...",
+        ]);
+        assert!(events.contains(&BackendEvent::TaskStarted("Setting up vim (2:9.1) ...".to_string())));
+    }
+
+    #[tokio::test]
+    async fn test_backend_unrecognised_failure_stays_an_error() {
+        let (backend, _) = backend_with(ReplayRunner::new().respond(
+            &apt_get(&["autoremove"]),
+            CommandOutput {
+                code: Some(1),
+                stdout: String::new(),
+                stderr: "W: Something odd happened\n".to_string(),
+            },
+        ));
+        let (tx, mut rx) = mpsc::unbounded_channel::<BackendEvent>();
+
+        backend.handle_command(BackendCommand::Autoremove, tx).await.unwrap();
+
+        let events = events_until_finished(&mut rx).await;
+        assert!(events.contains(&BackendEvent::Error(
+            "Command failed with status: exit status: 1\nW: Something odd happened".to_string()
+        )));
+    }
+
+    #[tokio::test]
+    async fn test_backend_repair_commands() {
+        let (backend, runner) = backend_with(
+            ReplayRunner::new()
+                .respond(&["dpkg", "--force-confdef", "--force-confold", "--configure", "-a"], ok("Setting up vim (2:9.1) ...\n"))
+                .respond(&apt_get(&["install", "-f"]), ok(""))
+                .respond(&["apt-get", "clean"], ok("")),
+        );
+
+        for repair in [Repair::ConfigurePending, Repair::FixBroken, Repair::CleanCache] {
+            let (tx, mut rx) = mpsc::unbounded_channel::<BackendEvent>();
+            backend.handle_command(BackendCommand::Repair(repair), tx).await.unwrap();
+            let events = events_until_finished(&mut rx).await;
+            assert_eq!(events.last(), Some(&BackendEvent::TaskFinished(BackendCommand::Repair(repair))));
+            assert!(!events.iter().any(|e| matches!(e, BackendEvent::Error(_))));
         }
 
-        assert!(found_packages, "Search results not received");
-        assert!(found_finished, "TaskFinished event not received");
+        let calls = runner.calls();
+        assert_eq!(calls[0].argv(), vec!["dpkg", "--force-confdef", "--force-confold", "--configure", "-a"]);
+        assert_eq!(calls[1].argv(), apt_get(&["install", "-f", "-y"]));
+        assert_eq!(calls[2].argv(), vec!["apt-get", "clean"]);
+    }
+
+    #[tokio::test]
+    async fn test_backend_announces_lock_holder() {
+        use std::os::unix::fs::MetadataExt;
+
+        let dir = fixture_dir("lock-holder", &[("lock-frontend", "")]);
+        let meta = std::fs::metadata(dir.join("lock-frontend")).unwrap();
+        // st_dev de um disco comum cabe nos 8+8 bits de baixo
+        let table = format!(
+            "1: POSIX  ADVISORY  WRITE 812 {:02x}:{:02x}:{} 0 EOF\n",
+            (meta.dev() >> 8) & 0xfff,
+            meta.dev() & 0xff,
+            meta.ino()
+        );
+        std::fs::write(dir.join("locks"), table).unwrap();
+        std::fs::create_dir_all(dir.join("812")).unwrap();
+        std::fs::write(dir.join("812/cmdline"), b"/usr/bin/unattended-upgrade\0").unwrap();
+
+        let runner = Arc::new(ReplayRunner::new().respond(&apt_get(&["autoremove"]), ok("")));
+        let paths = AptPaths {
+            dpkg_lock: dir.join("lock-frontend"),
+            proc_dir: dir.clone(),
+            ..AptPaths::default()
+        };
+        let backend = AptBackend::with_runner(runner.clone(), paths);
+        let (tx, mut rx) = mpsc::unbounded_channel::<BackendEvent>();
+
+        backend.handle_command(BackendCommand::Autoremove, tx).await.unwrap();
+
+        let events = events_until_finished(&mut rx).await;
+        let holder = crate::lock::LockHolder {
+            pid: 812,
+            cmdline: "/usr/bin/unattended-upgrade".to_string(),
+        };
+        assert_eq!(events.first(), Some(&BackendEvent::WaitingForLock(holder)));
+        // apt itself waits for the lock instead of failing
+        assert!(runner.calls()[0].args.contains(&"DPkg::Lock::Timeout=600".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_backend_remove_failure_reports_status() {
+        let (backend, _) = backend_with(ReplayRunner::new().respond(
+            &apt_get(&["remove"]),
+            CommandOutput {
+                code: Some(100),
+                stdout: String::new(),
+                stderr: "E: Unable to locate package ghost\n".to_string(),
+            },
+        ));
+        let (tx, mut rx) = mpsc::unbounded_channel::<BackendEvent>();
+
+        backend
+            .handle_command(BackendCommand::Remove(PackageId::new("ghost", "1.0", "all", "bookworm")), tx)
+            .await
+            .unwrap();
+
+        let events = events_until_finished(&mut rx).await;
+        let ghost = PackageId::new("ghost", "1.0", "all", "bookworm");
+        assert!(events.contains(&BackendEvent::Failed(
+            BackendCommand::Remove(ghost),
+            AptError::PackageNotFound("ghost".to_string()),
+            "Command failed with status: exit status: 100\nE: Unable to locate package ghost".to_string()
+        )));
+        assert!(events.contains(&BackendEvent::Output(LogLine::stderr("E: Unable to locate package ghost"))));
     }
 
     #[tokio::test]
     async fn test_backend_upgrade_system_dispatch() {
-        let backend = AptBackend::new().await.unwrap();
+        let (backend, runner) = backend_with(
+            ReplayRunner::new()
+                .respond(&apt_get(&["update"]), ok("Hit:1 http://archive.ubuntu.com/ubuntu noble InRelease\n"))
+                .respond(&apt_get(&["dist-upgrade"]), ok("Setting up libc6 (2.39) ...\n")),
+        );
         let (tx, mut rx) = mpsc::unbounded_channel::<BackendEvent>();
 
         backend.handle_command(BackendCommand::UpgradeSystem, tx).await.unwrap();
 
-        let mut found_started = false;
-        let mut found_finished = false;
-
-        for _ in 0..50 { // System upgrade might take longer even for just update
-            while let Ok(event) = rx.try_recv() {
-                match event {
-                    BackendEvent::TaskStarted(_) => {
-                        found_started = true;
-                    }
-                    BackendEvent::TaskFinished(BackendCommand::UpgradeSystem) => {
-                        found_finished = true;
-                    }
-                    _ => {}
-                }
-            }
-            if found_started && found_finished { break; }
-            tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
-        }
+        let events = events_until_finished(&mut rx).await;
+        assert!(events.contains(&BackendEvent::TaskStarted("Upgrading system...".to_string())));
+        assert_eq!(events.last(), Some(&BackendEvent::TaskFinished(BackendCommand::UpgradeSystem)));
+
+        let calls = runner.calls();
+        assert_eq!(calls[0].argv(), apt_get(&["update"]));
+        assert_eq!(calls[1].argv(), apt_get(&["dist-upgrade", "-y"]));
+    }
+
+    #[tokio::test]
+    async fn test_backend_upgrade_stops_when_update_fails() {
+        let (backend, runner) = backend_with(ReplayRunner::new().respond(&apt_get(&["update"]), CommandOutput {
+            code: Some(100),
+            stdout: String::new(),
+            stderr: "E: Failed to fetch http://deb.debian.org/debian/dists/bookworm/main/binary-amd64/Packages.xz  Hash Sum mismatch\n".into(),
+        }));
+        let (tx, mut rx) = mpsc::unbounded_channel::<BackendEvent>();
+
+        backend.handle_command(BackendCommand::UpgradeSystem, tx).await.unwrap();
 
-        assert!(found_started, "TaskStarted event not received");
-        assert!(found_finished, "TaskFinished event not received");
+        let events = events_until_finished(&mut rx).await;
+        assert!(events.iter().any(|e| matches!(
+            e,
+            BackendEvent::Failed(BackendCommand::UpgradeSystem, crate::apt::AptError::HashSumMismatch, _)
+        )));
+        assert!(!events.contains(&BackendEvent::TaskStarted("Upgrading system...".to_string())));
+        assert_eq!(runner.calls().len(), 1, "dist-upgrade must not run on stale lists");
     }
 
     #[tokio::test]
     async fn test_backend_refresh_repos_dispatch() {
-        let backend = AptBackend::new().await.unwrap();
+        let (backend, _) = backend_with(
+            ReplayRunner::new().respond(&apt_get(&["update"]), ok("Get:1 http://archive.ubuntu.com/ubuntu noble InRelease\n")),
+        );
         let (tx, mut rx) = mpsc::unbounded_channel::<BackendEvent>();
 
         backend.handle_command(BackendCommand::RefreshRepos, tx).await.unwrap();
 
-        let mut found_started = false;
-        let mut found_finished = false;
-
-        for _ in 0..20 {
-            while let Ok(event) = rx.try_recv() {
-                match event {
-                    BackendEvent::TaskStarted(_) => {
-                        found_started = true;
-                    }
-                    BackendEvent::TaskFinished(BackendCommand::RefreshRepos) => {
-                        found_finished = true;
-                    }
-                    _ => {}
-                }
-            }
-            if found_started && found_finished { break; }
-            tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+        let events = events_until_finished(&mut rx).await;
+        assert!(events.contains(&BackendEvent::TaskStarted("Refreshing repositories...".to_string())));
+        assert!(events.contains(&BackendEvent::TaskStarted(
+            "Get:1 http://archive.ubuntu.com/ubuntu noble InRelease".to_string()
+        )));
+        assert_eq!(events.last(), Some(&BackendEvent::TaskFinished(BackendCommand::RefreshRepos)));
+    }
+
+    #[tokio::test]
+    async fn test_backend_simulate_upgrade() {
+        let dir = fixture_dir(
+            "simulate",
+            &[
+                ("status", "Package: vim\nStatus: install ok installed\nArchitecture: amd64\nVersion: 2:9.1.0016-1ubuntu7\nInstalled-Size: 4000\n"),
+                (
+                    "archive.ubuntu.com_ubuntu_dists_noble-updates_main_binary-amd64_Packages",
+                    "Package: vim\nVersion: 2:9.1.0016-1ubuntu7.9\nArchitecture: amd64\nInstalled-Size: 4100\nSize: 1700000\nDescription: Vi IMproved\n",
+                ),
+            ],
+        );
+        let runner = Arc::new(ReplayRunner::new().respond(
+            &["apt-get", "-s", "dist-upgrade"],
+            ok("NOTE: This is only a simulation!\nInst vim [2:9.1.0016-1ubuntu7] (2:9.1.0016-1ubuntu7.9 Ubuntu:24.04/noble-updates [amd64])\nConf vim (2:9.1.0016-1ubuntu7.9 Ubuntu:24.04/noble-updates [amd64])\n"),
+        ));
+        let backend = AptBackend::with_runner(
+            runner.clone(),
+            AptPaths {
+                dpkg_status: dir.join("status"),
+                extended_states: dir.join("extended_states"),
+                lists_dir: dir.clone(),
+                ..AptPaths::default()
+            },
+        );
+        let (tx, mut rx) = mpsc::unbounded_channel::<BackendEvent>();
+
+        backend
+            .handle_command(BackendCommand::Simulate(Box::new(BackendCommand::UpgradeSystem)), tx)
+            .await
+            .unwrap();
+
+        let events = events_until_finished(&mut rx).await;
+        let (cmd, simulation) = events
+            .iter()
+            .find_map(|e| match e {
+                BackendEvent::SimulationFound(cmd, simulation) => Some((cmd, simulation)),
+                _ => None,
+            })
+            .expect("SimulationFound event not received");
+        assert_eq!(**cmd, BackendCommand::UpgradeSystem);
+        assert_eq!(simulation.upgrade.len(), 1);
+        assert_eq!(simulation.upgrade[0].name, "vim");
+        assert_eq!(simulation.download_size, 1700000);
+        assert_eq!(simulation.disk_delta, 100 * 1024);
+
+        // Same arguments as the real run, plus -s, and nothing else executed
+        let calls = runner.calls();
+        assert_eq!(calls.len(), 1);
+        assert_eq!(calls[0].argv(), vec!["apt-get", "-s", "dist-upgrade", "-y"]);
+    }
+
+    #[tokio::test]
+    async fn test_backend_simulate_failure() {
+        let (backend, _) = backend_with(ReplayRunner::new().respond(
+            &["apt-get", "-s", "remove"],
+            CommandOutput {
+                code: Some(100),
+                stdout: String::new(),
+                stderr: "E: Error, pkgProblemResolver::Resolve generated breaks".to_string(),
+            },
+        ));
+        let (tx, mut rx) = mpsc::unbounded_channel::<BackendEvent>();
+
+        let cmd = BackendCommand::Remove(PackageId::new("adduser", "3.134", "all", "installed"));
+        backend.handle_command(BackendCommand::Simulate(Box::new(cmd)), tx).await.unwrap();
+
+        let events = events_until_finished(&mut rx).await;
+        assert!(events.iter().any(|e| matches!(
+            e,
+            BackendEvent::Error(msg) if msg.starts_with("Simulation failed") && msg.contains("generated breaks")
+        )));
+        assert!(!events.iter().any(|e| matches!(e, BackendEvent::SimulationFound(..))));
+    }
+
+    #[tokio::test]
+    async fn test_backend_list_orphans() {
+        let dir = fixture_dir(
+            "list-orphans",
+            &[("status", DPKG_STATUS), ("extended_states", EXTENDED_STATES)],
+        );
+        let runner = Arc::new(ReplayRunner::new().respond(
+            &["apt-get", "-s", "autoremove"],
+            ok("The following packages will be REMOVED:\n  adduser\n0 upgraded, 0 newly installed, 1 to remove and 0 not upgraded.\nRemv adduser [3.137ubuntu1]\n"),
+        ));
+        let backend = AptBackend::with_runner(
+            runner.clone(),
+            AptPaths {
+                dpkg_status: dir.join("status"),
+                extended_states: dir.join("extended_states"),
+                lists_dir: dir.clone(),
+                ..AptPaths::default()
+            },
+        );
+        let (tx, mut rx) = mpsc::unbounded_channel::<BackendEvent>();
+
+        backend.handle_command(BackendCommand::ListOrphans, tx).await.unwrap();
+
+        let events = events_until_finished(&mut rx).await;
+        let pkgs = events
+            .iter()
+            .find_map(|e| match e {
+                BackendEvent::OrphanPackagesFound(pkgs) => Some(pkgs),
+                _ => None,
+            })
+            .expect("OrphanPackagesFound event not received");
+        assert_eq!(pkgs.len(), 1);
+        assert_eq!(pkgs[0].id.name, "adduser");
+        assert_eq!(pkgs[0].summary, "add and remove users and groups");
+        assert_eq!(runner.calls()[0].argv(), vec!["apt-get", "-s", "autoremove", "-y"]);
+    }
+
+    #[tokio::test]
+    async fn test_backend_autoremove_streams_output() {
+        let (backend, runner) = backend_with(ReplayRunner::new().respond(
+            &apt_get(&["autoremove"]),
+            ok("Removing adduser (3.137ubuntu1) ...\n"),
+        ));
+        let (tx, mut rx) = mpsc::unbounded_channel::<BackendEvent>();
+
+        backend.handle_command(BackendCommand::Autoremove, tx).await.unwrap();
+
+        let events = events_until_finished(&mut rx).await;
+        assert!(events.contains(&BackendEvent::TaskStarted("Removing adduser (3.137ubuntu1) ...".to_string())));
+        assert_eq!(events.last(), Some(&BackendEvent::TaskFinished(BackendCommand::Autoremove)));
+        assert_eq!(runner.calls()[0].argv(), apt_get(&["autoremove", "-y"]));
+    }
+
+    #[tokio::test]
+    async fn test_backend_apply_transaction_in_one_invocation() {
+        let (backend, runner) = backend_with(
+            ReplayRunner::new()
+                .respond(&["apt-get", "-s", "purge"], ok("Purg nano [7.2-1]\nPurg vim:amd64 [2:9.1]\n"))
+                .respond(&apt_get(&["install"]), ok("Setting up git (1:2.39.5-0+deb12u2) ...\n")),
+        );
+        let vim = PackageId::new("vim", "2:9.1", "amd64", "installed");
+        let nano = PackageId::new("nano", "7.2-1", "", "installed");
+
+        let purge = Transaction::new(TransactionKind::Purge, vec![nano.clone(), vim.clone()]);
+        let (tx, mut rx) = mpsc::unbounded_channel::<BackendEvent>();
+        backend
+            .handle_command(BackendCommand::Simulate(Box::new(BackendCommand::Apply(purge))), tx)
+            .await
+            .unwrap();
+        let events = events_until_finished(&mut rx).await;
+        let simulation = events
+            .iter()
+            .find_map(|e| match e {
+                BackendEvent::SimulationFound(_, sim) => Some(sim),
+                _ => None,
+            })
+            .expect("SimulationFound event not received");
+        assert_eq!(simulation.remove.len(), 2);
+
+        let git = PackageId::new("git", "1:2.39.5-0+deb12u2", "amd64", "bookworm");
+        let install = Transaction::new(TransactionKind::Install, vec![git, vim]);
+        let (tx, mut rx) = mpsc::unbounded_channel::<BackendEvent>();
+        backend.handle_command(BackendCommand::Apply(install.clone()), tx).await.unwrap();
+        let events = events_until_finished(&mut rx).await;
+        assert_eq!(events.last(), Some(&BackendEvent::TaskFinished(BackendCommand::Apply(install))));
+
+        // The package index may be read from the host to estimate sizes
+        let calls: Vec<_> = runner.calls().into_iter().filter(|c| c.program == "apt-get").collect();
+        assert_eq!(calls[0].argv(), vec!["apt-get", "-s", "purge", "-y", "nano", "vim:amd64"]);
+        assert_eq!(
+            calls[1].argv(),
+            apt_get(&["install", "-y", "git:amd64=1:2.39.5-0+deb12u2", "vim:amd64=2:9.1"])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_backend_mark_auto() {
+        let (backend, runner) = backend_with(
+            ReplayRunner::new().respond(&["apt-mark", "auto"], ok("vim set to automatically installed.\n")),
+        );
+        let (tx, mut rx) = mpsc::unbounded_channel::<BackendEvent>();
+
+        let id = PackageId::new("vim", "2:9.1", "amd64", "installed");
+        backend.handle_command(BackendCommand::Mark(id.clone(), PackageMark::Auto), tx).await.unwrap();
+
+        let events = events_until_finished(&mut rx).await;
+        assert_eq!(events[0], BackendEvent::PackageMarked(id, PackageMark::Auto));
+        assert_eq!(runner.calls()[0].argv(), vec!["apt-mark", "auto", "vim:amd64"]);
+    }
+
+    #[tokio::test]
+    async fn test_backend_hold_and_unhold() {
+        let (backend, runner) = backend_with(
+            ReplayRunner::new()
+                .respond(&["apt-mark", "hold"], ok("linux-image-amd64 set on hold.\n"))
+                .respond(&["apt-mark", "unhold"], ok("Canceled hold on linux-image-amd64.\n")),
+        );
+        let id = PackageId::new("linux-image-amd64", "6.1.158-1", "amd64", "installed");
+
+        for mark in [PackageMark::Hold, PackageMark::Unhold] {
+            let (tx, mut rx) = mpsc::unbounded_channel::<BackendEvent>();
+            backend.handle_command(BackendCommand::Mark(id.clone(), mark), tx).await.unwrap();
+            let events = events_until_finished(&mut rx).await;
+            assert_eq!(events[0], BackendEvent::PackageMarked(id.clone(), mark));
+        }
+        let calls = runner.calls();
+        assert_eq!(calls[0].argv(), vec!["apt-mark", "hold", "linux-image-amd64:amd64"]);
+        assert_eq!(calls[1].argv(), vec!["apt-mark", "unhold", "linux-image-amd64:amd64"]);
+    }
+
+    #[tokio::test]
+    async fn test_backend_mark_failure() {
+        let (backend, _) = backend_with(ReplayRunner::new().respond(
+            &["apt-mark", "manual"],
+            CommandOutput {
+                code: Some(100),
+                stdout: String::new(),
+                stderr: "E: Could not open lock file /var/lib/dpkg/lock-frontend".to_string(),
+            },
+        ));
+        let (tx, mut rx) = mpsc::unbounded_channel::<BackendEvent>();
+
+        let id = PackageId::new("vim", "2:9.1", "amd64", "installed");
+        backend.handle_command(BackendCommand::Mark(id, PackageMark::Manual), tx).await.unwrap();
+
+        let events = events_until_finished(&mut rx).await;
+        assert!(events.iter().any(|e| matches!(
+            e,
+            BackendEvent::Error(msg) if msg.starts_with("Failed to mark 'vim' as manual") && msg.contains("lock file")
+        )));
+        assert!(!events.iter().any(|e| matches!(e, BackendEvent::PackageMarked(..))));
+    }
+
+    /// Runs `script` with sh in place of every spawned command, and runs
+    /// queries (kill) for real, so cancellation hits a live process.
+    struct ScriptRunner {
+        script: &'static str,
+        calls: std::sync::Mutex<Vec<crate::runner::CommandSpec>>,
+    }
+
+    impl ScriptRunner {
+        fn backend(script: &'static str) -> (AptBackend, Arc<ScriptRunner>) {
+            let runner = Arc::new(ScriptRunner { script, calls: Default::default() });
+            (AptBackend::with_runner(runner.clone(), AptPaths::default()), runner)
+        }
+    }
+
+    impl crate::runner::CommandRunner for ScriptRunner {
+        fn output(&self, spec: &crate::runner::CommandSpec) -> anyhow::Result<CommandOutput> {
+            self.calls.lock().unwrap().push(spec.clone());
+            crate::runner::SystemRunner.output(spec)
+        }
+
+        fn spawn(&self, _spec: &crate::runner::CommandSpec) -> anyhow::Result<crate::runner::RunningCommand> {
+            crate::runner::SystemRunner.spawn(&crate::runner::CommandSpec::new("sh").args(["-c", self.script]))
+        }
+    }
+
+    /// Waits for the `TaskStarted` progress line `msg`.
+    async fn wait_for_progress(rx: &mut mpsc::UnboundedReceiver<BackendEvent>, msg: &str) {
+        let expected = BackendEvent::TaskStarted(msg.to_string());
+        while tokio::time::timeout(tokio::time::Duration::from_secs(2), rx.recv())
+            .await
+            .expect("timed out waiting for progress")
+            .expect("backend channel closed")
+            != expected
+        {}
+    }
+
+    #[tokio::test]
+    async fn test_backend_cancel_during_download() {
+        let (backend, runner) = ScriptRunner::backend(
+            "echo 'Get:1 http://deb.debian.org/debian bookworm/main amd64 vim amd64 2:9.1 [1,234 kB]'; exec sleep 5",
+        );
+        let (tx, mut rx) = mpsc::unbounded_channel::<BackendEvent>();
+        let id = PackageId::new("vim", "2:9.1", "amd64", "bookworm");
+
+        backend.handle_command(BackendCommand::Install(id.clone()), tx.clone()).await.unwrap();
+        wait_for_progress(&mut rx, "Get:1 http://deb.debian.org/debian bookworm/main amd64 vim amd64 2:9.1 [1,234 kB]").await;
+        backend.handle_command(BackendCommand::Cancel, tx).await.unwrap();
+
+        let events = events_until_finished(&mut rx).await;
+        assert!(events.contains(&BackendEvent::Cancelled(BackendCommand::Install(id))));
+        assert!(!events.iter().any(|e| matches!(e, BackendEvent::Error(_))));
+        let kill = &runner.calls.lock().unwrap()[0];
+        assert_eq!(&kill.argv()[..2], &["kill", "-INT"]);
+    }
+
+    #[tokio::test]
+    async fn test_backend_cancel_is_elevated() {
+        let (backend, runner) = ScriptRunner::backend(
+            "echo 'Get:1 http://deb.debian.org/debian bookworm/main amd64 vim amd64 2:9.1 [1,234 kB]'; exec sleep 1",
+        );
+        let backend = backend.with_privilege(crate::privilege::Privilege::Sudo);
+        let (tx, mut rx) = mpsc::unbounded_channel::<BackendEvent>();
+
+        backend.handle_command(BackendCommand::Install(PackageId::new("vim", "", "", "")), tx.clone()).await.unwrap();
+        wait_for_progress(&mut rx, "Get:1 http://deb.debian.org/debian bookworm/main amd64 vim amd64 2:9.1 [1,234 kB]").await;
+        backend.handle_command(BackendCommand::Cancel, tx).await.unwrap();
+        events_until_finished(&mut rx).await;
+
+        // apt-get runs as root under sudo, so only root may signal it
+        let kill = runner.calls.lock().unwrap()[0].clone();
+        assert_eq!(&kill.argv()[..6], &["sudo", "-S", "-k", "-p", "", "env"]);
+        assert_eq!(&kill.argv()[6..8], &["kill", "-INT"]);
+    }
+
+    #[tokio::test]
+    async fn test_backend_cancel_refused_once_dpkg_runs() {
+        let (backend, runner) = ScriptRunner::backend("echo 'Unpacking vim (2:9.1) over (2:9.0) ...'; exec sleep 1");
+        let (tx, mut rx) = mpsc::unbounded_channel::<BackendEvent>();
+        let id = PackageId::new("vim", "2:9.1", "amd64", "bookworm");
+
+        backend.handle_command(BackendCommand::Install(id), tx.clone()).await.unwrap();
+        wait_for_progress(&mut rx, "Unpacking vim (2:9.1) over (2:9.0) ...").await;
+        backend.handle_command(BackendCommand::Cancel, tx).await.unwrap();
+
+        let events = events_until_finished(&mut rx).await;
+        assert!(events.iter().any(|e| matches!(e, BackendEvent::CancelRefused(reason) if reason.contains("dpkg"))));
+        assert!(!events.iter().any(|e| matches!(e, BackendEvent::Cancelled(_))));
+        assert!(runner.calls.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_backend_cancel_with_nothing_running() {
+        let (backend, _) = backend_with(ReplayRunner::new());
+        let (tx, mut rx) = mpsc::unbounded_channel::<BackendEvent>();
+
+        backend.handle_command(BackendCommand::Cancel, tx).await.unwrap();
+        assert_eq!(
+            rx.recv().await,
+            Some(BackendEvent::CancelRefused("Nothing is running that can be cancelled".to_string()))
+        );
+    }
+
+    struct MockBackend;
+
+    impl PackageBackend for MockBackend {
+        async fn list_installed(&self, tx: mpsc::UnboundedSender<BackendEvent>) -> anyhow::Result<()> {
+            let pkg = crate::pkg::Package::from_packagekit("mock;1.0;all;mock", PackageState::Installed, "");
+            let _ = tx.send(BackendEvent::InstalledPackagesFound(vec![pkg]));
+            let _ = tx.send(BackendEvent::TaskFinished(BackendCommand::ListInstalled));
+            Ok(())
+        }
+
+        async fn list_upgradable(&self, _tx: mpsc::UnboundedSender<BackendEvent>) -> anyhow::Result<()> {
+            Ok(())
+        }
+
+        async fn list_orphans(&self, _tx: mpsc::UnboundedSender<BackendEvent>) -> anyhow::Result<()> {
+            Ok(())
+        }
+
+        async fn search(&self, _query: String, _tx: mpsc::UnboundedSender<BackendEvent>) -> anyhow::Result<()> {
+            Ok(())
+        }
+
+        async fn get_details(&self, _pkg_id: PackageId, _tx: mpsc::UnboundedSender<BackendEvent>) -> anyhow::Result<()> {
+            Ok(())
+        }
+
+        async fn install(&self, pkg_id: PackageId, _tx: mpsc::UnboundedSender<BackendEvent>) -> anyhow::Result<()> {
+            anyhow::bail!("cannot install {}", pkg_id.apt_target())
+        }
+
+        async fn remove(&self, _pkg_id: PackageId, _tx: mpsc::UnboundedSender<BackendEvent>) -> anyhow::Result<()> {
+            Ok(())
+        }
+
+        async fn reinstall(&self, _pkg_id: PackageId, _tx: mpsc::UnboundedSender<BackendEvent>) -> anyhow::Result<()> {
+            Ok(())
+        }
+
+        async fn refresh_repos(&self, _tx: mpsc::UnboundedSender<BackendEvent>) -> anyhow::Result<()> {
+            Ok(())
+        }
+
+        async fn upgrade_system(&self, _tx: mpsc::UnboundedSender<BackendEvent>) -> anyhow::Result<()> {
+            Ok(())
         }
 
-        assert!(found_started, "TaskStarted event not received");
-        assert!(found_finished, "TaskFinished event not received");
+        async fn autoremove(&self, _tx: mpsc::UnboundedSender<BackendEvent>) -> anyhow::Result<()> {
+            Ok(())
+        }
+
+        async fn repair(&self, _repair: Repair, _tx: mpsc::UnboundedSender<BackendEvent>) -> anyhow::Result<()> {
+            Ok(())
+        }
+
+        async fn apply(&self, _transaction: Transaction, _tx: mpsc::UnboundedSender<BackendEvent>) -> anyhow::Result<()> {
+            Ok(())
+        }
+
+        async fn authenticate(&self, _password: crate::privilege::Password, _tx: mpsc::UnboundedSender<BackendEvent>) -> anyhow::Result<()> {
+            Ok(())
+        }
+
+        async fn answer_debconf(&self, _id: crate::debconf::QuestionId, _value: Option<String>, _tx: mpsc::UnboundedSender<BackendEvent>) -> anyhow::Result<()> {
+            Ok(())
+        }
+
+        async fn scan_conffiles(&self, _tx: mpsc::UnboundedSender<BackendEvent>) -> anyhow::Result<()> {
+            Ok(())
+        }
+
+        async fn diff_conffile(&self, _path: std::path::PathBuf, _tx: mpsc::UnboundedSender<BackendEvent>) -> anyhow::Result<()> {
+            Ok(())
+        }
+
+        async fn resolve_conffile(&self, _path: std::path::PathBuf, _choice: crate::conffile::ConffileChoice, _tx: mpsc::UnboundedSender<BackendEvent>) -> anyhow::Result<()> {
+            Ok(())
+        }
+
+        async fn mark(
+            &self,
+            _pkg_id: PackageId,
+            _mark: PackageMark,
+            _tx: mpsc::UnboundedSender<BackendEvent>,
+        ) -> anyhow::Result<()> {
+            Ok(())
+        }
+
+        async fn simulate(&self, _cmd: BackendCommand, _tx: mpsc::UnboundedSender<BackendEvent>) -> anyhow::Result<()> {
+            Ok(())
+        }
+
+        async fn cancel(&self, _tx: mpsc::UnboundedSender<BackendEvent>) -> anyhow::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_run_backend_with_mock() {
+        use crate::backend::run_backend;
+
+        let (tx_cmd, rx_cmd) = mpsc::unbounded_channel::<BackendCommand>();
+        let (tx, mut rx) = mpsc::unbounded_channel::<BackendEvent>();
+
+        tx_cmd.send(BackendCommand::ListInstalled).unwrap();
+        tx_cmd.send(BackendCommand::Install(PackageId::new("vim", "1.0", "amd64", "bookworm"))).unwrap();
+        drop(tx_cmd);
+
+        run_backend(MockBackend, rx_cmd, tx).await;
+
+        // Queue bookkeeping is interleaved with the commands' own events
+        let mut events = std::iter::from_fn(|| rx.try_recv().ok())
+            .filter(|e| !matches!(e, BackendEvent::QueueUpdated(_)));
+        match events.next() {
+            Some(BackendEvent::InstalledPackagesFound(pkgs)) => assert_eq!(pkgs[0].id.name, "mock"),
+            other => panic!("Expected InstalledPackagesFound, got {:?}", other),
+        }
+        assert_eq!(events.next(), Some(BackendEvent::TaskFinished(BackendCommand::ListInstalled)));
+        assert_eq!(
+            events.next(),
+            Some(BackendEvent::Error("cannot install vim:amd64=1.0".to_string()))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_run_backend_serializes_mutating_jobs() {
+        use crate::backend::run_backend;
+        use crate::scheduler::JobState;
+
+        let (backend, runner) = backend_with(
+            ReplayRunner::new()
+                .respond(&apt_get(&["update"]), ok("Hit:1 http://deb.debian.org/debian bookworm InRelease\n"))
+                .respond(&apt_get(&["dist-upgrade"]), ok("Setting up vim (2:9.1) ...\n"))
+                .respond(&apt_get(&["autoremove"]), ok("Removing nano (7.2-1) ...\n")),
+        );
+        let (tx_cmd, rx_cmd) = mpsc::unbounded_channel::<BackendCommand>();
+        let (tx, mut rx) = mpsc::unbounded_channel::<BackendEvent>();
+
+        tx_cmd.send(BackendCommand::UpgradeSystem).unwrap();
+        tx_cmd.send(BackendCommand::Autoremove).unwrap();
+        tx_cmd.send(BackendCommand::RefreshRepos).unwrap();
+        tx_cmd.send(BackendCommand::DropJob(3)).unwrap();
+        let worker = tokio::spawn(run_backend(backend, rx_cmd, tx));
+
+        let mut events = Vec::new();
+        let final_jobs = loop {
+            let event = tokio::time::timeout(std::time::Duration::from_secs(5), rx.recv())
+                .await
+                .expect("timed out waiting for the queue to drain")
+                .unwrap();
+            events.push(event.clone());
+            if let BackendEvent::QueueUpdated(jobs) = event
+                && jobs.len() == 2
+                && jobs.iter().all(|j| j.state == JobState::Done)
+            {
+                break jobs;
+            }
+        };
+        drop(tx_cmd);
+        worker.await.unwrap();
+
+        assert_eq!(final_jobs.iter().map(|j| j.id).collect::<Vec<_>>(), vec![1, 2]);
+        let upgrade_finished = events
+            .iter()
+            .position(|e| *e == BackendEvent::TaskFinished(BackendCommand::UpgradeSystem))
+            .unwrap();
+        let autoremove_started = events
+            .iter()
+            .position(|e| matches!(e, BackendEvent::QueueUpdated(jobs) if jobs.get(1).is_some_and(|j| j.state == JobState::Running)))
+            .unwrap();
+        assert!(upgrade_finished < autoremove_started);
+
+        // The dropped refresh never ran
+        let argvs: Vec<String> = runner.calls().iter().map(|c| c.argv().join(" ")).collect();
+        let expected: Vec<String> = [&["update"][..], &["dist-upgrade", "-y"], &["autoremove", "-y"]]
+            .into_iter()
+            .map(|args| apt_get(args).join(" "))
+            .collect();
+        assert_eq!(argvs, expected);
     }
 
     #[test]
@@ -163,4 +1019,98 @@ mod tests {
         assert_eq!(parse_apt_signal("Hit:1 http://archive.ubuntu.com/ubuntu noble InRelease"), Some("Hit:1 http://archive.ubuntu.com/ubuntu noble InRelease".to_string()));
         assert_eq!(parse_apt_signal("Not interesting output"), None);
     }
+
+    #[tokio::test]
+    async fn test_backend_debconf_passthrough() {
+        let dir = std::env::temp_dir().join(format!("lapt-debconf-backend-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let (backend, runner) = backend_with(ReplayRunner::new().respond(&apt_get(&["install"]), ok("")));
+        let backend = backend.with_debconf(&dir);
+        let (tx, mut rx) = mpsc::unbounded_channel::<BackendEvent>();
+
+        backend
+            .handle_command(BackendCommand::Install(PackageId::new("tzdata", "", "", "")), tx)
+            .await
+            .unwrap();
+        events_until_finished(&mut rx).await;
+
+        let env = &runner.calls()[0].env;
+        assert!(env.contains(&("DEBIAN_FRONTEND".to_string(), "passthrough".to_string())));
+        let socket = dir.join("debconf-1.sock");
+        assert!(env.contains(&("DEBCONF_PIPE".to_string(), socket.to_string_lossy().into_owned())));
+        // The socket only lives as long as the command
+        assert!(!socket.exists());
+    }
+
+    #[test]
+    fn test_system_runner_keeps_non_utf8_stdout() {
+        use crate::runner::{CommandRunner, CommandSpec, SystemRunner};
+
+        // A Latin-1 changelog or description must not fail the whole command
+        let output = SystemRunner
+            .output(&CommandSpec::new("sh").args(["-c", "printf 'caf\\351\\n'; printf 'na\\357ve' >&2"]))
+            .unwrap();
+        assert!(output.success());
+        assert_eq!(output.stdout, "caf\u{FFFD}\n");
+        assert_eq!(output.stderr, "na\u{FFFD}ve");
+    }
+
+    #[tokio::test]
+    async fn test_debconf_fallback_does_not_fail_the_job() {
+        use crate::backend::run_backend;
+        use crate::scheduler::JobState;
+
+        // A directory lapt did not create is refused, so debconf keeps its defaults
+        let dir = std::env::temp_dir().join(format!("lapt-debconf-fallback-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir(&dir).unwrap();
+        let (backend, runner) = backend_with(ReplayRunner::new().respond(&apt_get(&["install"]), ok("")));
+        let backend = backend.with_debconf(&dir);
+        let (tx_cmd, rx_cmd) = mpsc::unbounded_channel::<BackendCommand>();
+        let (tx, mut rx) = mpsc::unbounded_channel::<BackendEvent>();
+        tx_cmd.send(BackendCommand::Install(PackageId::new("tzdata", "", "", ""))).unwrap();
+        tokio::spawn(run_backend(backend, rx_cmd, tx));
+
+        let mut events = Vec::new();
+        loop {
+            let event = tokio::time::timeout(std::time::Duration::from_secs(5), rx.recv())
+                .await
+                .expect("timed out waiting for the job")
+                .unwrap();
+            events.push(event.clone());
+            if let BackendEvent::QueueUpdated(jobs) = event
+                && jobs[0].state.is_finished()
+            {
+                assert_eq!(jobs[0].state, JobState::Done);
+                break;
+            }
+        }
+        std::fs::remove_dir(&dir).unwrap();
+
+        assert!(events.iter().any(|e| matches!(e, BackendEvent::Warning(m) if m.contains("using their defaults"))));
+        assert!(!events.iter().any(|e| matches!(e, BackendEvent::Error(_))));
+        let env = &runner.calls()[0].env;
+        assert!(env.contains(&("DEBIAN_FRONTEND".to_string(), "noninteractive".to_string())));
+    }
+
+    #[tokio::test]
+    async fn test_backend_reports_kept_conffiles() {
+        let output = "Setting up openssh-server (1:9.6p1-3) ...\n\
+                      Configuration file '/etc/ssh/sshd_config'\n \
+                      ==> Modified (by you or by a script) since installation.\n \
+                      ==> Keeping old config file as default.\n";
+        let (backend, _) = backend_with(ReplayRunner::new().respond(&apt_get(&["install"]), ok(output)));
+        let (tx, mut rx) = mpsc::unbounded_channel::<BackendEvent>();
+
+        backend
+            .handle_command(BackendCommand::Install(PackageId::new("openssh-server", "", "", "")), tx)
+            .await
+            .unwrap();
+        let events = events_until_finished(&mut rx).await;
+        assert!(events.contains(&BackendEvent::ConffileKept(crate::conffile::Conffile {
+            path: std::path::PathBuf::from("/etc/ssh/sshd_config"),
+            package: "openssh-server".to_string(),
+            leftover: crate::conffile::Leftover::DpkgDist,
+        })));
+    }
 }
\ No newline at end of file
@@ -0,0 +1,422 @@
+use crate::runner::{CommandRunner, CommandSpec};
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use strum::{Display, EnumIter, IntoEnumIterator};
+
+/// Where the maintainer scripts keep their config files.
+pub const CONFIG_DIR: &str = "/etc";
+
+/// The other version dpkg or ucf left next to a config file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display, EnumIter, Serialize, Deserialize)]
+pub enum Leftover {
+    #[strum(to_string = ".dpkg-dist")]
+    DpkgDist, // Versão do mantenedor; a sua ficou (confold)
+    #[strum(to_string = ".dpkg-new")]
+    DpkgNew, // Versão do mantenedor de um unpack que não terminou
+    #[strum(to_string = ".dpkg-old")]
+    DpkgOld, // A sua antiga; a do mantenedor entrou (confnew)
+    #[strum(to_string = ".ucf-dist")]
+    UcfDist, // Versão do mantenedor, pelo ucf
+}
+
+impl Leftover {
+    /// Splits `/etc/foo.conf.dpkg-dist` into the file it belongs to and
+    /// its kind.
+    pub fn split(path: &Path) -> Option<(PathBuf, Leftover)> {
+        let name = path.to_str()?;
+        Leftover::iter().find_map(|leftover| {
+            let config = name.strip_suffix(&leftover.to_string())?;
+            (!config.is_empty()).then(|| (PathBuf::from(config), leftover))
+        })
+    }
+}
+
+/// A config file with another version of it next to it: the maintainer's
+/// one dpkg did not install over your changes, or yours it replaced.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Conffile {
+    pub path: PathBuf,
+    pub package: String, // Vazio se nenhum pacote declara o arquivo
+    pub leftover: Leftover,
+}
+
+impl Conffile {
+    pub fn leftover_path(&self) -> PathBuf {
+        leftover_path(&self.path, self.leftover)
+    }
+}
+
+fn leftover_path(path: &Path, leftover: Leftover) -> PathBuf {
+    let mut other = path.as_os_str().to_owned();
+    other.push(leftover.to_string());
+    PathBuf::from(other)
+}
+
+/// What to do with the leftover once its diff has been read. Keeping both
+/// is just leaving the files alone.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display, Serialize, Deserialize)]
+pub enum ConffileChoice {
+    Adopt,   // O leftover entra no lugar; o que estava em uso fica ao lado
+    Discard, // Apaga o leftover
+}
+
+/// Follows dpkg's output for the conffiles it keeps:
+///
+/// ```text
+/// Setting up openssh-server (1:9.6p1-3) ...
+/// Configuration file '/etc/ssh/sshd_config'
+///  ==> Modified (by you or by a script) since installation.
+///  ==> Package distributor has shipped an updated version.
+///  ==> Keeping old config file as default.
+/// ```
+#[derive(Debug, Default)]
+pub struct ConffileTracker {
+    package: String,
+    path: Option<PathBuf>,
+}
+
+impl ConffileTracker {
+    pub fn line(&mut self, line: &str) -> Option<Conffile> {
+        let line = line.trim();
+        if let Some(rest) = line.strip_prefix("Setting up ") {
+            let name = rest.split_whitespace().next().unwrap_or("");
+            self.package = name.split(':').next().unwrap_or(name).to_string();
+        } else if let Some(rest) = line.strip_prefix("Configuration file '") {
+            self.path = rest.strip_suffix('\'').map(PathBuf::from);
+        } else if line == "==> Keeping old config file as default." {
+            return self.path.take().map(|path| Conffile {
+                path,
+                package: self.package.clone(),
+                leftover: Leftover::DpkgDist,
+            });
+        }
+        None
+    }
+}
+
+/// Every leftover under `dir`, with the package that declares the config
+/// file in `owners` (see `dpkg::parse_conffile_owners`).
+pub fn scan(runner: &dyn CommandRunner, dir: &Path, owners: &HashMap<PathBuf, String>) -> Result<Vec<Conffile>> {
+    let mut spec = CommandSpec::new("find").args([&*dir.to_string_lossy(), "-type", "f", "("]);
+    for (i, leftover) in Leftover::iter().enumerate() {
+        if i > 0 {
+            spec = spec.args(["-o"]);
+        }
+        spec = spec.args(["-name".to_string(), format!("*{}", leftover)]);
+    }
+    let output = runner.output(&spec.args([")"]))?;
+    // 1: algum diretório não pôde ser lido, o resto foi listado
+    if !matches!(output.code, Some(0) | Some(1)) {
+        bail!("{}", output.stderr.trim());
+    }
+
+    let mut conffiles: Vec<Conffile> = output
+        .stdout
+        .lines()
+        .filter_map(|line| Leftover::split(Path::new(line)))
+        .map(|(path, leftover)| Conffile {
+            package: owners.get(&path).cloned().unwrap_or_default(),
+            path,
+            leftover,
+        })
+        .collect();
+    conffiles.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(conffiles)
+}
+
+/// The leftovers lapt may diff and resolve, possibly as root: only those
+/// its own scan found under the config dir, checked again right before use.
+pub struct Leftovers {
+    runner: Arc<dyn CommandRunner>, // Para os scans; roda como o usuário
+    dir: PathBuf,
+    found: Mutex<HashSet<PathBuf>>,
+}
+
+impl Leftovers {
+    pub fn new(runner: Arc<dyn CommandRunner>, dir: impl Into<PathBuf>) -> Self {
+        Self {
+            runner,
+            dir: dir.into(),
+            found: Mutex::new(HashSet::new()),
+        }
+    }
+
+    pub fn scan(&self, owners: &HashMap<PathBuf, String>) -> Result<Vec<Conffile>> {
+        let conffiles = scan(self.runner.as_ref(), &self.dir, owners)?;
+        *self.found.lock().unwrap() = conffiles.iter().map(Conffile::leftover_path).collect();
+        Ok(conffiles)
+    }
+
+    /// Refuses anything but a regular file the scan found: a symlink or a
+    /// path leading out of the config dir would hand any file on the system
+    /// to `diff`, `rm` or `mv` running as root.
+    pub fn check(&self, other: &Path) -> Result<()> {
+        let metadata = std::fs::symlink_metadata(other).with_context(|| format!("Cannot read {}", other.display()))?;
+        if !metadata.file_type().is_file() {
+            bail!("{} is not a regular file", other.display());
+        }
+        let dir = self.dir.canonicalize()?;
+        if other.canonicalize()? != other || !other.starts_with(&dir) {
+            bail!("{} is not inside {}", other.display(), self.dir.display());
+        }
+        // Um conffile que o dpkg acabou de manter ainda não passou por um scan
+        if !self.found.lock().unwrap().contains(other) {
+            self.scan(&HashMap::new())?;
+        }
+        if !self.found.lock().unwrap().contains(other) {
+            bail!("{} is not a leftover config file", other.display());
+        }
+        Ok(())
+    }
+
+    pub fn diff(&self, runner: &dyn CommandRunner, other: &Path) -> Result<String> {
+        self.check(other)?;
+        diff(runner, other)
+    }
+
+    pub fn resolve(&self, runner: &dyn CommandRunner, other: &Path, choice: ConffileChoice) -> Result<()> {
+        self.check(other)?;
+        resolve(runner, other, choice)?;
+        self.found.lock().unwrap().remove(other);
+        Ok(())
+    }
+}
+
+/// `diff -u` of the file in use against the leftover at `other`.
+fn diff(runner: &dyn CommandRunner, other: &Path) -> Result<String> {
+    let Some((path, _)) = Leftover::split(other) else {
+        bail!("{} is not a leftover config file", other.display());
+    };
+    let output = runner.output(
+        &CommandSpec::new("diff").args(["-u", &path.to_string_lossy(), &other.to_string_lossy()]),
+    )?;
+    // 1 só quer dizer que os arquivos diferem
+    match output.code {
+        Some(0) | Some(1) => Ok(output.stdout),
+        _ => bail!("{}", output.stderr.trim()),
+    }
+}
+
+fn resolve(runner: &dyn CommandRunner, other: &Path, choice: ConffileChoice) -> Result<()> {
+    let Some((path, leftover)) = Leftover::split(other) else {
+        bail!("{} is not a leftover config file", other.display());
+    };
+    let (path, other) = (path.to_string_lossy(), other.to_string_lossy());
+    let steps = match choice {
+        ConffileChoice::Discard => vec![CommandSpec::new("rm").args(["-f", &other])],
+        // Como o dpkg faz quando se aceita a versão nova; ao voltar para o
+        // .dpkg-old, a do mantenedor fica como .dpkg-dist
+        ConffileChoice::Adopt => {
+            let backup = match leftover {
+                Leftover::DpkgOld => Leftover::DpkgDist,
+                _ => Leftover::DpkgOld,
+            };
+            vec![
+                CommandSpec::new("cp").args(["-a", &path, &format!("{}{}", path, backup)]),
+                CommandSpec::new("mv").args(["-f", &other, &path]),
+            ]
+        }
+    };
+    for step in steps {
+        let output = runner.output(&step)?;
+        if !output.success() {
+            bail!("{} failed: {}", step.program, output.stderr.trim());
+        }
+    }
+    Ok(())
+}
+
+/// One row of a side-by-side view of a unified diff.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DiffRow {
+    Hunk(String),                            // "@@ -1,3 +1,4 @@"
+    Same(String),                            // Igual dos dois lados
+    Changed(Option<String>, Option<String>), // (em uso, leftover)
+}
+
+/// Lays a `diff -u` out in two columns: removed and added lines of the same
+/// block are paired up, the longer side continues against blanks.
+pub fn side_by_side(diff: &str) -> Vec<DiffRow> {
+    let mut rows = Vec::new();
+    let (mut removed, mut added) = (Vec::new(), Vec::new());
+    let flush = |rows: &mut Vec<DiffRow>, removed: &mut Vec<String>, added: &mut Vec<String>| {
+        let len = removed.len().max(added.len());
+        let mut removed = removed.drain(..);
+        let mut added = added.drain(..);
+        for _ in 0..len {
+            rows.push(DiffRow::Changed(removed.next(), added.next()));
+        }
+    };
+
+    for line in diff.lines() {
+        if line.starts_with("---") || line.starts_with("+++") {
+            continue;
+        }
+        if let Some(line) = line.strip_prefix('-') {
+            removed.push(line.to_string());
+            continue;
+        }
+        if let Some(line) = line.strip_prefix('+') {
+            added.push(line.to_string());
+            continue;
+        }
+        flush(&mut rows, &mut removed, &mut added);
+        if line.starts_with("@@") {
+            rows.push(DiffRow::Hunk(line.to_string()));
+        } else if let Some(line) = line.strip_prefix(' ') {
+            rows.push(DiffRow::Same(line.to_string()));
+        }
+    }
+    flush(&mut rows, &mut removed, &mut added);
+    rows
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runner::{CommandOutput, ReplayRunner};
+
+    #[test]
+    fn test_tracker() {
+        let mut tracker = ConffileTracker::default();
+        let output = "\
+Setting up openssh-server:amd64 (1:9.6p1-3) ...
+Configuration file '/etc/ssh/sshd_config'
+ ==> Modified (by you or by a script) since installation.
+ ==> Package distributor has shipped an updated version.
+ ==> Keeping old config file as default.
+Installing new version of config file /etc/ssh/moduli ...
+Setting up vim (2:9.1) ...
+";
+        let kept: Vec<Conffile> = output.lines().filter_map(|line| tracker.line(line)).collect();
+        assert_eq!(kept, vec![Conffile {
+            path: PathBuf::from("/etc/ssh/sshd_config"),
+            package: "openssh-server".to_string(),
+            leftover: Leftover::DpkgDist,
+        }]);
+        assert_eq!(kept[0].leftover_path(), PathBuf::from("/etc/ssh/sshd_config.dpkg-dist"));
+    }
+
+    #[test]
+    fn test_diff_and_resolve() {
+        let done = CommandOutput { code: Some(0), ..CommandOutput::default() };
+        let runner = ReplayRunner::new()
+            .respond(&["diff"], CommandOutput {
+                code: Some(1),
+                stdout: "--- /etc/a.conf\n+++ /etc/a.conf.dpkg-dist\n@@ -1 +1 @@\n-x=1\n+x=2\n".into(),
+                stderr: String::new(),
+            })
+            .respond(&["cp"], done.clone())
+            .respond(&["mv"], done.clone())
+            .respond(&["rm"], done);
+
+        let dist = Path::new("/etc/a.conf.dpkg-dist");
+        assert!(diff(&runner, dist).unwrap().contains("+x=2"));
+
+        resolve(&runner, dist, ConffileChoice::Adopt).unwrap();
+        resolve(&runner, dist, ConffileChoice::Discard).unwrap();
+        // Going back to your old version keeps the maintainer's for later
+        resolve(&runner, Path::new("/etc/a.conf.dpkg-old"), ConffileChoice::Adopt).unwrap();
+        let calls: Vec<Vec<String>> = runner
+            .calls()
+            .iter()
+            .map(|c| c.argv().iter().map(|s| s.to_string()).collect())
+            .collect();
+        assert_eq!(calls[0], ["diff", "-u", "/etc/a.conf", "/etc/a.conf.dpkg-dist"]);
+        assert_eq!(calls[1..], [
+            vec!["cp", "-a", "/etc/a.conf", "/etc/a.conf.dpkg-old"],
+            vec!["mv", "-f", "/etc/a.conf.dpkg-dist", "/etc/a.conf"],
+            vec!["rm", "-f", "/etc/a.conf.dpkg-dist"],
+            vec!["cp", "-a", "/etc/a.conf", "/etc/a.conf.dpkg-dist"],
+            vec!["mv", "-f", "/etc/a.conf.dpkg-old", "/etc/a.conf"],
+        ]);
+
+        let missing = ReplayRunner::new().respond(&["diff"], CommandOutput {
+            code: Some(2),
+            stdout: String::new(),
+            stderr: "diff: /etc/a.conf.dpkg-dist: No such file or directory\n".into(),
+        });
+        assert!(diff(&missing, dist).unwrap_err().to_string().contains("No such file"));
+        assert!(diff(&missing, Path::new("/etc/a.conf")).is_err());
+    }
+
+    #[test]
+    fn test_scan() {
+        let runner = ReplayRunner::new().respond(&["find"], CommandOutput {
+            code: Some(1),
+            stdout: "/etc/ssh/sshd_config.dpkg-dist\n/etc/default/grub.ucf-dist\n/etc/apt/sources.list.dpkg-old\n".into(),
+            stderr: "find: '/etc/ssl/private': Permission denied\n".into(),
+        });
+        let owners = HashMap::from([(PathBuf::from("/etc/ssh/sshd_config"), "openssh-server".to_string())]);
+
+        let found = scan(&runner, Path::new(CONFIG_DIR), &owners).unwrap();
+        let found: Vec<(&str, &str, Leftover)> = found
+            .iter()
+            .map(|c| (c.path.to_str().unwrap(), c.package.as_str(), c.leftover))
+            .collect();
+        assert_eq!(found, [
+            ("/etc/apt/sources.list", "", Leftover::DpkgOld),
+            ("/etc/default/grub", "", Leftover::UcfDist),
+            ("/etc/ssh/sshd_config", "openssh-server", Leftover::DpkgDist),
+        ]);
+        assert_eq!(runner.calls()[0].argv().join(" "), "find /etc -type f ( -name *.dpkg-dist -o -name *.dpkg-new -o -name *.dpkg-old -o -name *.ucf-dist )");
+    }
+
+    #[test]
+    fn test_leftovers_are_checked() {
+        let root = std::env::temp_dir().join(format!("lapt-leftovers-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        let (etc, outside) = (root.join("etc"), root.join("home"));
+        std::fs::create_dir_all(&etc).unwrap();
+        std::fs::create_dir_all(&outside).unwrap();
+        let (etc, outside) = (etc.canonicalize().unwrap(), outside.canonicalize().unwrap());
+        for file in [etc.join("a.conf"), etc.join("a.conf.dpkg-dist"), outside.join("b.conf.dpkg-old")] {
+            std::fs::write(file, "x=1\n").unwrap();
+        }
+        std::os::unix::fs::symlink("/etc/shadow", etc.join("shadow.dpkg-old")).unwrap();
+
+        let dist = etc.join("a.conf.dpkg-dist");
+        let done = CommandOutput { code: Some(0), ..CommandOutput::default() };
+        let runner = Arc::new(
+            ReplayRunner::new()
+                .respond(&["find"], CommandOutput {
+                    code: Some(0),
+                    stdout: format!("{}\n", dist.display()),
+                    stderr: String::new(),
+                })
+                .respond(&["rm"], done),
+        );
+        let leftovers = Leftovers::new(runner.clone(), &etc);
+
+        // Found by the scan that runs when the path is not known yet
+        leftovers.resolve(runner.as_ref(), &dist, ConffileChoice::Discard).unwrap();
+
+        let refused = |path: &Path| leftovers.resolve(runner.as_ref(), path, ConffileChoice::Discard).unwrap_err().to_string();
+        assert!(refused(&outside.join("b.conf.dpkg-old")).contains("is not inside"));
+        assert!(refused(&etc.join("shadow.dpkg-old")).contains("not a regular file"));
+        assert!(refused(&etc.join("../home/b.conf.dpkg-old")).contains("is not inside"));
+        // A regular file in the config dir the scan did not report
+        std::fs::write(etc.join("c.conf.dpkg-new"), "").unwrap();
+        assert!(refused(&etc.join("c.conf.dpkg-new")).contains("not a leftover"));
+
+        let removed: Vec<_> = runner.calls().iter().filter(|c| c.program == "rm").map(|c| c.argv().join(" ")).collect();
+        assert_eq!(removed, [format!("rm -f {}", dist.display())]);
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_side_by_side() {
+        let diff = "--- /etc/a.conf\n+++ /etc/a.conf.dpkg-dist\n@@ -1,4 +1,4 @@\n a=1\n-b=1\n-c=1\n+b=2\n d=1\n+e=1\n";
+        assert_eq!(side_by_side(diff), [
+            DiffRow::Hunk("@@ -1,4 +1,4 @@".into()),
+            DiffRow::Same("a=1".into()),
+            DiffRow::Changed(Some("b=1".into()), Some("b=2".into())),
+            DiffRow::Changed(Some("c=1".into()), None),
+            DiffRow::Same("d=1".into()),
+            DiffRow::Changed(None, Some("e=1".into())),
+        ]);
+    }
+}
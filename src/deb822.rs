@@ -0,0 +1,107 @@
+/// A single deb822 stanza (one package in `status` or `Packages` files).
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Paragraph {
+    fields: Vec<(String, String)>,
+}
+
+impl Paragraph {
+    /// Field lookup, case-insensitive as mandated by deb822.
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.fields
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Field value or an empty string when absent.
+    pub fn field(&self, name: &str) -> &str {
+        self.get(name).unwrap_or("")
+    }
+}
+
+/// Parses deb822 text into paragraphs.
+///
+/// Continuation lines are joined with `\n` after dropping their leading
+/// space, so multi-line fields such as `Description` keep their layout.
+pub fn parse(input: &str) -> Vec<Paragraph> {
+    let mut paragraphs = Vec::new();
+    let mut current = Paragraph::default();
+
+    for line in input.lines() {
+        if line.trim().is_empty() {
+            if !current.fields.is_empty() {
+                paragraphs.push(std::mem::take(&mut current));
+            }
+            continue;
+        }
+
+        if line.starts_with(' ') || line.starts_with('\t') {
+            if let Some((_, value)) = current.fields.last_mut() {
+                if !value.is_empty() {
+                    value.push('\n');
+                }
+                value.push_str(&line[1..]);
+            }
+            continue;
+        }
+
+        if line.starts_with('#') {
+            continue;
+        }
+
+        if let Some((key, value)) = line.split_once(':') {
+            current
+                .fields
+                .push((key.trim().to_string(), value.trim().to_string()));
+        }
+    }
+
+    if !current.fields.is_empty() {
+        paragraphs.push(current);
+    }
+
+    paragraphs
+}
+
+/// Splits a `Description` value into its synopsis and long description,
+/// turning the " ." paragraph separators back into blank lines.
+pub fn split_description(value: &str) -> (String, String) {
+    let mut lines = value.lines();
+    let summary = lines.next().unwrap_or("").trim().to_string();
+    let long = lines
+        .map(|l| if l.trim() == "." { "" } else { l.trim() })
+        .collect::<Vec<_>>()
+        .join("\n");
+    (summary, long)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_paragraphs() {
+        let input = "Package: vim
+Status: install ok installed
+Description: Vi IMproved
+ Vim is an almost compatible version of the UNIX editor Vi.
+ .
+ Many new features have been added.
+
+package: adduser
+Version: 3.137ubuntu1
+";
+        let paragraphs = parse(input);
+        assert_eq!(paragraphs.len(), 2);
+        assert_eq!(paragraphs[0].get("package"), Some("vim"));
+        assert_eq!(paragraphs[1].get("Package"), Some("adduser"));
+        assert_eq!(paragraphs[1].field("Homepage"), "");
+
+        let (summary, long) = split_description(paragraphs[0].field("Description"));
+        assert_eq!(summary, "Vi IMproved");
+        assert_eq!(
+            long,
+            "Vim is an almost compatible version of the UNIX editor Vi.\n\nMany new features have been added."
+        );
+    }
+}
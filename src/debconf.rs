@@ -0,0 +1,545 @@
+use crate::backend::BackendEvent;
+use crate::privilege::Privilege;
+use crate::runner::{CommandOutput, CommandRunner, CommandSpec, RunningCommand};
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::os::unix::fs::{DirBuilderExt, MetadataExt};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{UnixListener, UnixStream};
+use tokio::sync::mpsc::UnboundedSender;
+use tokio::sync::oneshot;
+
+pub type QuestionId = u64;
+
+/// Whether `$LAPT_DEBCONF` asks for debconf questions to be shown in lapt
+/// instead of answered with their defaults.
+pub fn enabled() -> bool {
+    std::env::var_os("LAPT_DEBCONF").is_some_and(|value| !value.is_empty() && value != "0")
+}
+
+/// Where the per-job sockets go: a directory `Frontend` creates for this
+/// process only, under /run for root (the helper) and under the user's
+/// runtime directory otherwise. Only its owner and root reach them.
+pub fn default_dir() -> PathBuf {
+    let base = if Privilege::detect() == Privilege::Root {
+        PathBuf::from("/run")
+    } else {
+        std::env::var_os("XDG_RUNTIME_DIR")
+            .map(PathBuf::from)
+            .unwrap_or_else(std::env::temp_dir)
+    };
+    base.join(format!("lapt-debconf-{}", std::process::id()))
+}
+
+/// Debconf template types, as in the `Type:` field.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum QuestionKind {
+    Select,
+    Multiselect,
+    Boolean,
+    String,
+    Password,
+    Note,  // Só texto, sem resposta
+    Text,  // Idem
+    Error, // Idem, mas é uma falha
+}
+
+impl QuestionKind {
+    fn parse(raw: &str) -> Self {
+        match raw {
+            "select" => Self::Select,
+            "multiselect" => Self::Multiselect,
+            "boolean" => Self::Boolean,
+            "password" => Self::Password,
+            "note" => Self::Note,
+            "text" | "title" => Self::Text,
+            "error" => Self::Error,
+            _ => Self::String,
+        }
+    }
+
+    /// Typed answers; the others pick from a list or only inform.
+    pub fn takes_text(self) -> bool {
+        matches!(self, Self::String | Self::Password)
+    }
+}
+
+/// One question from a maintainer script, e.g. tzdata's `tzdata/Areas`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Question {
+    pub tag: String,
+    pub kind: QuestionKind,
+    pub title: String, // "Configuring tzdata"
+    pub description: String,
+    pub extended_description: String,
+    pub choices: Vec<String>, // select e multiselect
+    pub default: String,      // Valor atual; multiselect separa com ", "
+}
+
+/// What the Passthrough frontend is waiting for after a line.
+#[derive(Debug, PartialEq)]
+enum Step {
+    Reply(String),
+    Ask(Vec<Question>),
+}
+
+/// The state debconf builds up with `DATA`/`SET`/`SUBST`/`INPUT` before a
+/// `GO`, and the answers it reads back with `GET`:
+///
+/// ```text
+/// DATA tzdata/Areas type select
+/// DATA tzdata/Areas choices Africa, America, Europe
+/// SET tzdata/Areas Europe
+/// INPUT high tzdata/Areas
+/// GO
+/// GET tzdata/Areas
+/// ```
+#[derive(Debug, Default)]
+struct Conversation {
+    title: String,
+    data: HashMap<String, HashMap<String, String>>, // tag -> campo -> valor
+    values: HashMap<String, String>,
+    vars: HashMap<String, Vec<(String, String)>>, // SUBST
+    input: Vec<String>,
+}
+
+impl Conversation {
+    fn handle(&mut self, line: &str) -> Step {
+        let (command, rest) = line.split_once(' ').unwrap_or((line, ""));
+        let ok = || Step::Reply("0".to_string());
+        match command {
+            "CAPB" => Step::Reply("0 backup".to_string()),
+            "TITLE" => {
+                self.title = unescape(rest);
+                ok()
+            }
+            "SETTITLE" => {
+                self.title = self.field(rest, "description");
+                ok()
+            }
+            "DATA" => {
+                let mut parts = rest.splitn(3, ' ');
+                if let (Some(tag), Some(field)) = (parts.next(), parts.next()) {
+                    let value = unescape(parts.next().unwrap_or(""));
+                    self.data.entry(tag.to_string()).or_default().insert(field.to_string(), value);
+                }
+                ok()
+            }
+            "SET" => {
+                let (tag, value) = rest.split_once(' ').unwrap_or((rest, ""));
+                self.values.insert(tag.to_string(), unescape(value));
+                ok()
+            }
+            "SUBST" => {
+                let mut parts = rest.splitn(3, ' ');
+                if let (Some(tag), Some(var)) = (parts.next(), parts.next()) {
+                    let value = unescape(parts.next().unwrap_or(""));
+                    self.vars.entry(tag.to_string()).or_default().push((var.to_string(), value));
+                }
+                ok()
+            }
+            "INPUT" => {
+                if let Some((_priority, tag)) = rest.split_once(' ') {
+                    self.input.push(tag.to_string());
+                }
+                ok()
+            }
+            "GO" if self.input.is_empty() => Step::Reply("0 ok".to_string()),
+            "GO" => Step::Ask(std::mem::take(&mut self.input).iter().map(|tag| self.question(tag)).collect()),
+            "GET" => Step::Reply(format!("0 {}", self.values.get(rest).map(String::as_str).unwrap_or(""))),
+            // STOP, PROGRESS, X_LOADTEMPLATEFILE...: nada a fazer
+            _ => ok(),
+        }
+    }
+
+    fn answer(&mut self, tag: &str, value: String) {
+        self.values.insert(tag.to_string(), value);
+    }
+
+    /// A `DATA` field with the question's `SUBST` variables filled in.
+    fn field(&self, tag: &str, field: &str) -> String {
+        let mut value = self
+            .data
+            .get(tag)
+            .and_then(|fields| fields.get(field))
+            .cloned()
+            .unwrap_or_default();
+        for (var, substitution) in self.vars.get(tag).into_iter().flatten() {
+            value = value.replace(&format!("${{{}}}", var), substitution);
+        }
+        value
+    }
+
+    fn question(&self, tag: &str) -> Question {
+        Question {
+            tag: tag.to_string(),
+            kind: QuestionKind::parse(&self.field(tag, "type")),
+            title: self.title.clone(),
+            description: self.field(tag, "description"),
+            extended_description: self.field(tag, "extended_description"),
+            choices: split_choices(&self.field(tag, "choices")),
+            default: self.values.get(tag).cloned().unwrap_or_default(),
+        }
+    }
+}
+
+/// Undoes the Passthrough frontend's escaping: newlines travel as `\n`,
+/// and descriptions have their newlines and backslashes escaped once more
+/// before that.
+fn unescape(raw: &str) -> String {
+    let mut out = String::with_capacity(raw.len());
+    let mut chars = raw.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => out.push('\n'),
+            Some('\\') if chars.peek() == Some(&'n') => {
+                chars.next();
+                out.push('\n');
+            }
+            Some('\\') => out.push('\\'),
+            Some(other) => {
+                out.push('\\');
+                out.push(other);
+            }
+            None => out.push('\\'),
+        }
+    }
+    out
+}
+
+/// `Africa, America, Europe`, with `\,` for a comma inside a choice.
+pub fn split_choices(raw: &str) -> Vec<String> {
+    let mut choices = Vec::new();
+    let mut current = String::new();
+    let mut chars = raw.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if chars.peek() == Some(&',') => current.push(chars.next().unwrap()),
+            ',' => choices.push(std::mem::take(&mut current)),
+            c => current.push(c),
+        }
+    }
+    choices.push(current);
+    choices
+        .into_iter()
+        .map(|choice| choice.trim().to_string())
+        .filter(|choice| !choice.is_empty())
+        .collect()
+}
+
+/// The listening end of debconf's Passthrough frontend. Every dpkg run
+/// gets a socket of its own; each question on it becomes a
+/// `BackendEvent::DebconfQuestion`, and the maintainer script waits until
+/// `answer` is called for it.
+pub struct Frontend {
+    dir: PathBuf,
+    owner: Mutex<Option<u32>>, // Dono do diretório, depois que o criamos
+    pending: Arc<Mutex<HashMap<QuestionId, oneshot::Sender<Option<String>>>>>,
+    next_id: Arc<AtomicU64>,
+    next_socket: AtomicU64,
+    sessions: Mutex<HashMap<PathBuf, tokio::task::AbortHandle>>,
+}
+
+impl Frontend {
+    pub fn new(dir: &Path) -> Self {
+        Self {
+            dir: dir.to_path_buf(),
+            owner: Mutex::new(None),
+            pending: Arc::default(),
+            next_id: Arc::default(),
+            next_socket: AtomicU64::new(1),
+            sessions: Mutex::default(),
+        }
+    }
+
+    /// Starts listening for the command about to run, whose questions go to
+    /// `tx`. Returns the socket to pass as `DEBCONF_PIPE`, which `close`
+    /// takes back.
+    pub fn listen(&self, tx: UnboundedSender<BackendEvent>) -> Result<PathBuf> {
+        self.check_dir()?;
+        let socket = self
+            .dir
+            .join(format!("debconf-{}.sock", self.next_socket.fetch_add(1, Ordering::Relaxed)));
+        let listener = UnixListener::bind(&socket)?;
+        let pending = self.pending.clone();
+        let next_id = self.next_id.clone();
+        let task = tokio::spawn(async move {
+            // Um script do mantenedor por vez: cada um conversa até o STOP
+            while let Ok((stream, _)) = listener.accept().await {
+                let _ = converse(stream, &tx, &pending, &next_id).await;
+            }
+        });
+        self.sessions.lock().unwrap().insert(socket.clone(), task.abort_handle());
+        Ok(socket)
+    }
+
+    /// Stops listening on `socket` once its command is done. Its unanswered
+    /// questions keep their defaults; other sockets are left alone.
+    pub fn close(&self, socket: &Path) {
+        if let Some(session) = self.sessions.lock().unwrap().remove(socket) {
+            session.abort();
+        }
+        // Perguntas de uma sessão encerrada não têm mais quem espere
+        self.pending.lock().unwrap().retain(|_, waiting| !waiting.is_closed());
+        let _ = std::fs::remove_file(socket);
+    }
+
+    /// Creates the socket directory on first use, refusing one that is
+    /// already there, and afterwards checks it is still ours alone.
+    fn check_dir(&self) -> Result<()> {
+        let mut owner = self.owner.lock().unwrap();
+        if owner.is_none() {
+            std::fs::DirBuilder::new()
+                .mode(0o700)
+                .create(&self.dir)
+                .with_context(|| format!("Cannot create {}", self.dir.display()))?;
+            *owner = Some(std::fs::symlink_metadata(&self.dir)?.uid());
+        }
+        let meta = std::fs::symlink_metadata(&self.dir)?;
+        if !meta.is_dir() || Some(meta.uid()) != *owner || meta.mode() & 0o077 != 0 {
+            bail!("{} is no longer a private directory of lapt", self.dir.display());
+        }
+        Ok(())
+    }
+
+    /// `None` goes back to the previous question.
+    pub fn answer(&self, id: QuestionId, value: Option<String>) {
+        if let Some(waiting) = self.pending.lock().unwrap().remove(&id) {
+            let _ = waiting.send(value);
+        }
+    }
+}
+
+impl Drop for Frontend {
+    fn drop(&mut self) {
+        if self.owner.lock().unwrap().is_some() {
+            for socket in self.sessions.lock().unwrap().keys() {
+                let _ = std::fs::remove_file(socket);
+            }
+            let _ = std::fs::remove_dir(&self.dir);
+        }
+    }
+}
+
+async fn converse(
+    stream: UnixStream,
+    tx: &UnboundedSender<BackendEvent>,
+    pending: &Mutex<HashMap<QuestionId, oneshot::Sender<Option<String>>>>,
+    next_id: &AtomicU64,
+) -> Result<()> {
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = BufReader::new(reader).lines();
+    let mut conversation = Conversation::default();
+
+    while let Some(line) = lines.next_line().await? {
+        let reply = match conversation.handle(&line) {
+            Step::Reply(reply) => reply,
+            Step::Ask(questions) => {
+                let mut reply = "0 ok".to_string();
+                for question in questions {
+                    let id = next_id.fetch_add(1, Ordering::Relaxed);
+                    let (answer_tx, answer_rx) = oneshot::channel();
+                    pending.lock().unwrap().insert(id, answer_tx);
+                    let tag = question.tag.clone();
+                    let _ = tx.send(BackendEvent::DebconfQuestion(id, question));
+                    match answer_rx.await {
+                        Ok(Some(value)) => conversation.answer(&tag, value),
+                        Ok(None) => {
+                            reply = "30 backup".to_string();
+                            break;
+                        }
+                        // Ninguém para responder: fica o valor padrão
+                        Err(_) => {}
+                    }
+                }
+                reply
+            }
+        };
+        writer.write_all(format!("{}\n", reply).as_bytes()).await?;
+    }
+    Ok(())
+}
+
+/// Runs commands with the Passthrough frontend talking to `socket` in
+/// place of `DEBIAN_FRONTEND=noninteractive`.
+pub struct PassthroughRunner {
+    inner: Arc<dyn CommandRunner>,
+    socket: PathBuf,
+}
+
+impl PassthroughRunner {
+    pub fn new(inner: Arc<dyn CommandRunner>, socket: PathBuf) -> Self {
+        Self { inner, socket }
+    }
+
+    pub fn passthrough(&self, spec: &CommandSpec) -> CommandSpec {
+        let mut spec = spec.clone();
+        spec.env.retain(|(key, _)| key != "DEBIAN_FRONTEND" && key != "DEBCONF_PIPE");
+        spec.env("DEBIAN_FRONTEND", "passthrough")
+            .env("DEBCONF_PIPE", &self.socket.to_string_lossy())
+    }
+}
+
+impl CommandRunner for PassthroughRunner {
+    fn output(&self, spec: &CommandSpec) -> Result<CommandOutput> {
+        self.inner.output(&self.passthrough(spec))
+    }
+
+    fn spawn(&self, spec: &CommandSpec) -> Result<RunningCommand> {
+        self.inner.spawn(&self.passthrough(spec))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runner::ReplayRunner;
+    use std::os::unix::fs::PermissionsExt;
+    use tokio::sync::mpsc;
+
+    fn reply(conversation: &mut Conversation, line: &str) -> String {
+        match conversation.handle(line) {
+            Step::Reply(reply) => reply,
+            Step::Ask(questions) => panic!("{} asked {:?}", line, questions),
+        }
+    }
+
+    #[test]
+    fn test_conversation() {
+        let mut conversation = Conversation::default();
+        assert_eq!(reply(&mut conversation, "CAPB backup"), "0 backup");
+        for line in [
+            "TITLE Configuring tzdata",
+            "DATA tzdata/Areas type select",
+            "DATA tzdata/Areas description Geographic area:",
+            "DATA tzdata/Areas extended_description Please select the area.\\\\nCities are next.",
+            "DATA tzdata/Areas choices Africa, America, Etc\\, misc, ${extra}",
+            "SUBST tzdata/Areas extra Europe",
+            "SET tzdata/Areas Europe",
+            "INPUT high tzdata/Areas",
+        ] {
+            assert_eq!(reply(&mut conversation, line), "0");
+        }
+
+        let Step::Ask(questions) = conversation.handle("GO") else { panic!("GO asked nothing") };
+        assert_eq!(
+            questions,
+            vec![Question {
+                tag: "tzdata/Areas".into(),
+                kind: QuestionKind::Select,
+                title: "Configuring tzdata".into(),
+                description: "Geographic area:".into(),
+                extended_description: "Please select the area.\nCities are next.".into(),
+                choices: vec!["Africa".into(), "America".into(), "Etc, misc".into(), "Europe".into()],
+                default: "Europe".into(),
+            }]
+        );
+
+        conversation.answer("tzdata/Areas", "America".into());
+        assert_eq!(reply(&mut conversation, "GET tzdata/Areas"), "0 America");
+        // Nothing new to ask
+        assert_eq!(reply(&mut conversation, "GO"), "0 ok");
+        assert_eq!(reply(&mut conversation, "STOP"), "0");
+    }
+
+    #[test]
+    fn test_split_choices() {
+        assert_eq!(split_choices("a, b,c"), vec!["a", "b", "c"]);
+        assert_eq!(split_choices("No configuration, Internet site\\, with smarthost"), vec![
+            "No configuration",
+            "Internet site, with smarthost"
+        ]);
+        assert!(split_choices("").is_empty());
+    }
+
+    #[test]
+    fn test_passthrough_spec() {
+        let runner = PassthroughRunner::new(Arc::new(ReplayRunner::new()), PathBuf::from("/tmp/d/debconf.sock"));
+        let spec = runner.passthrough(
+            &CommandSpec::new("apt-get")
+                .env("DEBIAN_FRONTEND", "noninteractive")
+                .args(["install", "-y", "tzdata"]),
+        );
+        assert_eq!(spec.env, vec![
+            ("DEBIAN_FRONTEND".to_string(), "passthrough".to_string()),
+            ("DEBCONF_PIPE".to_string(), "/tmp/d/debconf.sock".to_string()),
+        ]);
+        assert_eq!(spec.argv(), vec!["apt-get", "install", "-y", "tzdata"]);
+    }
+
+    #[tokio::test]
+    async fn test_questions_are_answered_live() {
+        let dir = std::env::temp_dir().join(format!("lapt-debconf-test-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let frontend = Frontend::new(&dir);
+        let (tx, mut rx) = mpsc::unbounded_channel::<BackendEvent>();
+        let socket = frontend.listen(tx).unwrap();
+
+        // Plays debconf's side of the socket
+        let stream = UnixStream::connect(&socket).await.unwrap();
+        let debconf = tokio::spawn(async move {
+            let (reader, mut writer) = stream.into_split();
+            let mut replies = BufReader::new(reader).lines();
+            let mut transcript = Vec::new();
+            for line in [
+                "DATA postfix/main_mailer_type type select",
+                "DATA postfix/main_mailer_type choices No configuration, Local only",
+                "INPUT high postfix/main_mailer_type",
+                "GO",
+                "GET postfix/main_mailer_type",
+            ] {
+                writer.write_all(format!("{}\n", line).as_bytes()).await.unwrap();
+                transcript.push(replies.next_line().await.unwrap().unwrap());
+            }
+            transcript
+        });
+
+        let Some(BackendEvent::DebconfQuestion(id, question)) = rx.recv().await else {
+            panic!("expected a question");
+        };
+        assert_eq!(question.choices, vec!["No configuration", "Local only"]);
+        frontend.answer(id, Some("Local only".into()));
+        assert_eq!(debconf.await.unwrap()[3..], ["0 ok", "0 Local only"]);
+
+        frontend.close(&socket);
+        assert!(!socket.exists());
+    }
+
+    #[tokio::test]
+    async fn test_each_command_gets_its_own_socket() {
+        let dir = std::env::temp_dir().join(format!("lapt-debconf-sockets-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let (tx, _rx) = mpsc::unbounded_channel::<BackendEvent>();
+
+        // Someone else's directory is never reused
+        std::fs::create_dir(&dir).unwrap();
+        assert!(Frontend::new(&dir).listen(tx.clone()).is_err());
+        std::fs::remove_dir(&dir).unwrap();
+
+        let frontend = Frontend::new(&dir);
+        let first = frontend.listen(tx.clone()).unwrap();
+        let second = frontend.listen(tx.clone()).unwrap();
+        assert_ne!(first, second);
+        assert_eq!(std::fs::metadata(&dir).unwrap().mode() & 0o777, 0o700);
+
+        // Closing one command's socket leaves the other one listening
+        frontend.close(&first);
+        assert!(!first.exists());
+        assert!(UnixStream::connect(&second).await.is_ok());
+
+        // Loosened permissions are noticed before the next command
+        std::fs::set_permissions(&dir, std::fs::Permissions::from_mode(0o755)).unwrap();
+        assert!(frontend.listen(tx).is_err());
+
+        drop(frontend);
+        assert!(!dir.exists());
+    }
+}
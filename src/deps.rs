@@ -0,0 +1,371 @@
+use crate::deb822::Paragraph;
+use crate::pkg::Package;
+use crate::version::PackageVersion;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use strum::{Display, EnumIter, IntoEnumIterator};
+
+/// How many distinct manually installed roots "why" reports.
+const MAX_WHY_CHAINS: usize = 5;
+
+/// The relationship fields of a binary package, in the order the
+/// Dependencies view lists them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display, EnumIter, Serialize, Deserialize)]
+pub enum RelationKind {
+    #[strum(to_string = "Pre-Depends")]
+    PreDepends,
+    Depends,
+    Recommends,
+    Suggests,
+    Conflicts,
+    Breaks,
+    Provides,
+    // Não é um campo do pacote: calculado a partir dos outros instalados
+    #[strum(to_string = "Reverse-Depends")]
+    ReverseDepends,
+    #[strum(to_string = "Reverse-Recommends")]
+    ReverseRecommends,
+}
+
+impl RelationKind {
+    /// Relations that keep their targets installed, as apt's autoremover
+    /// sees them (Recommends included, apt's default).
+    pub fn is_strong(self) -> bool {
+        matches!(self, Self::PreDepends | Self::Depends | Self::Recommends)
+    }
+}
+
+/// One package named in a relation, e.g. `libc6:any (>= 2.34)`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Dependency {
+    pub name: String,
+    pub arch: Option<String>,
+    pub constraint: Option<(String, PackageVersion)>, // (operador, versão)
+}
+
+impl Dependency {
+    /// Whether `version` satisfies the version constraint, if any.
+    pub fn accepts(&self, version: &PackageVersion) -> bool {
+        match &self.constraint {
+            None => true,
+            Some((op, wanted)) => match op.as_str() {
+                "<<" | "<" => version < wanted,
+                "<=" => version <= wanted,
+                "=" => version == wanted,
+                ">=" => version >= wanted,
+                ">>" | ">" => version > wanted,
+                _ => true,
+            },
+        }
+    }
+}
+
+impl std::fmt::Display for Dependency {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.name)?;
+        if let Some(arch) = &self.arch {
+            write!(f, ":{}", arch)?;
+        }
+        if let Some((op, version)) = &self.constraint {
+            write!(f, " ({} {})", op, version)?;
+        }
+        Ok(())
+    }
+}
+
+/// One comma-separated entry of a relationship field: any of the
+/// `|`-separated alternatives satisfies it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Relation {
+    pub kind: RelationKind,
+    pub alternatives: Vec<Dependency>,
+}
+
+impl Relation {
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.alternatives.iter().map(|d| d.name.as_str())
+    }
+}
+
+impl std::fmt::Display for Relation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (i, dep) in self.alternatives.iter().enumerate() {
+            if i > 0 {
+                f.write_str(" | ")?;
+            }
+            write!(f, "{}", dep)?;
+        }
+        Ok(())
+    }
+}
+
+/// Parses a relationship field value such as
+/// `libc6 (>= 2.34), default-mta | mail-transport-agent`.
+pub fn parse_field(kind: RelationKind, value: &str) -> Vec<Relation> {
+    value
+        .split(',')
+        .filter_map(|entry| {
+            let alternatives: Vec<Dependency> = entry.split('|').filter_map(parse_dependency).collect();
+            (!alternatives.is_empty()).then_some(Relation { kind, alternatives })
+        })
+        .collect()
+}
+
+fn parse_dependency(text: &str) -> Option<Dependency> {
+    // Architecture restrictions ("[amd64]") and build profiles ("<!nocheck>")
+    // only matter when building, drop them. They come after the version, and
+    // "<" also appears inside it ("<< 1.0"), so cut after the parenthesis.
+    let text = match text.find(')') {
+        Some(end) => &text[..end],
+        None => text.split(['[', '<']).next().unwrap_or(""),
+    };
+    let text = text.trim();
+    let (name, constraint) = match text.split_once('(') {
+        Some((name, rest)) => {
+            let rest = rest.trim();
+            let op_len = rest.find(|c: char| !"<>=".contains(c)).unwrap_or(rest.len());
+            let (op, version) = rest.split_at(op_len);
+            (name.trim(), Some((op.to_string(), PackageVersion::parse(version))))
+        }
+        None => (text, None),
+    };
+    if name.is_empty() {
+        return None;
+    }
+
+    let (name, arch) = match name.split_once(':') {
+        Some((name, arch)) => (name, Some(arch.to_string())),
+        None => (name, None),
+    };
+    Some(Dependency {
+        name: name.to_string(),
+        arch,
+        constraint,
+    })
+}
+
+/// Every relationship field present in a `status`/`Packages` stanza.
+pub fn relations_from(paragraph: &Paragraph) -> Vec<Relation> {
+    RelationKind::iter()
+        .filter(|kind| !matches!(kind, RelationKind::ReverseDepends | RelationKind::ReverseRecommends))
+        .flat_map(|kind| parse_field(kind, &paragraph.field(&kind.to_string()).replace('\n', " ")))
+        .collect()
+}
+
+/// One link of a "why installed" chain: `package` declares `relation`,
+/// which is satisfied by the next link (or the package being explained).
+#[derive(Debug, Clone, PartialEq)]
+pub struct WhyStep {
+    pub package: String,
+    pub relation: Relation,
+}
+
+/// Answer to "why is this package installed?", like `aptitude why`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum WhyInstalled {
+    Manual,
+    /// Shortest chains, each starting at a manually installed package
+    Chains(Vec<Vec<WhyStep>>),
+    /// Automatic and nothing installed needs it: `apt autoremove` material
+    Orphan,
+}
+
+/// Walks the strong reverse dependencies of `target` among `installed`
+/// (breadth first, so every chain is a shortest one) up to manually
+/// installed packages.
+pub fn why_installed(target: &str, installed: &[Package]) -> WhyInstalled {
+    let Some(start) = installed.iter().position(|p| p.id.name == target) else {
+        return WhyInstalled::Orphan;
+    };
+    if !installed[start].status.automatic {
+        return WhyInstalled::Manual;
+    }
+
+    // Virtual names resolve to every installed provider
+    let mut providers: HashMap<&str, Vec<usize>> = HashMap::new();
+    for (i, pkg) in installed.iter().enumerate() {
+        providers.entry(pkg.id.name.as_str()).or_default().push(i);
+        for relation in pkg.relations.iter().filter(|r| r.kind == RelationKind::Provides) {
+            for name in relation.names() {
+                providers.entry(name).or_default().push(i);
+            }
+        }
+    }
+
+    // needed_by[q] = (p, relation of p that q satisfies)
+    let mut needed_by: HashMap<usize, Vec<(usize, &Relation)>> = HashMap::new();
+    for (i, pkg) in installed.iter().enumerate() {
+        for relation in pkg.relations.iter().filter(|r| r.kind.is_strong()) {
+            let mut satisfiers: Vec<usize> = relation
+                .names()
+                .flat_map(|name| providers.get(name).into_iter().flatten().copied())
+                .filter(|&q| q != i)
+                .collect();
+            satisfiers.dedup();
+            for q in satisfiers {
+                needed_by.entry(q).or_default().push((i, relation));
+            }
+        }
+    }
+
+    let mut parent: HashMap<usize, (usize, &Relation)> = HashMap::new();
+    let mut queue = VecDeque::from([start]);
+    let mut chains = Vec::new();
+    while let Some(current) = queue.pop_front() {
+        for &(dependent, relation) in needed_by.get(&current).into_iter().flatten() {
+            if dependent == start || parent.contains_key(&dependent) {
+                continue;
+            }
+            parent.insert(dependent, (current, relation));
+            if installed[dependent].status.automatic {
+                queue.push_back(dependent);
+                continue;
+            }
+
+            // Reached a manual package: rebuild the chain down to the target
+            let mut chain = Vec::new();
+            let mut node = dependent;
+            while node != start {
+                let (next, relation) = parent[&node];
+                chain.push(WhyStep {
+                    package: installed[node].id.name.clone(),
+                    relation: relation.clone(),
+                });
+                node = next;
+            }
+            chains.push(chain);
+            if chains.len() == MAX_WHY_CHAINS {
+                return WhyInstalled::Chains(chains);
+            }
+        }
+    }
+
+    if chains.is_empty() {
+        WhyInstalled::Orphan
+    } else {
+        WhyInstalled::Chains(chains)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::deb822;
+
+    #[test]
+    fn test_parse_field() {
+        let relations = parse_field(
+            RelationKind::Depends,
+            "libc6 (>= 2.34), default-mta | mail-transport-agent, perl:any, debhelper-compat (= 13) [amd64] <!nocheck>",
+        );
+        assert_eq!(relations.len(), 4);
+
+        assert_eq!(relations[0].alternatives[0].name, "libc6");
+        assert_eq!(
+            relations[0].alternatives[0].constraint,
+            Some((">=".to_string(), "2.34".into()))
+        );
+        assert_eq!(relations[1].names().collect::<Vec<_>>(), vec!["default-mta", "mail-transport-agent"]);
+        assert_eq!(relations[2].alternatives[0].arch.as_deref(), Some("any"));
+        assert_eq!(relations[3].to_string(), "debhelper-compat (= 13)");
+        assert_eq!(relations[1].to_string(), "default-mta | mail-transport-agent");
+        assert!(parse_field(RelationKind::Depends, "").is_empty());
+    }
+
+    #[test]
+    fn test_dependency_accepts() {
+        let dep = &parse_field(RelationKind::Depends, "libc6 (>= 2.34)")[0].alternatives[0];
+        assert!(dep.accepts(&"2.36-9".into()));
+        assert!(!dep.accepts(&"2.31-13".into()));
+
+        let strict = &parse_field(RelationKind::Breaks, "dpkg (<< 1.21~)")[0].alternatives[0];
+        assert!(strict.accepts(&"1.20.12".into()));
+        assert!(!strict.accepts(&"1.21.22".into()));
+    }
+
+    #[test]
+    fn test_relations_from_paragraph() {
+        let paragraph = &deb822::parse(
+            "Package: exim4-daemon-light
+Pre-Depends: debconf (>= 0.5)
+Depends: exim4-base (>= 4.96),
+ libc6 (>= 2.34)
+Recommends: ca-certificates
+Conflicts: mail-transport-agent
+Provides: mail-transport-agent
+",
+        )[0];
+        let relations = relations_from(paragraph);
+        let kinds: Vec<RelationKind> = relations.iter().map(|r| r.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                RelationKind::PreDepends,
+                RelationKind::Depends,
+                RelationKind::Depends,
+                RelationKind::Recommends,
+                RelationKind::Conflicts,
+                RelationKind::Provides,
+            ]
+        );
+        assert_eq!(relations[2].alternatives[0].name, "libc6");
+    }
+
+    fn installed(name: &str, automatic: bool, relations: &[(RelationKind, &str)]) -> Package {
+        let mut pkg = Package::new(
+            crate::pkg::PackageId::new(name, "1.0", "amd64", "installed"),
+            crate::pkg::PackageState::Installed.into(),
+            "",
+        );
+        pkg.status.automatic = automatic;
+        pkg.relations = relations.iter().flat_map(|(kind, value)| parse_field(*kind, value)).collect();
+        pkg
+    }
+
+    #[test]
+    fn test_why_installed_chains() {
+        let packages = vec![
+            installed("mutt", false, &[(RelationKind::Depends, "libc6, default-mta | mail-transport-agent")]),
+            installed("exim4-daemon-light", true, &[
+                (RelationKind::Depends, "exim4-base"),
+                (RelationKind::Provides, "mail-transport-agent"),
+            ]),
+            installed("exim4-base", true, &[(RelationKind::Depends, "libc6")]),
+            installed("libc6", true, &[]),
+            installed("bash", false, &[(RelationKind::PreDepends, "libc6")]),
+            installed("vim", false, &[(RelationKind::Suggests, "exim4-base")]),
+            installed("libfoo", true, &[]),
+        ];
+
+        match why_installed("exim4-base", &packages) {
+            WhyInstalled::Chains(chains) => {
+                assert_eq!(chains.len(), 1, "Suggests does not keep packages installed");
+                let steps: Vec<(String, String)> = chains[0]
+                    .iter()
+                    .map(|s| (s.package.clone(), s.relation.to_string()))
+                    .collect();
+                assert_eq!(
+                    steps,
+                    vec![
+                        ("mutt".to_string(), "default-mta | mail-transport-agent".to_string()),
+                        ("exim4-daemon-light".to_string(), "exim4-base".to_string()),
+                    ]
+                );
+            }
+            other => panic!("expected chains, got {:?}", other),
+        }
+
+        // Shortest chains first, one per manual root
+        match why_installed("libc6", &packages) {
+            WhyInstalled::Chains(chains) => {
+                let roots: Vec<&str> = chains.iter().map(|c| c[0].package.as_str()).collect();
+                assert_eq!(roots, vec!["mutt", "bash"]);
+                assert!(chains.iter().all(|c| c.len() == 1));
+            }
+            other => panic!("expected chains, got {:?}", other),
+        }
+
+        assert_eq!(why_installed("vim", &packages), WhyInstalled::Manual);
+        assert_eq!(why_installed("libfoo", &packages), WhyInstalled::Orphan);
+    }
+}
@@ -0,0 +1,228 @@
+use crate::deb822::{self, Paragraph};
+use crate::pkg::{Package, PackageId, PackageState, PackageStatus};
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+
+/// Origin of packages read from the status database: what is on disk may no
+/// longer be in any repository.
+pub const INSTALLED_ORIGIN: &str = "installed";
+
+/// Names (`name:arch`) apt marked as automatically installed, read from
+/// `/var/lib/apt/extended_states`.
+pub fn parse_extended_states(input: &str) -> HashSet<String> {
+    deb822::parse(input)
+        .iter()
+        .filter(|p| p.field("Auto-Installed") == "1")
+        .map(|p| format!("{}:{}", p.field("Package"), p.field("Architecture")))
+        .collect()
+}
+
+/// Parses `/var/lib/dpkg/status` into the installed package list: everything
+/// that still has files on disk, like `apt list --installed`.
+pub fn parse_status(input: &str, auto_installed: &HashSet<String>) -> Vec<Package> {
+    // apt guarda os pacotes `Architecture: all` com a arquitetura nativa
+    let auto_names: HashSet<&str> = auto_installed
+        .iter()
+        .filter_map(|key| key.split(':').next())
+        .collect();
+    deb822::parse(input)
+        .iter()
+        .map(|p| package_from_status(p, auto_installed, &auto_names))
+        .filter(|pkg| pkg.status.is_installed())
+        .collect()
+}
+
+/// Which package declares each conffile, from the `Conffiles` records of
+/// `/var/lib/dpkg/status`. Packages removed but not purged still own theirs.
+pub fn parse_conffile_owners(input: &str) -> HashMap<PathBuf, String> {
+    deb822::parse(input)
+        .iter()
+        .flat_map(|p| {
+            // " /etc/deluser.conf 773fb95e98a27947de4a95abb3d3f2a2 [obsolete]"
+            let name = p.field("Package");
+            p.field("Conffiles")
+                .lines()
+                .filter_map(|line| line.split_whitespace().next())
+                .map(move |path| (PathBuf::from(path), name.to_string()))
+        })
+        .collect()
+}
+
+/// Maps a dpkg `Status: <want> <flag> <state>` field onto [`PackageStatus`].
+pub fn parse_status_field(field: &str) -> PackageStatus {
+    let mut words = field.split_whitespace();
+    let (want, flag, state) = (words.next(), words.next(), words.next());
+
+    let state = match state {
+        _ if flag == Some("reinstreq") => PackageState::Broken,
+        Some("installed" | "triggers-awaited" | "triggers-pending") => PackageState::Installed,
+        Some("half-installed" | "unpacked" | "half-configured") => PackageState::Broken,
+        Some("config-files") => PackageState::ConfigFiles,
+        _ => PackageState::Available,
+    };
+
+    PackageStatus {
+        state,
+        held: want == Some("hold"),
+        ..Default::default()
+    }
+}
+
+fn package_from_status(p: &Paragraph, auto_installed: &HashSet<String>, auto_names: &HashSet<&str>) -> Package {
+    let name = p.field("Package");
+    let version = p.field("Version");
+    let arch = p.field("Architecture");
+    let (summary, description) = deb822::split_description(p.field("Description"));
+
+    // Source may carry its own version: "glibc (2.39-0ubuntu8)"
+    let source = p
+        .get("Source")
+        .map(|s| s.split_whitespace().next().unwrap_or(name))
+        .unwrap_or(name);
+
+    let mut status = parse_status_field(p.field("Status"));
+    status.automatic = auto_installed.contains(&format!("{}:{}", name, arch))
+        || (arch == "all" && auto_names.contains(name));
+
+    let id = PackageId::new(name, version, arch, INSTALLED_ORIGIN);
+    let mut pkg = Package::new(id, status, &summary);
+    pkg.update_details(
+        &description,
+        "Unknown",
+        p.field("Installed-Size").parse::<u64>().unwrap_or(0) * 1024,
+        p.field("Homepage"),
+    );
+    pkg.section = p.field("Section").to_string();
+    pkg.priority = p.field("Priority").to_string();
+    pkg.maintainer = p.field("Maintainer").to_string();
+    pkg.source = source.to_string();
+    pkg.depends = p.field("Depends").replace('\n', " ");
+    pkg.relations = crate::deps::relations_from(p);
+    pkg
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STATUS: &str = "Package: adduser
+Status: install ok installed
+Priority: important
+Section: admin
+Installed-Size: 612
+Maintainer: Ubuntu Core Developers <ubuntu-devel-discuss@lists.ubuntu.com>
+Architecture: all
+Multi-Arch: foreign
+Version: 3.137ubuntu1
+Depends: passwd (>= 1:4.15.2), login
+Conffiles:
+ /etc/adduser.conf cc3493ecd2d09837ffdcc3e25fdfff18
+ /etc/deluser.conf 773fb95e98a27947de4a95abb3d3f2a2
+Description: add and remove users and groups
+ This package includes the 'adduser' and 'deluser' commands.
+Homepage: https://salsa.debian.org/debian/adduser
+
+Package: debconf
+Status: install ok installed
+Priority: important
+Section: admin
+Installed-Size: 512
+Architecture: all
+Version: 1.5.86ubuntu1
+Description: Debian configuration management system
+
+Package: libc6
+Status: hold ok installed
+Priority: optional
+Section: libs
+Installed-Size: 13000
+Maintainer: Ubuntu Developers <ubuntu-devel-discuss@lists.ubuntu.com>
+Architecture: amd64
+Source: glibc (2.39-0ubuntu8)
+Version: 2.39-0ubuntu8.3
+Description: GNU C Library: Shared libraries
+
+Package: oldpkg
+Status: deinstall ok config-files
+Architecture: amd64
+Version: 1.0
+Conffiles:
+ /etc/oldpkg.conf 0a1b2c3d4e5f60718293a4b5c6d7e8f9 obsolete
+Description: removed but configured
+";
+
+    const EXTENDED_STATES: &str = "Package: libc6
+Architecture: amd64
+Auto-Installed: 1
+
+Package: adduser
+Architecture: all
+Auto-Installed: 0
+
+Package: debconf
+Architecture: amd64
+Auto-Installed: 1
+";
+
+    #[test]
+    fn test_parse_extended_states() {
+        let auto = parse_extended_states(EXTENDED_STATES);
+        assert!(auto.contains("libc6:amd64"));
+        assert!(!auto.contains("adduser:all"));
+    }
+
+    #[test]
+    fn test_parse_status() {
+        let pkgs = parse_status(STATUS, &parse_extended_states(EXTENDED_STATES));
+        assert_eq!(pkgs.len(), 3, "config-files only packages are not installed");
+
+        let adduser = &pkgs[0];
+        assert_eq!(adduser.id.name, "adduser");
+        assert_eq!(adduser.id.version, "3.137ubuntu1");
+        assert_eq!(adduser.id.arch, "all");
+        assert_eq!(adduser.id.origin, INSTALLED_ORIGIN);
+        assert_eq!(adduser.status, PackageState::Installed.into());
+        assert_eq!(adduser.summary, "add and remove users and groups");
+        assert!(adduser.description.contains("'deluser' commands"));
+        assert_eq!(adduser.size, 612 * 1024);
+        assert_eq!(adduser.section, "admin");
+        assert_eq!(adduser.priority, "important");
+        assert!(adduser.maintainer.starts_with("Ubuntu Core Developers"));
+        assert_eq!(adduser.depends, "passwd (>= 1:4.15.2), login");
+        assert_eq!(adduser.source, "adduser");
+        assert_eq!(adduser.url, "https://salsa.debian.org/debian/adduser");
+        assert!(!adduser.status.automatic);
+
+        // apt records arch:all packages under the native arch
+        let debconf = &pkgs[1];
+        assert_eq!(debconf.id.arch, "all");
+        assert!(debconf.status.automatic);
+
+        let libc = &pkgs[2];
+        assert_eq!(libc.source, "glibc");
+        assert!(libc.status.automatic);
+        assert!(libc.status.held);
+    }
+
+    #[test]
+    fn test_parse_conffile_owners() {
+        let owners = parse_conffile_owners(STATUS);
+        assert_eq!(owners.len(), 3);
+        assert_eq!(owners[&PathBuf::from("/etc/deluser.conf")], "adduser");
+        assert_eq!(owners[&PathBuf::from("/etc/oldpkg.conf")], "oldpkg");
+    }
+
+    #[test]
+    fn test_parse_status_field() {
+        assert_eq!(parse_status_field("install ok installed").state, PackageState::Installed);
+        assert_eq!(parse_status_field("install ok triggers-pending").state, PackageState::Installed);
+        assert_eq!(parse_status_field("install ok half-configured").state, PackageState::Broken);
+        assert_eq!(parse_status_field("install reinstreq installed").state, PackageState::Broken);
+        assert_eq!(parse_status_field("deinstall ok config-files").state, PackageState::ConfigFiles);
+        assert_eq!(parse_status_field("purge ok not-installed").state, PackageState::Available);
+
+        let held = parse_status_field("hold ok installed");
+        assert!(held.held);
+        assert_eq!(held.state, PackageState::Installed);
+    }
+}
@@ -0,0 +1,455 @@
+use crate::apt::AptPaths;
+use crate::backend::{run_shared_backend, AptBackend, BackendCommand, BackendEvent, PackageBackend, Request};
+use crate::conffile::Leftover;
+use crate::privilege::Privilege;
+use crate::protocol::{decode, encode, ClientMessage, HelperMessage, PROTOCOL_VERSION};
+use crate::runner::SystemRunner;
+use anyhow::{bail, Context, Result};
+use std::os::unix::fs::{MetadataExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{UnixListener, UnixStream};
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+
+pub const DEFAULT_SOCKET: &str = "/run/lapt.sock";
+
+/// `$LAPT_SOCKET`, or the socket `lapt helper` listens on by default.
+pub fn socket_path() -> PathBuf {
+    std::env::var_os("LAPT_SOCKET")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from(DEFAULT_SOCKET))
+}
+
+/// `lapt helper [--socket PATH] [--allow-uid UID]...`: the root half of
+/// lapt. Root may always connect; so may the user who started it through
+/// sudo or pkexec, and every `--allow-uid`. Only the first two are trusted
+/// with diffs of files they could not read themselves.
+pub async fn run(args: &[String]) -> Result<()> {
+    if Privilege::detect() != Privilege::Root {
+        bail!("lapt helper must run as root");
+    }
+
+    let mut socket = socket_path();
+    let mut trusted = vec![0];
+    trusted.extend(
+        ["SUDO_UID", "PKEXEC_UID"]
+            .iter()
+            .filter_map(|var| std::env::var(var).ok()?.parse::<u32>().ok()),
+    );
+    let mut allowed = trusted.clone();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--socket" => socket = args.next().context("--socket needs a path")?.into(),
+            "--allow-uid" => allowed.push(
+                args.next()
+                    .and_then(|uid| uid.parse().ok())
+                    .context("--allow-uid needs a numeric uid")?,
+            ),
+            other => bail!("Unknown option for lapt helper: {}", other),
+        }
+    }
+
+    // Um socket velho de uma execução anterior impede o bind
+    let _ = std::fs::remove_file(&socket);
+    let listener = UnixListener::bind(&socket).with_context(|| format!("Cannot listen on {}", socket.display()))?;
+    // Qualquer um conecta; quem pode usar é decidido pelo peer_cred
+    std::fs::set_permissions(&socket, std::fs::Permissions::from_mode(0o666))?;
+
+    let backend = AptBackend::with_runner(Arc::new(SystemRunner), AptPaths::default());
+    let backend = if crate::debconf::enabled() {
+        backend.with_debconf(&crate::debconf::default_dir())
+    } else {
+        backend
+    };
+    serve(listener, Peers { allowed, trusted }, backend).await
+}
+
+/// Who may talk to the helper, and who of those may see root-only files.
+#[derive(Debug, Clone)]
+pub struct Peers {
+    pub allowed: Vec<u32>,
+    pub trusted: Vec<u32>, // Root e quem iniciou o helper com sudo/pkexec
+}
+
+/// Accepts TUI clients forever, after checking each one's uid against
+/// `peers` and its protocol version against ours. All of them share
+/// `backend` and its single job queue, so two clients never run apt at the
+/// same time; each gets back only the events of its own commands.
+pub async fn serve<B: PackageBackend>(listener: UnixListener, peers: Peers, backend: B) -> Result<()> {
+    let (tx_request, rx_request) = mpsc::unbounded_channel::<Request>();
+    tokio::spawn(run_shared_backend(backend, rx_request));
+    loop {
+        let (stream, _) = listener.accept().await?;
+        let peers = peers.clone();
+        let tx_request = tx_request.clone();
+        tokio::spawn(async move {
+            let _ = serve_client(stream, &peers, tx_request).await;
+        });
+    }
+}
+
+async fn serve_client(stream: UnixStream, peers: &Peers, tx_request: UnboundedSender<Request>) -> Result<()> {
+    let cred = stream.peer_cred()?;
+    let (uid, gid) = (cred.uid(), cred.gid());
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = BufReader::new(reader).lines();
+
+    let refusal = if !peers.allowed.contains(&uid) {
+        Some(format!("uid {} is not allowed to use the lapt helper", uid))
+    } else {
+        match decode(&lines.next_line().await?.unwrap_or_default()) {
+            Ok(ClientMessage::Hello { version }) if version == PROTOCOL_VERSION => None,
+            Ok(ClientMessage::Hello { version }) => Some(format!(
+                "lapt helper speaks protocol version {}, the client speaks {}; restart the helper after upgrading lapt",
+                PROTOCOL_VERSION, version
+            )),
+            _ => Some("Expected a Hello from the client".to_string()),
+        }
+    };
+    if let Some(reason) = refusal {
+        writer.write_all(encode(&HelperMessage::Refused(reason))?.as_bytes()).await?;
+        return Ok(());
+    }
+    writer
+        .write_all(encode(&HelperMessage::Welcome { version: PROTOCOL_VERSION })?.as_bytes())
+        .await?;
+
+    let (tx_event, mut rx_event) = mpsc::unbounded_channel::<BackendEvent>();
+
+    // Eventos do backend para o socket
+    let forward = tokio::spawn(async move {
+        while let Some(event) = rx_event.recv().await {
+            let Ok(line) = encode(&HelperMessage::Event(event)) else { continue };
+            if writer.write_all(line.as_bytes()).await.is_err() {
+                break;
+            }
+        }
+    });
+
+    // Comandos do socket para a fila, marcados com o canal de volta deste cliente
+    while let Ok(Some(line)) = lines.next_line().await {
+        let Ok(ClientMessage::Command(cmd)) = decode(&line) else { continue };
+        // O diff roda como root: só mostra o que o próprio cliente poderia ler
+        if let BackendCommand::DiffConffile(path) = &cmd
+            && !peers.trusted.contains(&uid)
+            && let Err(e) = readable_by(path, uid, gid)
+        {
+            let _ = tx_event.send(BackendEvent::Error(e.to_string()));
+            continue;
+        }
+        let _ = tx_request.send((cmd, tx_event.clone()));
+    }
+    // Fechar o receptor faz a fila descartar os jobs pendentes deste cliente
+    forward.abort();
+    Ok(())
+}
+
+/// Fails unless `uid` (whose primary group is `gid`) could read the
+/// leftover at `other` and the config file it belongs to on its own.
+/// Supplementary groups are not known here, so they never grant access.
+fn readable_by(other: &Path, uid: u32, gid: u32) -> Result<()> {
+    let config = Leftover::split(other).map(|(config, _)| config);
+    for file in std::iter::once(other).chain(config.as_deref()) {
+        // Ler o arquivo pede x em cada diretório do caminho e r nele
+        let mut readable = permits(file, uid, gid, 0o4)?;
+        for dir in file.ancestors().skip(1).filter(|d| !d.as_os_str().is_empty()) {
+            readable &= permits(dir, uid, gid, 0o1)?;
+        }
+        if !readable {
+            bail!("uid {} cannot read {}, so the lapt helper will not show it", uid, file.display());
+        }
+    }
+    Ok(())
+}
+
+/// Whether the owner, group or other `bits` of `path` apply to `uid`.
+fn permits(path: &Path, uid: u32, gid: u32, bits: u32) -> Result<bool> {
+    let meta = std::fs::metadata(path).with_context(|| format!("Cannot read {}", path.display()))?;
+    let shift = if meta.uid() == uid {
+        6
+    } else if meta.gid() == gid {
+        3
+    } else {
+        0
+    };
+    Ok(meta.mode() & (bits << shift) != 0)
+}
+
+/// Connects to the helper at `path`, which must be running as
+/// `helper_uid`, and says hello. `None` when no helper is listening there.
+pub async fn connect(path: &Path, helper_uid: u32) -> Result<Option<UnixStream>> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    let mut stream = UnixStream::connect(path)
+        .await
+        .with_context(|| format!("Cannot connect to the lapt helper at {}", path.display()))?;
+
+    // Quem escuta no socket vai ver todos os comandos: tem que ser o root
+    let uid = stream.peer_cred()?.uid();
+    if uid != helper_uid {
+        bail!("{} is served by uid {}, not by the lapt helper", path.display(), uid);
+    }
+
+    stream
+        .write_all(encode(&ClientMessage::Hello { version: PROTOCOL_VERSION })?.as_bytes())
+        .await?;
+    let mut reply = String::new();
+    BufReader::new(&mut stream).read_line(&mut reply).await?;
+    match decode(&reply)? {
+        HelperMessage::Welcome { .. } => Ok(Some(stream)),
+        HelperMessage::Refused(reason) => bail!("The lapt helper refused the connection: {}", reason),
+        HelperMessage::Event(_) => bail!("The lapt helper did not answer the Hello"),
+    }
+}
+
+/// The socket transport: sends every command from `rx` to the helper and
+/// every event it streams back to `tx`, in place of a local `run_backend`.
+pub fn attach(stream: UnixStream, mut rx: UnboundedReceiver<BackendCommand>, tx: UnboundedSender<BackendEvent>) {
+    let (reader, mut writer) = stream.into_split();
+
+    tokio::spawn(async move {
+        while let Some(cmd) = rx.recv().await {
+            let Ok(line) = encode(&ClientMessage::Command(cmd)) else { continue };
+            if writer.write_all(line.as_bytes()).await.is_err() {
+                break;
+            }
+        }
+    });
+
+    tokio::spawn(async move {
+        let mut lines = BufReader::new(reader).lines();
+        while let Ok(Some(line)) = lines.next_line().await {
+            match decode(&line) {
+                Ok(HelperMessage::Event(event)) => {
+                    let _ = tx.send(event);
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    let _ = tx.send(BackendEvent::Error(e.to_string()));
+                }
+            }
+        }
+        let _ = tx.send(BackendEvent::Error("Lost the connection to the lapt helper".into()));
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runner::ReplayRunner;
+
+    async fn start_helper(test: &str, allowed: Vec<u32>) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("lapt-helper-{}-{}", test, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let socket = dir.join("lapt.sock");
+        let listener = UnixListener::bind(&socket).unwrap();
+        let peers = Peers {
+            trusted: allowed.clone(),
+            allowed,
+        };
+        tokio::spawn(serve(
+            listener,
+            peers,
+            AptBackend::with_runner(Arc::new(ReplayRunner::new()), AptPaths::default()),
+        ));
+        socket
+    }
+
+    async fn own_uid() -> u32 {
+        let (a, _b) = UnixStream::pair().unwrap();
+        a.peer_cred().unwrap().uid()
+    }
+
+    #[tokio::test]
+    async fn test_commands_and_events_cross_the_socket() {
+        let uid = own_uid().await;
+        let socket = start_helper("roundtrip", vec![uid]).await;
+
+        let stream = connect(&socket, uid).await.unwrap().unwrap();
+        let (tx_cmd, rx_cmd) = mpsc::unbounded_channel::<BackendCommand>();
+        let (tx_event, mut rx_event) = mpsc::unbounded_channel::<BackendEvent>();
+        attach(stream, rx_cmd, tx_event);
+
+        tx_cmd.send(BackendCommand::Cancel).unwrap();
+        assert_eq!(
+            rx_event.recv().await.unwrap(),
+            BackendEvent::CancelRefused("Nothing is running that can be cancelled".into())
+        );
+
+        assert!(connect(&socket.with_file_name("missing.sock"), uid).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_clients_share_one_queue_and_get_their_own_events() {
+        let uid = own_uid().await;
+        let dir = std::env::temp_dir().join(format!("lapt-helper-shared-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let socket = dir.join("lapt.sock");
+        let runner = ReplayRunner::new().respond(
+            &["apt-get"],
+            crate::runner::CommandOutput {
+                code: Some(0),
+                stdout: String::new(),
+                stderr: String::new(),
+            },
+        );
+        let paths = AptPaths {
+            dpkg_lock: dir.join("lock-frontend"),
+            ..AptPaths::default()
+        };
+        let peers = Peers {
+            allowed: vec![uid],
+            trusted: vec![uid],
+        };
+        tokio::spawn(serve(
+            UnixListener::bind(&socket).unwrap(),
+            peers,
+            AptBackend::with_runner(Arc::new(runner), paths),
+        ));
+
+        let client = async || {
+            let (tx_cmd, rx_cmd) = mpsc::unbounded_channel::<BackendCommand>();
+            let (tx_event, rx_event) = mpsc::unbounded_channel::<BackendEvent>();
+            attach(connect(&socket, uid).await.unwrap().unwrap(), rx_cmd, tx_event);
+            (tx_cmd, rx_event)
+        };
+        let until_finished = async |rx: &mut UnboundedReceiver<BackendEvent>, cmd: BackendCommand| {
+            let mut events = Vec::new();
+            loop {
+                let event = tokio::time::timeout(tokio::time::Duration::from_secs(2), rx.recv())
+                    .await
+                    .expect("timed out waiting for the job")
+                    .unwrap();
+                events.push(event.clone());
+                if event == BackendEvent::TaskFinished(cmd.clone()) {
+                    return events;
+                }
+            }
+        };
+
+        let (tx_a, mut rx_a) = client().await;
+        tx_a.send(BackendCommand::Autoremove).unwrap();
+        until_finished(&mut rx_a, BackendCommand::Autoremove).await;
+
+        let (tx_b, mut rx_b) = client().await;
+        tx_b.send(BackendCommand::RefreshRepos).unwrap();
+        let events = until_finished(&mut rx_b, BackendCommand::RefreshRepos).await;
+
+        // One queue: the second client's job is the second job, listed after the first
+        let ids: Vec<_> = events
+            .iter()
+            .rev()
+            .find_map(|e| match e {
+                BackendEvent::QueueUpdated(jobs) => Some(jobs.iter().map(|j| j.id).collect()),
+                _ => None,
+            })
+            .unwrap();
+        assert_eq!(ids, vec![1, 2]);
+
+        // The first client follows the queue but not the other client's job
+        tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+        let seen: Vec<BackendEvent> = std::iter::from_fn(|| rx_a.try_recv().ok()).collect();
+        assert!(seen.iter().any(|e| matches!(e, BackendEvent::QueueUpdated(jobs) if jobs.len() == 2)));
+        assert!(seen.iter().all(|e| matches!(e, BackendEvent::QueueUpdated(_))));
+    }
+
+    #[tokio::test]
+    async fn test_helper_refuses_paths_and_names_outside_its_reach() {
+        let uid = own_uid().await;
+        let socket = start_helper("reach", vec![uid]).await;
+        let stream = connect(&socket, uid).await.unwrap().unwrap();
+        let (tx_cmd, rx_cmd) = mpsc::unbounded_channel::<BackendCommand>();
+        let (tx_event, mut rx_event) = mpsc::unbounded_channel::<BackendEvent>();
+        attach(stream, rx_cmd, tx_event);
+
+        let mut next_error = async || loop {
+            let event = tokio::time::timeout(tokio::time::Duration::from_secs(2), rx_event.recv())
+                .await
+                .expect("timed out waiting for an error")
+                .unwrap();
+            if let BackendEvent::Error(message) = event {
+                return message;
+            }
+        };
+
+        // A leftover-looking symlink to a root-only file
+        let link = socket.with_file_name("shadow.dpkg-old");
+        std::os::unix::fs::symlink("/etc/shadow", &link).unwrap();
+        tx_cmd.send(BackendCommand::DiffConffile(link.clone())).unwrap();
+        assert!(next_error().await.contains("not a regular file"));
+        tx_cmd
+            .send(BackendCommand::ResolveConffile(link, crate::conffile::ConffileChoice::Discard))
+            .unwrap();
+        assert!(next_error().await.contains("not a regular file"));
+
+        // A local .deb instead of a package name
+        let evil = crate::pkg::PackageId::new("./evil.deb", "", "", "");
+        tx_cmd.send(BackendCommand::Install(evil)).unwrap();
+        assert!(next_error().await.contains("not a valid package name"));
+    }
+
+    #[test]
+    fn test_diffs_need_the_client_to_read_both_files() {
+        let dir = std::env::temp_dir().join(format!("lapt-helper-readable-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::set_permissions(&dir, std::fs::Permissions::from_mode(0o755)).unwrap();
+        let config = dir.join("secret.conf");
+        let other = dir.join("secret.conf.dpkg-dist");
+        std::fs::write(&config, "password=old\n").unwrap();
+        std::fs::write(&other, "password=new\n").unwrap();
+        std::fs::set_permissions(&other, std::fs::Permissions::from_mode(0o644)).unwrap();
+        let meta = std::fs::metadata(&config).unwrap();
+        let (owner, group) = (meta.uid(), meta.gid());
+
+        // Only the owner can read the file in use
+        std::fs::set_permissions(&config, std::fs::Permissions::from_mode(0o600)).unwrap();
+        assert!(readable_by(&other, owner, group).is_ok());
+        let err = readable_by(&other, owner + 1, group + 1).unwrap_err();
+        assert!(err.to_string().contains("secret.conf,"));
+
+        // Group and other bits count for everyone else
+        std::fs::set_permissions(&config, std::fs::Permissions::from_mode(0o640)).unwrap();
+        assert!(readable_by(&other, owner + 1, group).is_ok());
+        assert!(readable_by(&other, owner + 1, group + 1).is_err());
+
+        // A directory on the way that others cannot enter
+        std::fs::set_permissions(&config, std::fs::Permissions::from_mode(0o644)).unwrap();
+        std::fs::set_permissions(&dir, std::fs::Permissions::from_mode(0o700)).unwrap();
+        assert!(readable_by(&other, owner + 1, group + 1).is_err());
+        std::fs::set_permissions(&dir, std::fs::Permissions::from_mode(0o755)).unwrap();
+        assert!(readable_by(&other, owner + 1, group + 1).is_ok());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_peer_credentials_and_version_are_checked() {
+        let uid = own_uid().await;
+
+        // The helper turns away uids it was not told about
+        let socket = start_helper("refused", vec![uid + 1]).await;
+        let err = connect(&socket, uid).await.unwrap_err();
+        assert!(err.to_string().contains(&format!("uid {} is not allowed", uid)));
+
+        // The client only talks to a helper running as the expected user
+        let socket = start_helper("impostor", vec![uid]).await;
+        let err = connect(&socket, uid + 1).await.unwrap_err();
+        assert!(err.to_string().contains("not by the lapt helper"));
+
+        // A client from another lapt version
+        let mut stream = UnixStream::connect(&socket).await.unwrap();
+        let hello = encode(&ClientMessage::Hello { version: PROTOCOL_VERSION + 1 }).unwrap();
+        stream.write_all(hello.as_bytes()).await.unwrap();
+        let mut reply = String::new();
+        BufReader::new(&mut stream).read_line(&mut reply).await.unwrap();
+        let HelperMessage::Refused(reason) = decode(&reply).unwrap() else {
+            panic!("expected a refusal, got {}", reply);
+        };
+        assert!(reason.contains("protocol version"));
+    }
+}
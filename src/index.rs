@@ -0,0 +1,392 @@
+use crate::deb822;
+use crate::pkg::{Package, PackageId, PackageState};
+use crate::runner::{CommandRunner, CommandSpec};
+use crate::version::PackageVersion;
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Upper bound on results returned per query, keeps as-you-type searches cheap
+/// to render.
+const MAX_RESULTS: usize = 500;
+
+/// In-memory index of every package apt knows about, built from the
+/// `*_Packages` files under `/var/lib/apt/lists`.
+#[derive(Debug, Default)]
+pub struct PackageIndex {
+    packages: Vec<IndexedPackage>,
+    positions: HashMap<(String, String), usize>, // (nome, arch) -> posição em `packages`
+    skipped: Vec<String>,                        // Listas que não deu para ler, com o motivo
+}
+
+#[derive(Debug)]
+struct IndexedPackage {
+    package: Package,
+    // Lowercased copies so matching doesn't allocate per query
+    name: String,
+    summary: String,
+    description: String,
+}
+
+/// Where a `Packages` file comes from, e.g. `deb.debian.org/debian bookworm/main`.
+pub fn origin_from_filename(file_name: &str) -> String {
+    let stem = file_name
+        .split_once("_Packages")
+        .map(|(stem, _)| stem)
+        .unwrap_or(file_name);
+    let decode = |s: &str| s.replace("%5f", "_").replace("%5F", "_");
+
+    let Some((repo, dist)) = stem.split_once("_dists_") else {
+        // Flat repository: no suite/component layout
+        return decode(&stem.replace('_', "/"));
+    };
+
+    let repo = decode(&repo.replace('_', "/"));
+    let parts: Vec<&str> = dist.split('_').collect();
+    match parts.iter().position(|p| p.starts_with("binary-")) {
+        Some(i) if i >= 2 => format!(
+            "{} {}/{}",
+            repo,
+            decode(&parts[..i - 1].join("/")),
+            decode(parts[i - 1])
+        ),
+        _ => format!("{} {}", repo, decode(&parts.join("/"))),
+    }
+}
+
+impl PackageIndex {
+    /// Builds the index from the apt lists directory. Compressed lists are
+    /// decompressed through `apt-helper cat-file`, which knows every format
+    /// apt can store them in. A list that cannot be read is left out and
+    /// reported through `skipped` instead of failing the whole index.
+    pub fn load(runner: &dyn CommandRunner, lists_dir: &Path) -> Result<Self> {
+        let mut packages = Vec::new();
+        let mut translations = Vec::new();
+        let mut skipped = Vec::new();
+
+        let entries = std::fs::read_dir(lists_dir)
+            .with_context(|| format!("Failed to read {}", lists_dir.display()))?;
+        for entry in entries.flatten() {
+            let file_name = entry.file_name().to_string_lossy().into_owned();
+            let is_packages = file_name.contains("_Packages");
+            if !is_packages && !file_name.contains("_i18n_Translation-") {
+                continue;
+            }
+            match read_list(runner, &entry.path()) {
+                Ok(content) if is_packages => packages.push((origin_from_filename(&file_name), content)),
+                Ok(content) => translations.push(content),
+                Err(e) => skipped.push(format!("{:#}", e)),
+            }
+        }
+
+        // Deterministic order regardless of directory iteration
+        packages.sort_by(|a, b| a.0.cmp(&b.0));
+        skipped.sort();
+        let mut index = Self::from_lists(&packages, &translations);
+        index.skipped = skipped;
+        Ok(index)
+    }
+
+    /// Builds the index from already-read `(origin, Packages content)` pairs
+    /// and `Translation-*` contents.
+    pub fn from_lists(packages: &[(String, String)], translations: &[String]) -> Self {
+        let mut long_descriptions: HashMap<String, String> = HashMap::new();
+        for content in translations {
+            for p in deb822::parse(content) {
+                let md5 = p.field("Description-md5");
+                if md5.is_empty() {
+                    continue;
+                }
+                let text = p
+                    .get("Description-en")
+                    .or_else(|| p.get("Description"))
+                    .unwrap_or("");
+                long_descriptions.insert(md5.to_string(), text.to_string());
+            }
+        }
+
+        let mut index = Self::default();
+        // Only the newest version of each (name, arch) is kept, like apt's candidate
+        for (origin, content) in packages {
+            for p in deb822::parse(content) {
+                let name = p.field("Package");
+                let version = p.field("Version");
+                let arch = p.field("Architecture");
+                if name.is_empty() {
+                    continue;
+                }
+                let key = (name.to_string(), arch.to_string());
+                let existing = index.positions.get(&key).copied();
+                if let Some(i) = existing
+                    && index.packages[i].package.id.version >= PackageVersion::parse(version)
+                {
+                    continue;
+                }
+
+                let mut description = p.field("Description").to_string();
+                if !description.contains('\n')
+                    && let Some(full) = long_descriptions.get(p.field("Description-md5"))
+                {
+                    description = full.clone();
+                }
+                let (summary, long) = deb822::split_description(&description);
+
+                let id = PackageId::new(name, version, arch, origin);
+                let mut package = Package::new(id, PackageState::Available.into(), &summary);
+                package.update_details(
+                    &long,
+                    "Unknown",
+                    p.field("Installed-Size").parse::<u64>().unwrap_or(0) * 1024,
+                    p.field("Homepage"),
+                );
+                package.download_size = p.field("Size").parse().unwrap_or(0);
+                package.section = p.field("Section").to_string();
+                package.priority = p.field("Priority").to_string();
+                package.maintainer = p.field("Maintainer").to_string();
+                package.depends = p.field("Depends").replace('\n', " ");
+                package.relations = crate::deps::relations_from(&p);
+                package.source = p
+                    .get("Source")
+                    .and_then(|s| s.split_whitespace().next())
+                    .unwrap_or(name)
+                    .to_string();
+
+                let entry = IndexedPackage {
+                    name: name.to_lowercase(),
+                    summary: summary.to_lowercase(),
+                    description: long.to_lowercase(),
+                    package,
+                };
+                match existing {
+                    Some(i) => index.packages[i] = entry,
+                    None => {
+                        index.positions.insert(key, index.packages.len());
+                        index.packages.push(entry);
+                    }
+                }
+            }
+        }
+        index
+    }
+
+    /// The indexed (newest) version of `name` for `arch`.
+    pub fn get(&self, name: &str, arch: &str) -> Option<&Package> {
+        self.positions
+            .get(&(name.to_string(), arch.to_string()))
+            .map(|&i| &self.packages[i].package)
+    }
+
+    /// Lists left out of the last `load` because they could not be read.
+    pub fn skipped(&self) -> &[String] {
+        &self.skipped
+    }
+
+    /// Matches every whitespace-separated term against name, summary and long
+    /// description (like `apt search`), ranking name hits first.
+    pub fn search(&self, query: &str) -> Vec<Package> {
+        let terms: Vec<String> = query.split_whitespace().map(str::to_lowercase).collect();
+        if terms.is_empty() {
+            return Vec::new();
+        }
+
+        let mut hits: Vec<(u32, &IndexedPackage)> = self
+            .packages
+            .iter()
+            .filter_map(|p| {
+                let mut score = 0;
+                for term in &terms {
+                    score += if p.name == *term {
+                        100
+                    } else if p.name.starts_with(term.as_str()) {
+                        50
+                    } else if p.name.contains(term.as_str()) {
+                        25
+                    } else if p.summary.contains(term.as_str()) {
+                        10
+                    } else if p.description.contains(term.as_str()) {
+                        1
+                    } else {
+                        return None;
+                    };
+                }
+                Some((score, p))
+            })
+            .collect();
+
+        hits.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.name.cmp(&b.1.name)));
+        hits.into_iter()
+            .take(MAX_RESULTS)
+            .map(|(_, p)| p.package.clone())
+            .collect()
+    }
+}
+
+fn read_list(runner: &dyn CommandRunner, path: &Path) -> Result<String> {
+    let is_compressed = path
+        .extension()
+        .is_some_and(|ext| ["lz4", "gz", "xz", "zst", "bz2", "lzma"].contains(&&*ext.to_string_lossy()));
+
+    if !is_compressed {
+        return std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()));
+    }
+
+    let spec = CommandSpec::new("/usr/lib/apt/apt-helper")
+        .args(["cat-file", &path.to_string_lossy()]);
+    let output = runner.output(&spec)?;
+    if !output.success() {
+        anyhow::bail!("Failed to decompress {}: {}", path.display(), output.stderr.trim());
+    }
+    Ok(output.stdout)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PACKAGES: &str = "Package: vim
+Version: 2:9.0.1378-2
+Installed-Size: 3745
+Size: 1715792
+Architecture: amd64
+Description: Vi IMproved - enhanced vi editor
+Description-md5: 59e8b8f7757db8b53566d5d119872de8
+Section: editors
+Priority: optional
+
+Package: neovim
+Version: 0.7.2-7
+Architecture: amd64
+Description: heavily refactored vim fork
+ Neovim is a fork of Vim.
+
+Package: nano
+Version: 7.2-1
+Architecture: amd64
+Description: small, friendly text editor inspired by Pico
+ Clone of Pico with extras like an undo buffer. Not a vi clone.
+";
+
+    const TRANSLATION: &str = "Package: vim
+Description-md5: 59e8b8f7757db8b53566d5d119872de8
+Description-en: Vi IMproved - enhanced vi editor
+ Vim is an almost compatible version of the UNIX editor Vi.
+";
+
+    const SECURITY_PACKAGES: &str = "Package: vim
+Version: 2:9.0.1378-2+deb12u1
+Architecture: amd64
+Description: Vi IMproved - enhanced vi editor
+
+Package: nano
+Version: 7.2-1~bpo11
+Architecture: amd64
+Description: small, friendly text editor inspired by Pico
+";
+
+    fn index() -> PackageIndex {
+        PackageIndex::from_lists(
+            &[("deb.debian.org/debian bookworm/main".to_string(), PACKAGES.to_string())],
+            &[TRANSLATION.to_string()],
+        )
+    }
+
+    #[test]
+    fn test_index_keeps_newest_version() {
+        let index = PackageIndex::from_lists(
+            &[
+                ("deb.debian.org/debian bookworm/main".to_string(), PACKAGES.to_string()),
+                ("deb.debian.org/debian-security bookworm-security/main".to_string(), SECURITY_PACKAGES.to_string()),
+            ],
+            &[],
+        );
+        assert_eq!(index.packages.len(), 3);
+
+        let vim = &index.search("vim")[0];
+        assert_eq!(vim.id.version, "2:9.0.1378-2+deb12u1");
+        assert_eq!(vim.id.origin, "deb.debian.org/debian-security bookworm-security/main");
+
+        let nano = &index.search("nano")[0];
+        assert_eq!(nano.id.version, "7.2-1");
+    }
+
+    #[test]
+    fn test_origin_from_filename() {
+        assert_eq!(
+            origin_from_filename("deb.debian.org_debian_dists_bookworm_main_binary-amd64_Packages.lz4"),
+            "deb.debian.org/debian bookworm/main"
+        );
+        assert_eq!(
+            origin_from_filename("security.debian.org_dists_stable_updates_main_binary-amd64_Packages"),
+            "security.debian.org stable/updates/main"
+        );
+        assert_eq!(
+            origin_from_filename("deb.nodesource.com_node%5f20.x_dists_nodistro_main_binary-amd64_Packages"),
+            "deb.nodesource.com/node_20.x nodistro/main"
+        );
+        assert_eq!(
+            origin_from_filename("download.example.com_repo_._Packages"),
+            "download.example.com/repo/."
+        );
+    }
+
+    #[test]
+    fn test_index_fills_translations() {
+        let index = index();
+        assert_eq!(index.packages.len(), 3);
+
+        let vim = &index.search("vim")[0];
+        assert_eq!(vim.id.name, "vim");
+        assert_eq!(vim.id.version, "2:9.0.1378-2");
+        assert_eq!(vim.id.origin, "deb.debian.org/debian bookworm/main");
+        assert_eq!(vim.summary, "Vi IMproved - enhanced vi editor");
+        assert!(vim.description.contains("almost compatible"));
+        assert_eq!(vim.section, "editors");
+        assert_eq!(vim.size, 3745 * 1024);
+        assert_eq!(vim.download_size, 1715792);
+        assert_eq!(index.get("vim", "amd64").map(|p| p.id.name.as_str()), Some("vim"));
+        assert!(index.get("vim", "i386").is_none());
+    }
+
+    #[test]
+    fn test_load_skips_unreadable_lists() {
+        let dir = std::env::temp_dir().join(format!("lapt-index-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let plain = "deb.debian.org_debian_dists_bookworm_main_binary-amd64_Packages";
+        let broken = "deb.debian.org_debian-security_dists_bookworm-security_main_binary-amd64_Packages.lz4";
+        std::fs::write(dir.join(plain), PACKAGES).unwrap();
+        std::fs::write(dir.join(broken), "not lz4").unwrap();
+
+        let runner = crate::runner::ReplayRunner::new().respond(
+            &["/usr/lib/apt/apt-helper", "cat-file"],
+            crate::runner::CommandOutput {
+                code: Some(1),
+                stdout: String::new(),
+                stderr: "E: Corrupt lz4 stream".to_string(),
+            },
+        );
+        let index = PackageIndex::load(&runner, &dir).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(index.packages.len(), 3);
+        assert!(index.get("nano", "amd64").is_some());
+        assert_eq!(index.skipped().len(), 1);
+        assert!(index.skipped()[0].contains(broken));
+        assert!(index.skipped()[0].contains("Corrupt lz4 stream"));
+    }
+
+    #[test]
+    fn test_search_ranking() {
+        let names: Vec<String> = index().search("vim").into_iter().map(|p| p.id.name).collect();
+        // Exact name, then substring of a name, then description-only match
+        assert_eq!(names, vec!["vim", "neovim"]);
+
+        let names: Vec<String> = index().search("vi clone").into_iter().map(|p| p.id.name).collect();
+        assert_eq!(names, vec!["nano"]);
+
+        let names: Vec<String> = index().search("EDITOR").into_iter().map(|p| p.id.name).collect();
+        assert_eq!(names, vec!["nano", "vim"]);
+
+        assert!(index().search("   ").is_empty());
+    }
+}
@@ -0,0 +1,121 @@
+use serde::{Deserialize, Serialize};
+
+/// Which pipe a line of job output came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LogStream {
+    Stdout,
+    Stderr,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LogLine {
+    pub stream: LogStream,
+    pub text: String,
+}
+
+impl LogLine {
+    pub fn stdout(text: impl Into<String>) -> Self {
+        Self {
+            stream: LogStream::Stdout,
+            text: text.into(),
+        }
+    }
+
+    pub fn stderr(text: impl Into<String>) -> Self {
+        Self {
+            stream: LogStream::Stderr,
+            text: text.into(),
+        }
+    }
+
+    /// Lines apt and dpkg use to report a failure: "E: ...", "Err:1 ...",
+    /// "dpkg: error processing ...", and `pmerror:` status records.
+    pub fn is_error(&self) -> bool {
+        let text = self.text.trim_start();
+        text.starts_with("E: ")
+            || text.starts_with("Err:")
+            || text.starts_with("pmerror:")
+            || text.starts_with("dpkg: error")
+            || text.contains("Errors were encountered")
+    }
+}
+
+/// Everything a job wrote to stdout and stderr, in the order it arrived.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct JobLog {
+    lines: Vec<LogLine>,
+}
+
+impl JobLog {
+    pub fn push(&mut self, line: LogLine) {
+        self.lines.push(line);
+    }
+
+    pub fn lines(&self) -> &[LogLine] {
+        &self.lines
+    }
+
+    pub fn len(&self) -> usize {
+        self.lines.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lines.is_empty()
+    }
+
+    pub fn first_error(&self) -> Option<usize> {
+        self.lines.iter().position(LogLine::is_error)
+    }
+
+    /// First line after `from` containing `query` (case-insensitive),
+    /// wrapping around to the top.
+    pub fn find_next(&self, query: &str, from: usize) -> Option<usize> {
+        if query.is_empty() || self.lines.is_empty() {
+            return None;
+        }
+        let query = query.to_lowercase();
+        let len = self.lines.len();
+        (1..=len)
+            .map(|offset| (from + offset) % len)
+            .find(|&i| self.lines[i].text.to_lowercase().contains(&query))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn log(lines: &[LogLine]) -> JobLog {
+        let mut log = JobLog::default();
+        for line in lines {
+            log.push(line.clone());
+        }
+        log
+    }
+
+    #[test]
+    fn test_first_error() {
+        let log = log(&[
+            LogLine::stdout("Reading package lists..."),
+            LogLine::stderr("W: Target Packages is configured multiple times"),
+            LogLine::stdout("dpkg: error processing package vim (--configure):"),
+            LogLine::stderr("E: Sub-process /usr/bin/dpkg returned an error code (1)"),
+        ]);
+        assert_eq!(log.first_error(), Some(2));
+        assert_eq!(JobLog::default().first_error(), None);
+    }
+
+    #[test]
+    fn test_find_next_wraps_around() {
+        let log = log(&[
+            LogLine::stdout("Unpacking vim (2:9.1) ..."),
+            LogLine::stdout("Setting up nano (7.2-1) ..."),
+            LogLine::stdout("Setting up VIM (2:9.1) ..."),
+        ]);
+        assert_eq!(log.find_next("vim", 0), Some(2));
+        assert_eq!(log.find_next("vim", 2), Some(0));
+        assert_eq!(log.find_next("nano", 1), Some(1));
+        assert_eq!(log.find_next("emacs", 0), None);
+        assert_eq!(log.find_next("", 0), None);
+    }
+}
@@ -0,0 +1,123 @@
+use serde::{Deserialize, Serialize};
+use std::os::unix::fs::MetadataExt;
+use std::path::Path;
+
+/// A process holding the dpkg frontend lock, usually unattended-upgrades
+/// or another apt in a terminal.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LockHolder {
+    pub pid: u32,
+    pub cmdline: String, // Vazio se o processo já terminou
+}
+
+impl LockHolder {
+    /// "/usr/bin/unattended-upgrade (PID 812)".
+    pub fn describe(&self) -> String {
+        if self.cmdline.is_empty() {
+            format!("PID {}", self.pid)
+        } else {
+            format!("{} (PID {})", self.cmdline, self.pid)
+        }
+    }
+}
+
+/// Looks `lock` up in the kernel's lock table (`<proc>/locks`), which,
+/// unlike the fds under `/proc/<pid>`, anyone can read. `None` when the
+/// file is missing or nobody holds a lock on it.
+pub fn find_holder(lock: &Path, proc_dir: &Path) -> Option<LockHolder> {
+    let meta = std::fs::metadata(lock).ok()?;
+    let table = std::fs::read_to_string(proc_dir.join("locks")).ok()?;
+    let pid = parse_locks(&table, dev_major(meta.dev()), dev_minor(meta.dev()), meta.ino())?;
+    let cmdline = std::fs::read(proc_dir.join(pid.to_string()).join("cmdline"))
+        .map(|raw| parse_cmdline(&raw))
+        .unwrap_or_default();
+    Some(LockHolder { pid, cmdline })
+}
+
+/// PID of the process holding a lock on the given device and inode:
+///
+/// ```text
+/// 1: POSIX  ADVISORY  WRITE 812 08:02:1311 0 EOF
+/// 1: -> POSIX  ADVISORY  WRITE 4242 08:02:1311 0 EOF
+/// ```
+///
+/// Lines with `->` are processes waiting for the lock, not holding it.
+fn parse_locks(table: &str, major: u32, minor: u32, inode: u64) -> Option<u32> {
+    table.lines().find_map(|line| {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.get(1) == Some(&"->") {
+            return None;
+        }
+        let pid = fields.get(4)?.parse().ok()?;
+        let mut id = fields.get(5)?.split(':');
+        // Major e minor vêm em hexadecimal, o inode em decimal
+        let matches = u32::from_str_radix(id.next()?, 16).ok()? == major
+            && u32::from_str_radix(id.next()?, 16).ok()? == minor
+            && id.next()?.parse::<u64>().ok()? == inode;
+        matches.then_some(pid)
+    })
+}
+
+fn parse_cmdline(raw: &[u8]) -> String {
+    String::from_utf8_lossy(raw)
+        .split('\0')
+        .filter(|arg| !arg.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+// Codificação do dev_t no Linux (glibc gnu_dev_major/gnu_dev_minor)
+fn dev_major(dev: u64) -> u32 {
+    (((dev >> 8) & 0xfff) | ((dev >> 32) & 0xffff_f000)) as u32
+}
+
+fn dev_minor(dev: u64) -> u32 {
+    ((dev & 0xff) | ((dev >> 12) & 0xffff_ff00)) as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_locks() {
+        let table = "\
+1: POSIX  ADVISORY  WRITE 812 08:02:1311 0 EOF
+1: -> POSIX  ADVISORY  WRITE 4242 08:02:1311 0 EOF
+2: FLOCK  ADVISORY  WRITE 977 00:1a:1311 0 EOF
+3: OFDLCK ADVISORY  READ  -1 08:02:99 0 EOF
+";
+        assert_eq!(parse_locks(table, 8, 2, 1311), Some(812));
+        assert_eq!(parse_locks(table, 0, 0x1a, 1311), Some(977));
+        assert_eq!(parse_locks(table, 8, 2, 99), None);
+        assert_eq!(parse_locks(table, 8, 2, 5), None);
+    }
+
+    #[test]
+    fn test_find_holder() {
+        let dir = std::env::temp_dir().join(format!("lapt-lock-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("812")).unwrap();
+        let lock = dir.join("lock-frontend");
+        std::fs::write(&lock, "").unwrap();
+
+        let meta = std::fs::metadata(&lock).unwrap();
+        let table = format!(
+            "1: POSIX  ADVISORY  WRITE 812 {:02x}:{:02x}:{} 0 EOF\n",
+            dev_major(meta.dev()),
+            dev_minor(meta.dev()),
+            meta.ino()
+        );
+        std::fs::write(dir.join("locks"), table).unwrap();
+        std::fs::write(dir.join("812/cmdline"), b"/usr/bin/python3\0/usr/bin/unattended-upgrade\0").unwrap();
+
+        let holder = find_holder(&lock, &dir).unwrap();
+        assert_eq!(holder.pid, 812);
+        assert_eq!(holder.describe(), "/usr/bin/python3 /usr/bin/unattended-upgrade (PID 812)");
+
+        // Nobody else on the file, or no file at all
+        std::fs::write(dir.join("locks"), "").unwrap();
+        assert_eq!(find_holder(&lock, &dir), None);
+        assert_eq!(find_holder(&dir.join("missing"), &dir), None);
+    }
+}
@@ -3,20 +3,39 @@ mod app;
 mod apt;
 mod backend;
 mod backend_tests;
+mod conffile;
+mod deb822;
+mod debconf;
+mod deps;
+mod dpkg;
+mod helper;
+mod index;
+mod joblog;
+mod lock;
 mod notification_tests;
 mod pkg;
 mod pkg_tests;
+mod privilege;
+mod progress;
+mod protocol;
+mod runner;
+mod scheduler;
+mod simulation;
 mod tab_tests;
+mod transaction;
 mod ui;
 mod ui_tab_tests;
 mod ui_details;
 mod ui_error_tests;
 mod ui_tests;
+mod version;
+mod version_tests;
 
 use crate::app::App;
+use crate::privilege::Privilege;
 use crate::{
     action::Action,
-    backend::{AptBackend, BackendCommand, BackendEvent},
+    backend::{run_backend, AptBackend, BackendCommand, BackendEvent},
 };
 use anyhow::Result;
 use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind};
@@ -25,31 +44,53 @@ use tokio::time::Duration;
 
 #[tokio::main]
 async fn main() -> Result<()> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("helper") {
+        return helper::run(&args[1..]).await;
+    }
+
+    // Sem root, os jobs passam pelo helper, sudo ou pkexec (ou a TUI fica só leitura)
+    let mut privilege = Privilege::detect();
+    let helper = match privilege {
+        Privilege::Root => Ok(None),
+        _ => helper::connect(&helper::socket_path(), 0).await,
+    };
+
     let mut terminal = ratatui::init();
 
     // channels
     let (tx_action, mut rx_action) = mpsc::unbounded_channel::<Action>();
-    let (tx_backend_cmd, mut rx_backend_cmd) = mpsc::unbounded_channel::<BackendCommand>();
+    let (tx_backend_cmd, rx_backend_cmd) = mpsc::unbounded_channel::<BackendCommand>();
 
-    // Backend worker
+    // Transporte: o helper root pelo socket, ou o backend neste processo
     let tx_action_backend = tx_action.clone();
-    tokio::spawn(async move {
-        let backend = match AptBackend::new().await {
-            Ok(b) => b,
-            Err(e) => {
-                let _ = tx_action_backend
-                    .send(Action::BackendResponse(BackendEvent::Error(e.to_string())));
-                return;
-            }
-        };
-
-        while let Some(cmd) = rx_backend_cmd.recv().await {
-            if let Err(e) = backend.handle_command(cmd, tx_action_backend.clone().downgrade_to_backend()).await {
-                let _ = tx_action_backend
-                    .send(Action::BackendResponse(BackendEvent::Error(e.to_string())));
-            }
+    if let Ok(Some(stream)) = helper {
+        privilege = Privilege::Helper;
+        helper::attach(stream, rx_backend_cmd, tx_action_backend.downgrade_to_backend());
+    } else {
+        if let Err(e) = helper {
+            let fallback = if privilege.can_modify() {
+                format!("Changes will go through {} instead.", privilege)
+            } else {
+                "lapt stays read-only.".to_string()
+            };
+            let _ = tx_action_backend
+                .send(Action::BackendResponse(BackendEvent::Error(format!("{:#}\n{}", e, fallback))));
         }
-    });
+        tokio::spawn(async move {
+            let backend = match AptBackend::new(privilege).await {
+                Ok(b) if debconf::enabled() => b.with_debconf(&debconf::default_dir()),
+                Ok(b) => b,
+                Err(e) => {
+                    let _ = tx_action_backend
+                        .send(Action::BackendResponse(BackendEvent::Error(e.to_string())));
+                    return;
+                }
+            };
+
+            run_backend(backend, rx_backend_cmd, tx_action_backend.downgrade_to_backend()).await;
+        });
+    }
 
     // Input loop
     let tx_action_input = tx_action.clone();
@@ -57,10 +98,10 @@ async fn main() -> Result<()> {
         let tick_rate = Duration::from_millis(16);
         loop {
             if event::poll(tick_rate).unwrap() {
-                if let Event::Key(key) = event::read().unwrap() {
-                    if key.kind == KeyEventKind::Press {
-                        let _ = tx_action_input.send(Action::Key(key));
-                    }
+                if let Event::Key(key) = event::read().unwrap()
+                    && key.kind == KeyEventKind::Press
+                {
+                    let _ = tx_action_input.send(Action::Key(key));
                 }
             } else {
                 let _ = tx_action_input.send(Action::Tick);
@@ -69,6 +110,7 @@ async fn main() -> Result<()> {
     });
 
     let mut app = App::new(tx_backend_cmd);
+    app.privilege = privilege;
 
     loop {
         terminal.draw(|f| ui::draw(f, &mut app))?;
@@ -112,17 +154,44 @@ impl ActionSenderExt for mpsc::UnboundedSender<Action> {
 }
 
 fn map_key_to_action(key: KeyEvent, app: &App) -> Option<Action> {
-    if !app.notification_queue.is_empty() {
+    if let Some(notification) = app.notification_queue.first() {
         return match key.code {
+            KeyCode::Enter if notification.remedy.is_some() => Some(Action::ApplyRemedy),
             KeyCode::Enter | KeyCode::Esc => Some(Action::DismissNotification),
             _ => None,
         };
     }
 
+    if let Some(prompt) = &app.debconf_prompt {
+        let typing = prompt.question.kind.takes_text();
+        return match key.code {
+            KeyCode::Enter => Some(Action::DebconfSubmit),
+            KeyCode::Esc => Some(Action::DebconfBack),
+            KeyCode::Backspace if typing => Some(Action::DebconfBackspace),
+            KeyCode::Char(c) if typing => Some(Action::DebconfInput(c)),
+            KeyCode::Char('j') | KeyCode::Down => Some(Action::DebconfNext),
+            KeyCode::Char('k') | KeyCode::Up => Some(Action::DebconfPrev),
+            KeyCode::Char(' ') => Some(Action::DebconfToggle),
+            _ => None,
+        };
+    }
+
+    if app.password_prompt.is_some() {
+        return match key.code {
+            KeyCode::Enter => Some(Action::SubmitPassword),
+            KeyCode::Esc => Some(Action::CancelPassword),
+            KeyCode::Backspace => Some(Action::PasswordBackspace),
+            KeyCode::Char(c) => Some(Action::PasswordInput(c)),
+            _ => None,
+        };
+    }
+
     if app.popup.visible {
         return match key.code {
             KeyCode::Char('y') | KeyCode::Enter => Some(Action::ConfirmAction),
             KeyCode::Char('n') | KeyCode::Esc => Some(Action::CancelAction),
+            KeyCode::Char('j') | KeyCode::Down => Some(Action::ScrollPopupDown),
+            KeyCode::Char('k') | KeyCode::Up => Some(Action::ScrollPopupUp),
             _ => None,
         };
     }
@@ -131,7 +200,7 @@ fn map_key_to_action(key: KeyEvent, app: &App) -> Option<Action> {
         return match key.code {
             KeyCode::Esc => Some(Action::ExitSearchMode),
             KeyCode::Enter => {
-                if app.selected_tab == crate::app::SelectedTab::Online {
+                if app.selected_tab == crate::app::SelectedTab::Online && !app.browsing_log() {
                     Some(Action::TriggerOnlineSearch)
                 } else {
                     Some(Action::ExitSearchMode)
@@ -144,6 +213,10 @@ fn map_key_to_action(key: KeyEvent, app: &App) -> Option<Action> {
     }
 
     match key.code {
+        // Esc desfaz a seleção antes de sair
+        KeyCode::Esc if !app.selection.is_empty() || app.visual_anchor.is_some() => {
+            Some(Action::ClearSelection)
+        }
         KeyCode::Char('q') | KeyCode::Esc => Some(Action::Quit),
         KeyCode::Char('j') | KeyCode::Down => Some(Action::SelectNext),
         KeyCode::Char('k') | KeyCode::Up => Some(Action::SelectPrev),
@@ -151,10 +224,32 @@ fn map_key_to_action(key: KeyEvent, app: &App) -> Option<Action> {
         KeyCode::Char('l') | KeyCode::Right => Some(Action::SwitchTabNext),
         KeyCode::Char('/') => Some(Action::EnterSearchMode),
         KeyCode::Tab => Some(Action::ToggleFocus),
+        KeyCode::Char('D') => Some(Action::ToggleDependencies),
+        KeyCode::Char('Q') => Some(Action::ToggleQueue),
+        KeyCode::Char('L') => Some(Action::ToggleLog),
+        KeyCode::Char('n') => Some(Action::NextLogMatch),
+        KeyCode::Char('E') => Some(Action::JumpToFirstError),
+        KeyCode::Char('C') => Some(Action::ToggleConffiles),
+        KeyCode::Char('t') => Some(Action::AdoptConffile),
+        KeyCode::Char('o') => Some(Action::DiscardConffile),
+        KeyCode::Char('s') => Some(Action::KeepBothConffiles),
+        KeyCode::Char('u') => Some(Action::ToggleConffileDiff),
+        KeyCode::Char('K') => Some(Action::MoveJobUp),
+        KeyCode::Char('J') => Some(Action::MoveJobDown),
+        KeyCode::Char('x') => Some(Action::DropJob),
+        KeyCode::Char('c') => Some(Action::CancelJob),
+        KeyCode::Enter => Some(Action::OpenDependency),
         KeyCode::Char('i') => Some(Action::RequestInstall),
         KeyCode::Char('d') => Some(Action::RequestUninstall),
         KeyCode::Char('r') => Some(Action::RequestReinstall),
+        KeyCode::Char('P') => Some(Action::RequestPurge),
+        KeyCode::Char(' ') => Some(Action::ToggleSelection),
+        KeyCode::Char('v') => Some(Action::ToggleVisualSelection),
         KeyCode::Char('U') => Some(Action::RequestUpgradeSystem),
+        KeyCode::Char('A') => Some(Action::RequestAutoremove),
+        KeyCode::Char('a') => Some(Action::MarkAuto),
+        KeyCode::Char('m') => Some(Action::MarkManual),
+        KeyCode::Char('H') => Some(Action::ToggleHold),
         KeyCode::Char('f') => Some(Action::RefreshRepos),
         _ => None,
     }
@@ -45,4 +45,145 @@ mod tests {
         app.update(crate::action::Action::DismissNotification).unwrap();
         assert_eq!(app.notification_queue.len(), 0);
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn test_cancel_outcomes_are_notified() {
+        use crate::action::Action;
+        use crate::app::NotificationKind;
+        use crate::backend::BackendEvent;
+        let (tx, mut rx) = mpsc::unbounded_channel::<BackendCommand>();
+        let mut app = App::new(tx);
+        while rx.try_recv().is_ok() {}
+
+        app.update(Action::CancelJob).unwrap();
+        assert_eq!(rx.try_recv().unwrap(), BackendCommand::Cancel);
+
+        app.update(Action::BackendResponse(BackendEvent::CancelRefused("dpkg is already unpacking".to_string())))
+            .unwrap();
+        app.update(Action::BackendResponse(BackendEvent::Cancelled(BackendCommand::UpgradeSystem))).unwrap();
+
+        assert_eq!(app.notification_queue[0].kind, NotificationKind::Warning);
+        assert_eq!(app.notification_queue[1], Notification::info("Upgrade system was cancelled".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_failures_offer_a_remedy() {
+        use crate::action::Action;
+        use crate::apt::{AptError, Repair};
+        use crate::backend::BackendEvent;
+        use crate::pkg::PackageId;
+        let (tx, mut rx) = mpsc::unbounded_channel::<BackendCommand>();
+        let mut app = App::new(tx);
+        while rx.try_recv().is_ok() {}
+
+        let install = BackendCommand::Install(PackageId::new("vim", "2:9.1", "amd64", "bookworm"));
+        let failed = |error| {
+            Action::BackendResponse(BackendEvent::Failed(install.clone(), error, "Command failed".to_string()))
+        };
+
+        app.update(failed(AptError::DpkgInterrupted)).unwrap();
+        let notification = &app.notification_queue[0];
+        assert!(notification.message.starts_with("Command failed\n\nA previous installation was interrupted"));
+        assert_eq!(notification.remedy.as_ref().map(|r| r.label.as_str()), Some("Configure pending packages"));
+
+        app.update(Action::ApplyRemedy).unwrap();
+        assert!(app.notification_queue.is_empty());
+        assert_eq!(rx.try_recv().unwrap(), BackendCommand::Repair(Repair::ConfigurePending));
+
+        // A held lock retries the command that hit it
+        app.update(failed(AptError::LockHeld)).unwrap();
+        app.update(Action::ApplyRemedy).unwrap();
+        assert_eq!(rx.try_recv().unwrap(), install);
+
+        app.update(failed(AptError::PackageNotFound("vim".into()))).unwrap();
+        app.update(Action::ApplyRemedy).unwrap();
+        assert_eq!(rx.try_recv().unwrap(), BackendCommand::RefreshRepos);
+
+        // Nothing lapt can run fixes missing root: Enter only dismisses
+        app.update(failed(AptError::PermissionDenied)).unwrap();
+        assert_eq!(app.notification_queue[0].remedy, None);
+        assert!(app.notification_queue[0].message.contains("sudo or pkexec"));
+        app.update(Action::ApplyRemedy).unwrap();
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_waiting_for_lock_offers_cancel() {
+        use crate::action::Action;
+        use crate::app::NotificationKind;
+        use crate::backend::BackendEvent;
+        use crate::lock::LockHolder;
+        let (tx, mut rx) = mpsc::unbounded_channel::<BackendCommand>();
+        let mut app = App::new(tx);
+        while rx.try_recv().is_ok() {}
+
+        let holder = LockHolder { pid: 812, cmdline: "/usr/bin/unattended-upgrade".to_string() };
+        app.update(Action::BackendResponse(BackendEvent::WaitingForLock(holder.clone()))).unwrap();
+        assert_eq!(app.lock_holder, Some(holder));
+        let notification = &app.notification_queue[0];
+        assert_eq!(notification.kind, NotificationKind::Warning);
+        assert!(notification.message.contains("/usr/bin/unattended-upgrade (PID 812)"));
+
+        // Esc keeps waiting; the status zone still shows the holder
+        app.update(Action::DismissNotification).unwrap();
+        assert!(rx.try_recv().is_err());
+        assert!(app.lock_holder.is_some());
+
+        // Once apt reports progress it has the lock
+        let progress = crate::progress::parse_status_line("pmstatus:vim:10:Unpacking vim").unwrap();
+        app.update(Action::BackendResponse(BackendEvent::Progress(progress))).unwrap();
+        assert_eq!(app.lock_holder, None);
+
+        let holder = LockHolder { pid: 900, cmdline: String::new() };
+        app.update(Action::BackendResponse(BackendEvent::WaitingForLock(holder))).unwrap();
+        app.update(Action::ApplyRemedy).unwrap();
+        assert_eq!(rx.try_recv().unwrap(), BackendCommand::Cancel);
+    }
+
+    #[tokio::test]
+    async fn test_privileged_commands_need_elevation() {
+        use crate::action::Action;
+        use crate::backend::BackendEvent;
+        use crate::privilege::{Password, Privilege};
+        let (tx, mut rx) = mpsc::unbounded_channel::<BackendCommand>();
+        let mut app = App::new(tx);
+        while rx.try_recv().is_ok() {}
+
+        // Read-only: browsing works, changes are refused with a reason
+        app.privilege = Privilege::ReadOnly;
+        app.update(Action::RefreshRepos).unwrap();
+        assert!(rx.try_recv().is_err());
+        assert!(app.notification_queue[0].message.contains("read-only"));
+        assert!(app.notification_queue[0].message.contains("LAPT_ELEVATE"));
+        assert!(!app.notification_queue[0].message.contains("Restart it as root"));
+        app.update(Action::DismissNotification).unwrap();
+
+        // sudo: the first change asks for the password and waits for it
+        app.privilege = Privilege::Sudo;
+        app.update(Action::RefreshRepos).unwrap();
+        assert!(app.password_prompt.is_some());
+        assert!(rx.try_recv().is_err());
+
+        for c in "hunter2".chars() {
+            app.update(Action::PasswordInput(c)).unwrap();
+        }
+        app.update(Action::PasswordBackspace).unwrap();
+        app.update(Action::SubmitPassword).unwrap();
+        assert_eq!(rx.try_recv().unwrap(), BackendCommand::Authenticate(Password::new("hunter".into())));
+
+        app.update(Action::BackendResponse(BackendEvent::AuthenticationFailed("incorrect password".into()))).unwrap();
+        let prompt = app.password_prompt.as_ref().unwrap();
+        assert_eq!(prompt.error.as_deref(), Some("incorrect password"));
+        assert!(prompt.input.is_empty());
+
+        app.update(Action::SubmitPassword).unwrap();
+        rx.try_recv().unwrap();
+        app.update(Action::BackendResponse(BackendEvent::Authenticated)).unwrap();
+        assert_eq!(rx.try_recv().unwrap(), BackendCommand::RefreshRepos);
+
+        // Cached for the session
+        app.update(Action::RefreshRepos).unwrap();
+        assert_eq!(rx.try_recv().unwrap(), BackendCommand::RefreshRepos);
+        assert!(app.password_prompt.is_none());
+    }
+}
@@ -1,42 +1,227 @@
-#[derive(Debug, Clone, PartialEq)]
-pub struct Package {
-    pub id: String, // ID único do PackageKit
+use crate::deps::{Relation, RelationKind};
+use crate::version::PackageVersion;
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+
+/// Identifies one exact build of a package: which version, for which
+/// architecture, from where.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PackageId {
     pub name: String,
-    pub version: String,
+    pub version: PackageVersion,
     pub arch: String,
-    pub data: String, // Repositório ou dados extras
-    pub status: String,
+    pub origin: String, // Repositório ("deb.debian.org/debian bookworm/main") ou "installed"
+}
+
+impl PackageId {
+    pub fn new(name: &str, version: &str, arch: &str, origin: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            version: PackageVersion::parse(version),
+            arch: arch.to_string(),
+            origin: origin.to_string(),
+        }
+    }
+
+    /// Parses a PackageKit-style `name;version;arch;data` id, a compact way to
+    /// spell packages in tests.
+    #[cfg(test)]
+    pub fn from_packagekit(id: &str) -> Self {
+        let mut parts = id.split(';');
+        let mut next = || parts.next().unwrap_or("");
+        let (name, version, arch, origin) = (next(), next(), next(), next());
+        Self::new(name, version, arch, origin)
+    }
+
+    /// `name:arch`, how apt addresses an installed package.
+    pub fn apt_name(&self) -> String {
+        if self.arch.is_empty() {
+            self.name.clone()
+        } else {
+            format!("{}:{}", self.name, self.arch)
+        }
+    }
+
+    /// Checks the id against Debian's grammar before it becomes an apt-get
+    /// argument: a client could otherwise slip `./evil.deb` or an option
+    /// past apt running as root.
+    pub fn validate(&self) -> Result<()> {
+        // Nome: [a-z0-9][a-z0-9+.-]+
+        let mut name = self.name.chars();
+        let valid_name = self.name.len() >= 2
+            && name.next().is_some_and(|c| c.is_ascii_lowercase() || c.is_ascii_digit())
+            && name.all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || "+.-".contains(c));
+        if !valid_name {
+            bail!("'{}' is not a valid package name", self.name);
+        }
+        let valid_arch = self.arch.is_empty()
+            || (!self.arch.starts_with('-')
+                && self.arch.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || "_-".contains(c)));
+        if !valid_arch {
+            bail!("'{}' is not a valid architecture", self.arch);
+        }
+        // Epoch e upstream começam com um dígito
+        let version = self.version.as_str();
+        let valid_version = version.is_empty()
+            || (version.starts_with(|c: char| c.is_ascii_digit())
+                && version.chars().all(|c| c.is_ascii_alphanumeric() || ".+~:-".contains(c)));
+        if !valid_version {
+            bail!("'{}' is not a valid version", version);
+        }
+        Ok(())
+    }
+
+    /// `name:arch=version`, pinning apt to exactly this build.
+    pub fn apt_target(&self) -> String {
+        if self.version.as_str().is_empty() {
+            self.apt_name()
+        } else {
+            format!("{}={}", self.apt_name(), self.version)
+        }
+    }
+}
+
+/// Where a package stands on this system.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PackageState {
+    #[default]
+    Available,
+    Installed,
+    ConfigFiles, // Removido, mas os arquivos de configuração ficaram
+    Broken,      // dpkg parou no meio (unpacked, half-configured, reinstreq...)
+}
+
+/// State plus the flags apt and dpkg keep alongside it.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PackageStatus {
+    pub state: PackageState,
+    pub automatic: bool, // Instalado como dependência (apt-mark auto)
+    pub held: bool,      // dpkg want=hold (apt-mark hold)
+    pub upgradable_from: Option<PackageVersion>, // Versão instalada, quando esta é mais nova
+}
+
+impl PackageStatus {
+    pub fn is_installed(&self) -> bool {
+        matches!(self.state, PackageState::Installed | PackageState::Broken)
+    }
+
+    pub fn is_upgradable(&self) -> bool {
+        self.upgradable_from.is_some()
+    }
+
+    /// Human readable form, e.g. "Installed (automatic, held)".
+    pub fn label(&self) -> String {
+        let base = match self.state {
+            PackageState::Available => "Available",
+            PackageState::Installed => "Installed",
+            PackageState::ConfigFiles => "Config files only",
+            PackageState::Broken => "Broken",
+        };
+
+        let mut flags = Vec::new();
+        if let Some(from) = &self.upgradable_from {
+            flags.push(format!("upgradable from {}", from));
+        }
+        if self.automatic {
+            flags.push("automatic".to_string());
+        }
+        if self.held {
+            flags.push("held".to_string());
+        }
+
+        if flags.is_empty() {
+            base.to_string()
+        } else {
+            format!("{} ({})", base, flags.join(", "))
+        }
+    }
+}
+
+/// Flags `apt-mark` can flip on an installed package.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PackageMark {
+    Auto,
+    Manual,
+    Hold,
+    Unhold,
+}
+
+impl PackageMark {
+    /// The `apt-mark` subcommand.
+    pub fn as_arg(self) -> &'static str {
+        match self {
+            Self::Auto => "auto",
+            Self::Manual => "manual",
+            Self::Hold => "hold",
+            Self::Unhold => "unhold",
+        }
+    }
+
+    pub fn apply(self, status: &mut PackageStatus) {
+        match self {
+            Self::Auto => status.automatic = true,
+            Self::Manual => status.automatic = false,
+            Self::Hold => status.held = true,
+            Self::Unhold => status.held = false,
+        }
+    }
+}
+
+impl From<PackageState> for PackageStatus {
+    fn from(state: PackageState) -> Self {
+        Self {
+            state,
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Package {
+    pub id: PackageId,
+    pub status: PackageStatus,
     pub summary: String,
     pub description: String,
     pub license: String,
-    pub size: u64,
+    pub size: u64,          // Installed-Size, em bytes
+    pub download_size: u64, // Tamanho do .deb (campo Size), quando vem de um repositório
     pub url: String,
+    pub section: String,
+    pub priority: String,
+    pub maintainer: String,
+    pub source: String,
+    pub depends: String,
+    pub relations: Vec<Relation>, // Depends, Recommends, Provides... já interpretados
 }
 
 impl Package {
-    pub fn from_packagekit(id: &str, status: &str, summary: &str) -> Self {
-        let parts: Vec<&str> = id.split(';').collect();
-
-        let name = parts.get(0).unwrap_or(&"?").to_string();
-        let version = parts.get(1).unwrap_or(&"?").to_string();
-        let arch = parts.get(2).unwrap_or(&"?").to_string();
-        let data = parts.get(3).unwrap_or(&"?").to_string();
-
+    pub fn new(id: PackageId, status: PackageStatus, summary: &str) -> Self {
         Self {
-            id: id.to_string(),
-            name,
-            version,
-            arch,
-            data,
-            status: status.to_string(),
+            id,
+            status,
             summary: summary.to_string(),
             description: String::new(),
             license: String::new(),
             size: 0,
             url: String::new(),
+            ..Default::default()
         }
     }
 
+    #[cfg(test)]
+    pub fn from_packagekit(id: &str, status: PackageState, summary: &str) -> Self {
+        Self::new(PackageId::from_packagekit(id), status.into(), summary)
+    }
+
+    /// Whether this package is `name` or declares `Provides: name`.
+    pub fn provides(&self, name: &str) -> bool {
+        self.id.name == name
+            || self
+                .relations
+                .iter()
+                .any(|r| r.kind == RelationKind::Provides && r.names().any(|n| n == name))
+    }
+
     pub fn update_details(
         &mut self,
         description: &str,
@@ -1,38 +1,52 @@
 #[cfg(test)]
 mod tests {
-    use crate::pkg::Package;
+    use crate::pkg::{Package, PackageId, PackageState, PackageStatus};
 
     #[test]
     fn test_package_new_fields() {
         let pkg = Package {
-            id: "vim;8.2.1234;x86_64;updates".to_string(),
-            name: "vim".to_string(),
-            version: "8.2.1234".to_string(),
-            arch: "x86_64".to_string(),
-            data: "updates".to_string(),
-            status: "installed".to_string(),
+            id: PackageId::new("vim", "8.2.1234", "x86_64", "updates"),
+            status: PackageStatus {
+                state: PackageState::Installed,
+                automatic: false,
+                held: false,
+                upgradable_from: None,
+            },
             summary: "Vi IMproved, a programmers tool that is largely compatible with Vi".to_string(),
             description: "Vim is a text editor that is upwards compatible to Vi. It can be used to edit all kinds of plain text. It is especially useful for editing programs.".to_string(),
             license: "Vim".to_string(),
             size: 1234567,
+            download_size: 456789,
             url: "https://www.vim.org/".to_string(),
+            section: "editors".to_string(),
+            priority: "optional".to_string(),
+            maintainer: "Debian Vim Maintainers".to_string(),
+            source: "vim".to_string(),
+            depends: "vim-common, libc6 (>= 2.34)".to_string(),
+            relations: crate::deps::parse_field(crate::deps::RelationKind::Depends, "vim-common, libc6 (>= 2.34)"),
         };
 
+        assert_eq!(pkg.id.name, "vim");
+        assert_eq!(pkg.id.origin, "updates");
         assert_eq!(pkg.summary, "Vi IMproved, a programmers tool that is largely compatible with Vi");
         assert_eq!(pkg.license, "Vim");
         assert_eq!(pkg.size, 1234567);
         assert_eq!(pkg.url, "https://www.vim.org/");
+        assert_eq!(pkg.section, "editors");
+        assert!(!pkg.status.automatic);
+        assert_eq!(pkg.relations.len(), 2);
     }
 
     #[test]
     fn test_from_packagekit_updated() {
         let pkg = Package::from_packagekit(
             "vim;8.2.1234;x86_64;updates",
-            "installed",
+            PackageState::Installed,
             "Vi IMproved"
         );
 
-        assert_eq!(pkg.name, "vim");
+        assert_eq!(pkg.id.name, "vim");
+        assert_eq!(pkg.id.origin, "updates");
         assert_eq!(pkg.summary, "Vi IMproved");
         assert_eq!(pkg.description, ""); // Should be empty by default
         assert_eq!(pkg.license, "");
@@ -44,7 +58,7 @@ mod tests {
     fn test_update_details() {
         let mut pkg = Package::from_packagekit(
             "vim;8.2.1234;x86_64;updates",
-            "installed",
+            PackageState::Installed,
             "Vi IMproved"
         );
 
@@ -60,4 +74,42 @@ mod tests {
         assert_eq!(pkg.size, 9999);
         assert_eq!(pkg.url, "https://vim.org");
     }
+
+    #[test]
+    fn test_package_id_apt_rendering() {
+        let id = PackageId::new("vim", "2:9.0.1378-2", "amd64", "deb.debian.org/debian bookworm/main");
+        assert_eq!(id.apt_name(), "vim:amd64");
+        assert_eq!(id.apt_target(), "vim:amd64=2:9.0.1378-2");
+
+        // Missing parts are left out instead of producing "vim:=" targets
+        let bare = PackageId::from_packagekit("vim");
+        assert_eq!(bare.apt_name(), "vim");
+        assert_eq!(bare.apt_target(), "vim");
+        assert_eq!(PackageId::from_packagekit("vim;1.0").apt_target(), "vim=1.0");
+    }
+
+    #[test]
+    fn test_package_id_validate() {
+        for id in ["vim;2:9.0.1378-2;amd64", "libstdc++6;14.2.0-4ubuntu2~24.04", "g++", "0ad;0.0.26-3;i386", "tzdata;;all"] {
+            assert!(PackageId::from_packagekit(id).validate().is_ok(), "{}", id);
+        }
+        for id in ["./evil.deb", "/tmp/evil.deb", "-oAPT::Get::Assume-Yes=1", "Vim", "v", "vim;;../amd64", "vim;--purge", "vim;1.0 extra"] {
+            assert!(PackageId::from_packagekit(id).validate().is_err(), "{}", id);
+        }
+    }
+
+    #[test]
+    fn test_status_label() {
+        assert_eq!(PackageStatus::from(PackageState::Available).label(), "Available");
+        assert_eq!(PackageStatus::from(PackageState::ConfigFiles).label(), "Config files only");
+
+        let status = PackageStatus {
+            state: PackageState::Installed,
+            automatic: true,
+            held: true,
+            upgradable_from: Some("1.0-1".into()),
+        };
+        assert!(status.is_installed());
+        assert_eq!(status.label(), "Installed (upgradable from 1.0-1, automatic, held)");
+    }
 }
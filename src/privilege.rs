@@ -0,0 +1,212 @@
+use crate::runner::{CommandOutput, CommandRunner, CommandSpec, RunningCommand};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use strum::Display;
+
+/// How lapt gets root for the commands that change the system.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display)]
+pub enum Privilege {
+    #[strum(to_string = "root")]
+    Root, // Já rodando como root
+    #[strum(to_string = "sudo")]
+    Sudo, // sudo -S, senha digitada na TUI
+    #[strum(to_string = "pkexec")]
+    Pkexec, // O agente do polkit pede a senha
+    #[strum(to_string = "helper")]
+    Helper, // Os comandos vão para o `lapt helper` pelo socket
+    #[strum(to_string = "read-only")]
+    ReadOnly, // Sem como elevar: só navegação
+}
+
+impl Privilege {
+    /// Root when lapt runs as root. Otherwise the helper named by
+    /// `$LAPT_ELEVATE` (`sudo`, `pkexec` or `none`), falling back to
+    /// whichever of sudo and pkexec is installed, and read-only without
+    /// either.
+    pub fn detect() -> Self {
+        let status = std::fs::read_to_string("/proc/self/status").unwrap_or_default();
+        if is_root(&status) {
+            return Self::Root;
+        }
+        match std::env::var("LAPT_ELEVATE").as_deref() {
+            Ok("sudo") => Self::Sudo,
+            Ok("pkexec") => Self::Pkexec,
+            Ok("none") => Self::ReadOnly,
+            _ if in_path("sudo") => Self::Sudo,
+            _ if in_path("pkexec") => Self::Pkexec,
+            _ => Self::ReadOnly,
+        }
+    }
+
+    pub fn can_modify(self) -> bool {
+        self != Self::ReadOnly
+    }
+
+    /// sudo gets its password from the TUI; pkexec asks through polkit.
+    pub fn needs_password(self) -> bool {
+        self == Self::Sudo
+    }
+}
+
+/// Whether the effective uid on the `Uid:` line of `/proc/<pid>/status`
+/// (real, effective, saved, filesystem) is 0.
+fn is_root(status: &str) -> bool {
+    status
+        .lines()
+        .find_map(|line| line.strip_prefix("Uid:"))
+        .and_then(|uids| uids.split_whitespace().nth(1))
+        == Some("0")
+}
+
+fn in_path(program: &str) -> bool {
+    std::env::var_os("PATH")
+        .is_some_and(|path| std::env::split_paths(&path).any(|dir| Path::new(&dir).join(program).is_file()))
+}
+
+/// The sudo password, kept for the session. Never printed.
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Password(String);
+
+impl Password {
+    pub fn new(password: String) -> Self {
+        Self(password)
+    }
+}
+
+impl std::fmt::Debug for Password {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Password(***)")
+    }
+}
+
+/// Runs every command through the configured helper: `sudo -S` fed the
+/// session password on stdin, or `pkexec`. The environment is passed with
+/// env(1), since both helpers reset it.
+pub struct ElevatedRunner {
+    inner: Arc<dyn CommandRunner>,
+    privilege: Privilege,
+    password: Mutex<Option<Password>>,
+}
+
+impl ElevatedRunner {
+    pub fn new(inner: Arc<dyn CommandRunner>, privilege: Privilege) -> Self {
+        Self {
+            inner,
+            privilege,
+            password: Mutex::new(None),
+        }
+    }
+
+    pub fn elevate(&self, spec: &CommandSpec) -> CommandSpec {
+        let helper = match self.privilege {
+            Privilege::Root | Privilege::Helper | Privilege::ReadOnly => return spec.clone(),
+            // -k: sempre pede a senha, então ela nunca sobra no stdin do apt
+            Privilege::Sudo => CommandSpec::new("sudo").args(["-S", "-k", "-p", ""]),
+            Privilege::Pkexec => CommandSpec::new("pkexec"),
+        };
+        let mut elevated = helper
+            .args(["env"])
+            .args(spec.env.iter().map(|(k, v)| format!("{}={}", k, v)))
+            .args(spec.argv());
+        if self.privilege == Privilege::Sudo
+            && let Some(Password(password)) = self.password.lock().unwrap().as_ref()
+        {
+            elevated = elevated.stdin(&format!("{}\n", password));
+        }
+        elevated
+    }
+
+    /// Checks `password` with a no-op through sudo and keeps it for the
+    /// session if it works.
+    pub fn authenticate(&self, password: Password) -> Result<()> {
+        if !self.privilege.needs_password() {
+            return Ok(());
+        }
+        let check = CommandSpec::new("sudo")
+            .args(["-S", "-k", "-p", "", "true"])
+            .stdin(&format!("{}\n", password.0));
+        let output = self.inner.output(&check)?;
+        if !output.success() {
+            let reason = output.stderr.lines().last().unwrap_or("incorrect password").trim();
+            anyhow::bail!("{}", reason.strip_prefix("sudo: ").unwrap_or(reason));
+        }
+        *self.password.lock().unwrap() = Some(password);
+        Ok(())
+    }
+}
+
+impl CommandRunner for ElevatedRunner {
+    fn output(&self, spec: &CommandSpec) -> Result<CommandOutput> {
+        self.inner.output(&self.elevate(spec))
+    }
+
+    fn spawn(&self, spec: &CommandSpec) -> Result<RunningCommand> {
+        self.inner.spawn(&self.elevate(spec))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runner::ReplayRunner;
+
+    #[test]
+    fn test_is_root() {
+        assert!(is_root("Name:\tlapt\nUid:\t1000\t0\t0\t0\nGid:\t1000\t1000\t1000\t1000\n"));
+        assert!(!is_root("Name:\tlapt\nUid:\t1000\t1000\t1000\t1000\n"));
+        assert!(!is_root(""));
+    }
+
+    #[test]
+    fn test_elevate() {
+        let spec = CommandSpec::new("apt-get")
+            .env("DEBIAN_FRONTEND", "noninteractive")
+            .args(["install", "-y", "vim"]);
+
+        let root = ElevatedRunner::new(Arc::new(ReplayRunner::new()), Privilege::Root);
+        assert_eq!(root.elevate(&spec), spec);
+
+        let pkexec = ElevatedRunner::new(Arc::new(ReplayRunner::new()), Privilege::Pkexec);
+        assert_eq!(
+            pkexec.elevate(&spec).argv(),
+            vec!["pkexec", "env", "DEBIAN_FRONTEND=noninteractive", "apt-get", "install", "-y", "vim"]
+        );
+
+        let sudo = ElevatedRunner::new(Arc::new(ReplayRunner::new()), Privilege::Sudo);
+        assert_eq!(sudo.elevate(&spec).argv()[..5], ["sudo", "-S", "-k", "-p", ""]);
+        assert_eq!(sudo.elevate(&spec).stdin, None);
+    }
+
+    #[test]
+    fn test_sudo_authentication() {
+        let runner = Arc::new(
+            ReplayRunner::new().respond(&["sudo", "-S", "-k", "-p", "", "true"], CommandOutput { code: Some(0), ..CommandOutput::default() }),
+        );
+        let sudo = ElevatedRunner::new(runner.clone(), Privilege::Sudo);
+
+        sudo.authenticate(Password::new("hunter2".into())).unwrap();
+        assert_eq!(runner.calls()[0].stdin.as_deref(), Some("hunter2\n"));
+
+        // The password rides along on stdin from now on, and stays out of Debug
+        let spec = sudo.elevate(&CommandSpec::new("apt-get").args(["update"]));
+        assert_eq!(spec.stdin.as_deref(), Some("hunter2\n"));
+        assert!(!format!("{:?}", spec).contains("hunter2"));
+
+        let refused = ElevatedRunner::new(
+            Arc::new(ReplayRunner::new().respond(
+                &["sudo"],
+                CommandOutput {
+                    code: Some(1),
+                    stdout: String::new(),
+                    stderr: "sudo: 1 incorrect password attempt\n".into(),
+                },
+            )),
+            Privilege::Sudo,
+        );
+        let err = refused.authenticate(Password::new("wrong".into())).unwrap_err();
+        assert_eq!(err.to_string(), "1 incorrect password attempt");
+        assert_eq!(refused.elevate(&CommandSpec::new("true")).stdin, None);
+    }
+}
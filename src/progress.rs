@@ -0,0 +1,183 @@
+use serde::{Deserialize, Serialize};
+use std::time::Instant;
+
+/// Which half of a transaction a status record belongs to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ProgressPhase {
+    Download, // dlstatus
+    Install,  // pmstatus (dpkg)
+}
+
+/// One `APT::Status-Fd` record, plus the speed lapt works out for downloads.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Progress {
+    pub phase: ProgressPhase,
+    pub percent: f64,
+    pub package: String,     // Vazio durante o download
+    pub message: String,     // "Unpacking vim (amd64)", "Retrieving file 1 of 3"
+    pub eta: Option<String>, // "1min 5s"
+    pub speed: Option<u64>,  // Bytes/s
+}
+
+/// Parses the machine-readable lines apt writes to `APT::Status-Fd`:
+///
+/// ```text
+/// dlstatus:2:45.3:Retrieving file 2 of 3 (5s remaining)
+/// pmstatus:vim:66.6667:Unpacking vim (amd64)
+/// ```
+///
+/// Other records (`pmerror`, `pmconffile`, ...) and ordinary output return
+/// `None`.
+pub fn parse_status_line(line: &str) -> Option<Progress> {
+    let (kind, rest) = line.trim_end().split_once(':')?;
+    let phase = match kind {
+        "dlstatus" => ProgressPhase::Download,
+        "pmstatus" => ProgressPhase::Install,
+        _ => return None,
+    };
+    // Multiarch packages come as "libc6:i386", so the item may itself
+    // contain a colon; the percentage is the first numeric field after it
+    let fields: Vec<&str> = rest.splitn(4, ':').collect();
+    let (item, percent, mut message) = match fields.as_slice() {
+        [item, percent, message @ ..] if percent.parse::<f64>().is_ok() => {
+            (item.to_string(), *percent, message.join(":"))
+        }
+        [name, arch, percent, message @ ..] => (format!("{}:{}", name, arch), *percent, message.join(":")),
+        _ => return None,
+    };
+    let percent: f64 = percent.parse().ok()?;
+
+    // apt appends its own estimate to download messages
+    let mut eta = None;
+    if let Some(start) = message.rfind(" (")
+        && let Some(remaining) = message[start + 2..].strip_suffix(" remaining)")
+    {
+        eta = Some(remaining.to_string());
+        message.truncate(start);
+    }
+
+    Some(Progress {
+        phase,
+        percent: percent.clamp(0.0, 100.0),
+        package: match phase {
+            ProgressPhase::Download => String::new(),
+            ProgressPhase::Install => item,
+        },
+        message,
+        eta,
+        speed: None,
+    })
+}
+
+/// Bytes apt is about to download, from "Need to get 1,234 kB/5,678 kB of
+/// archives." (the part after the slash is the total including the cache).
+pub fn parse_need_to_get(line: &str) -> Option<u64> {
+    let rest = line.strip_prefix("Need to get ")?;
+    let (size, _) = rest.split_once(" of archives")?;
+    let size = size.split('/').next()?.replace(',', "");
+    Some(crate::apt::parse_size(&size))
+}
+
+/// Average download speed since the first record, derived from the
+/// download percentage and the size apt announced with "Need to get".
+#[derive(Debug, Default)]
+pub struct SpeedMeter {
+    total: Option<u64>,
+    started: Option<Instant>,
+}
+
+impl SpeedMeter {
+    pub fn set_total(&mut self, bytes: u64) {
+        self.total = Some(bytes);
+    }
+
+    /// Fills in `speed`, and `eta` when apt gave none, for a download record.
+    pub fn update(&mut self, progress: &mut Progress) {
+        if progress.phase != ProgressPhase::Download {
+            return;
+        }
+        let started = *self.started.get_or_insert_with(Instant::now);
+        self.fill(progress, started.elapsed().as_secs_f64());
+    }
+
+    fn fill(&self, progress: &mut Progress, elapsed: f64) {
+        let Some(total) = self.total.filter(|t| *t > 0) else {
+            return;
+        };
+        if elapsed <= 0.0 {
+            return;
+        }
+        let done = total as f64 * progress.percent / 100.0;
+        let speed = done / elapsed;
+        progress.speed = Some(speed as u64);
+        if progress.eta.is_none() && speed > 0.0 {
+            progress.eta = Some(format_duration(((total as f64 - done) / speed) as u64));
+        }
+    }
+}
+
+/// "45s", "2min 5s", "1h 3min", the way apt prints its estimates.
+pub fn format_duration(secs: u64) -> String {
+    match secs {
+        0..60 => format!("{}s", secs),
+        60..3600 => format!("{}min {}s", secs / 60, secs % 60),
+        _ => format!("{}h {}min", secs / 3600, (secs % 3600) / 60),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_status_line() {
+        let dl = parse_status_line("dlstatus:2:45.3:Retrieving file 2 of 3 (1min 5s remaining)").unwrap();
+        assert_eq!(dl.phase, ProgressPhase::Download);
+        assert_eq!(dl.percent, 45.3);
+        assert_eq!(dl.message, "Retrieving file 2 of 3");
+        assert_eq!(dl.eta.as_deref(), Some("1min 5s"));
+        assert_eq!(dl.package, "");
+
+        let pm = parse_status_line("pmstatus:vim:66.6667:Unpacking vim (amd64)").unwrap();
+        assert_eq!(pm.phase, ProgressPhase::Install);
+        assert_eq!(pm.package, "vim");
+        assert_eq!(pm.message, "Unpacking vim (amd64)");
+        assert_eq!(pm.eta, None);
+
+        // Both the package and the message may contain colons
+        let pm = parse_status_line("pmstatus:libc6:i386:10:Preparing libc6:i386 (i386)").unwrap();
+        assert_eq!(pm.package, "libc6:i386");
+        assert_eq!(pm.percent, 10.0);
+        assert_eq!(pm.message, "Preparing libc6:i386 (i386)");
+        let pm = parse_status_line("pmstatus:dpkg-exec:0:Running dpkg: stage 1").unwrap();
+        assert_eq!(pm.message, "Running dpkg: stage 1");
+
+        assert_eq!(parse_status_line("pmerror:/var/cache/apt/archives/x.deb:50:trying to overwrite"), None);
+        assert_eq!(parse_status_line("Get:1 http://deb.debian.org/debian bookworm/main vim"), None);
+    }
+
+    #[test]
+    fn test_parse_need_to_get() {
+        assert_eq!(parse_need_to_get("Need to get 1,234 kB of archives."), Some(1234 * 1024));
+        assert_eq!(parse_need_to_get("Need to get 2.5 MB/9.6 MB of archives."), Some(2_621_440));
+        assert_eq!(parse_need_to_get("After this operation, 3 MB of additional disk space"), None);
+    }
+
+    #[test]
+    fn test_speed_meter() {
+        let mut meter = SpeedMeter::default();
+        let mut progress = parse_status_line("dlstatus:1:25:Retrieving file 1 of 2").unwrap();
+
+        // Unknown total: nothing to derive
+        meter.fill(&mut progress, 2.0);
+        assert_eq!(progress.speed, None);
+
+        meter.set_total(4_000_000);
+        meter.fill(&mut progress, 2.0);
+        assert_eq!(progress.speed, Some(500_000));
+        assert_eq!(progress.eta.as_deref(), Some("6s"));
+
+        assert_eq!(format_duration(125), "2min 5s");
+        assert_eq!(format_duration(3780), "1h 3min");
+    }
+}
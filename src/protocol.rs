@@ -0,0 +1,74 @@
+use crate::backend::{BackendCommand, BackendEvent};
+use anyhow::{Context, Result};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+/// Bumped whenever a message, command or event changes shape, so a TUI and
+/// a helper from different lapt builds refuse each other up front instead
+/// of failing on the first message they cannot read.
+pub const PROTOCOL_VERSION: u32 = 7;
+
+/// What the TUI sends the helper. `Hello` first, then only commands.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ClientMessage {
+    Hello { version: u32 },
+    Command(BackendCommand),
+}
+
+/// What the helper sends back. `Welcome` or `Refused` answers the `Hello`;
+/// after a `Welcome`, only events.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum HelperMessage {
+    Welcome { version: u32 },
+    Refused(String), // Motivo; o helper fecha a conexão em seguida
+    Event(BackendEvent),
+}
+
+/// One message per line: JSON never contains a raw newline.
+pub fn encode<T: Serialize>(message: &T) -> Result<String> {
+    let mut line = serde_json::to_string(message)?;
+    line.push('\n');
+    Ok(line)
+}
+
+pub fn decode<T: DeserializeOwned>(line: &str) -> Result<T> {
+    serde_json::from_str(line.trim_end()).context("Malformed message from the lapt helper connection")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::apt::AptError;
+    use crate::pkg::{Package, PackageId, PackageState};
+    use crate::version::PackageVersion;
+
+    #[test]
+    fn test_round_trip() {
+        let pkg = Package::from_packagekit("vim;2:9.1.0016-1;amd64;installed", PackageState::Installed, "Vi IMproved");
+        let messages = [
+            HelperMessage::Welcome { version: PROTOCOL_VERSION },
+            HelperMessage::Event(BackendEvent::InstalledPackagesFound(vec![pkg])),
+            HelperMessage::Event(BackendEvent::Failed(
+                BackendCommand::Install(PackageId::new("nosuch", "", "", "")),
+                AptError::PackageNotFound("nosuch".into()),
+                "E: Unable to locate package nosuch\nsecond line".into(),
+            )),
+        ];
+        for message in messages {
+            let line = encode(&message).unwrap();
+            assert_eq!(line.matches('\n').count(), 1);
+            assert_eq!(decode::<HelperMessage>(&line).unwrap(), message);
+        }
+
+        // Versions travel as text and are parsed again on the other side
+        let line = encode(&PackageVersion::parse("1:2.0~rc1-3")).unwrap();
+        assert_eq!(line, "\"1:2.0~rc1-3\"\n");
+        assert!(decode::<PackageVersion>(&line).unwrap() < PackageVersion::parse("1:2.0-1"));
+    }
+
+    #[test]
+    fn test_decode_rejects_garbage() {
+        assert!(decode::<ClientMessage>("{\"Hello\":{}}").is_err());
+        assert!(decode::<ClientMessage>("GET / HTTP/1.1").is_err());
+    }
+}
@@ -0,0 +1,251 @@
+use anyhow::{Context, Result};
+use std::process::Stdio;
+use tokio::io::AsyncRead;
+
+/// A fully described external invocation: program, arguments, the extra
+/// environment and the input it runs with. Runners receive this instead of
+/// building `Command`s themselves so every call can be recorded and replayed.
+#[derive(Clone, PartialEq, Eq)]
+pub struct CommandSpec {
+    pub program: String,
+    pub args: Vec<String>,
+    pub env: Vec<(String, String)>,
+    pub stdin: Option<String>, // Pode levar a senha do sudo
+}
+
+impl std::fmt::Debug for CommandSpec {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CommandSpec")
+            .field("program", &self.program)
+            .field("args", &self.args)
+            .field("env", &self.env)
+            .field("stdin", &self.stdin.as_ref().map(|_| "<redacted>"))
+            .finish()
+    }
+}
+
+impl CommandSpec {
+    pub fn new(program: &str) -> Self {
+        Self {
+            program: program.to_string(),
+            args: Vec::new(),
+            env: Vec::new(),
+            stdin: None,
+        }
+    }
+
+    pub fn args<I, S>(mut self, args: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        self.args
+            .extend(args.into_iter().map(|a| a.as_ref().to_string()));
+        self
+    }
+
+    pub fn env(mut self, key: &str, value: &str) -> Self {
+        self.env.push((key.to_string(), value.to_string()));
+        self
+    }
+
+    /// Text written to the command's stdin, which is then closed.
+    pub fn stdin(mut self, input: &str) -> Self {
+        self.stdin = Some(input.to_string());
+        self
+    }
+
+    /// Program followed by its arguments, as typed on a shell.
+    pub fn argv(&self) -> Vec<&str> {
+        std::iter::once(self.program.as_str())
+            .chain(self.args.iter().map(String::as_str))
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CommandOutput {
+    /// Exit code, `None` when the process was killed by a signal.
+    pub code: Option<i32>,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+impl CommandOutput {
+    pub fn success(&self) -> bool {
+        self.code == Some(0)
+    }
+}
+
+type OutputStream = Box<dyn AsyncRead + Send + Unpin>;
+
+enum Handle {
+    Process(tokio::process::Child),
+    #[cfg(test)]
+    Replayed(Option<i32>),
+}
+
+/// A spawned command whose stdout/stderr can be streamed line by line.
+pub struct RunningCommand {
+    pub stdout: Option<OutputStream>,
+    pub stderr: Option<OutputStream>,
+    handle: Handle,
+}
+
+impl RunningCommand {
+    /// OS process id, while the process is still running.
+    pub fn id(&self) -> Option<u32> {
+        match &self.handle {
+            Handle::Process(child) => child.id(),
+            #[cfg(test)]
+            Handle::Replayed(_) => None,
+        }
+    }
+
+    /// Waits for the command to exit and returns its exit code (`None` if it
+    /// was terminated by a signal).
+    pub async fn wait(&mut self) -> std::io::Result<Option<i32>> {
+        match &mut self.handle {
+            Handle::Process(child) => Ok(child.wait().await?.code()),
+            #[cfg(test)]
+            Handle::Replayed(code) => Ok(*code),
+        }
+    }
+}
+
+pub trait CommandRunner: Send + Sync {
+    /// Runs the command to completion and captures its output.
+    fn output(&self, spec: &CommandSpec) -> Result<CommandOutput>;
+
+    /// Starts the command with piped stdout/stderr.
+    fn spawn(&self, spec: &CommandSpec) -> Result<RunningCommand>;
+}
+
+/// Sends SIGINT to `pid` with kill(1). apt-get handles it like Ctrl-C:
+/// downloads are aborted and it exits without touching dpkg.
+pub fn interrupt(runner: &dyn CommandRunner, pid: u32) -> Result<()> {
+    let output = runner.output(&CommandSpec::new("kill").args(["-INT", &pid.to_string()]))?;
+    if !output.success() {
+        anyhow::bail!("kill -INT {} failed: {}", pid, output.stderr.trim());
+    }
+    Ok(())
+}
+
+/// Runs commands on the host system.
+pub struct SystemRunner;
+
+impl CommandRunner for SystemRunner {
+    fn output(&self, spec: &CommandSpec) -> Result<CommandOutput> {
+        use std::io::Write;
+
+        let mut command = std::process::Command::new(&spec.program);
+        command
+            .args(&spec.args)
+            .envs(spec.env.iter().map(|(k, v)| (k, v)))
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .stdin(if spec.stdin.is_some() { Stdio::piped() } else { Stdio::null() });
+        let mut child = command
+            .spawn()
+            .with_context(|| format!("Failed to run `{}`", spec.argv().join(" ")))?;
+        if let Some(input) = &spec.stdin
+            && let Some(mut stdin) = child.stdin.take()
+        {
+            // Um comando que não lê o stdin fecha o pipe: não é erro
+            let _ = stdin.write_all(input.as_bytes());
+        }
+        let output = child.wait_with_output()?;
+
+        Ok(CommandOutput {
+            code: output.status.code(),
+            stdout: String::from_utf8_lossy(&output.stdout).into_owned(),
+            stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+        })
+    }
+
+    fn spawn(&self, spec: &CommandSpec) -> Result<RunningCommand> {
+        let mut command = tokio::process::Command::new(&spec.program);
+        command
+            .args(&spec.args)
+            .envs(spec.env.iter().map(|(k, v)| (k, v)))
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        if spec.stdin.is_some() {
+            command.stdin(Stdio::piped());
+        }
+        let mut child = command
+            .spawn()
+            .with_context(|| format!("Failed to run `{}`", spec.argv().join(" ")))?;
+        if let Some(input) = spec.stdin.clone()
+            && let Some(mut stdin) = child.stdin.take()
+        {
+            tokio::spawn(async move {
+                use tokio::io::AsyncWriteExt;
+                let _ = stdin.write_all(input.as_bytes()).await;
+            });
+        }
+
+        let stdout = child.stdout.take().map(|s| Box::new(s) as OutputStream);
+        let stderr = child.stderr.take().map(|s| Box::new(s) as OutputStream);
+
+        Ok(RunningCommand {
+            stdout,
+            stderr,
+            handle: Handle::Process(child),
+        })
+    }
+}
+
+/// Test double that records every invocation and replays canned output.
+///
+/// Responses are matched against the start of the invocation's argv, first
+/// registered wins; unmatched commands fail like a missing binary would.
+#[cfg(test)]
+#[derive(Default)]
+pub struct ReplayRunner {
+    responses: Vec<(Vec<String>, CommandOutput)>,
+    calls: std::sync::Mutex<Vec<CommandSpec>>,
+}
+
+#[cfg(test)]
+impl ReplayRunner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn respond(mut self, argv_prefix: &[&str], output: CommandOutput) -> Self {
+        let prefix = argv_prefix.iter().map(|s| s.to_string()).collect();
+        self.responses.push((prefix, output));
+        self
+    }
+
+    pub fn calls(&self) -> Vec<CommandSpec> {
+        self.calls.lock().unwrap().clone()
+    }
+
+    fn replay(&self, spec: &CommandSpec) -> Result<CommandOutput> {
+        self.calls.lock().unwrap().push(spec.clone());
+        let argv = spec.argv();
+        self.responses
+            .iter()
+            .find(|(prefix, _)| argv.starts_with(&prefix.iter().map(String::as_str).collect::<Vec<_>>()))
+            .map(|(_, output)| output.clone())
+            .ok_or_else(|| anyhow::anyhow!("No canned response for `{}`", argv.join(" ")))
+    }
+}
+
+#[cfg(test)]
+impl CommandRunner for ReplayRunner {
+    fn output(&self, spec: &CommandSpec) -> Result<CommandOutput> {
+        self.replay(spec)
+    }
+
+    fn spawn(&self, spec: &CommandSpec) -> Result<RunningCommand> {
+        let output = self.replay(spec)?;
+        Ok(RunningCommand {
+            stdout: Some(Box::new(std::io::Cursor::new(output.stdout.into_bytes()))),
+            stderr: Some(Box::new(std::io::Cursor::new(output.stderr.into_bytes()))),
+            handle: Handle::Replayed(output.code),
+        })
+    }
+}
@@ -0,0 +1,188 @@
+use crate::backend::BackendCommand;
+use serde::{Deserialize, Serialize};
+
+pub type JobId = u64;
+
+/// How many finished jobs stay listed after they are done.
+pub const FINISHED_HISTORY: usize = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum JobState {
+    Pending,
+    Running,
+    Done,
+    Failed,
+    Cancelled,
+}
+
+impl JobState {
+    pub fn is_finished(self) -> bool {
+        matches!(self, Self::Done | Self::Failed | Self::Cancelled)
+    }
+}
+
+/// A mutating command waiting for, holding, or done with the dpkg lock.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Job {
+    pub id: JobId,
+    pub command: BackendCommand,
+    pub state: JobState,
+}
+
+impl Job {
+    /// Short description for the queue panel: "Install vim".
+    pub fn label(&self) -> String {
+        self.command.label()
+    }
+}
+
+/// FIFO of mutating jobs, run one at a time so two `apt-get`s never fight
+/// over the dpkg lock. Finished jobs are kept for a while so the UI can
+/// show how they ended.
+#[derive(Debug, Default)]
+pub struct JobQueue {
+    jobs: Vec<Job>,
+    next_id: JobId,
+}
+
+impl JobQueue {
+    pub fn push(&mut self, command: BackendCommand) -> JobId {
+        self.next_id += 1;
+        self.jobs.push(Job {
+            id: self.next_id,
+            command,
+            state: JobState::Pending,
+        });
+        self.next_id
+    }
+
+    pub fn jobs(&self) -> &[Job] {
+        &self.jobs
+    }
+
+    pub fn running(&self) -> Option<&Job> {
+        self.jobs.iter().find(|j| j.state == JobState::Running)
+    }
+
+    /// Marks the first pending job as running and returns it, unless a job
+    /// is already running.
+    pub fn start_next(&mut self) -> Option<Job> {
+        if self.running().is_some() {
+            return None;
+        }
+        let job = self.jobs.iter_mut().find(|j| j.state == JobState::Pending)?;
+        job.state = JobState::Running;
+        Some(job.clone())
+    }
+
+    pub fn finish(&mut self, id: JobId, state: JobState) {
+        if let Some(job) = self.jobs.iter_mut().find(|j| j.id == id) {
+            job.state = state;
+        }
+        // Só o histórico mais recente fica na lista
+        let finished = self.jobs.iter().filter(|j| j.state.is_finished()).count();
+        let mut excess = finished.saturating_sub(FINISHED_HISTORY);
+        self.jobs.retain(|j| {
+            let drop = excess > 0 && j.state.is_finished();
+            if drop {
+                excess -= 1;
+            }
+            !drop
+        });
+    }
+
+    /// Swaps a pending job with the pending job before (`up`) or after it.
+    /// Returns false when there is nothing to swap with.
+    pub fn move_job(&mut self, id: JobId, up: bool) -> bool {
+        let pending: Vec<usize> = self
+            .jobs
+            .iter()
+            .enumerate()
+            .filter(|(_, j)| j.state == JobState::Pending)
+            .map(|(i, _)| i)
+            .collect();
+        let Some(pos) = pending.iter().position(|&i| self.jobs[i].id == id) else {
+            return false;
+        };
+        let other = if up { pos.checked_sub(1) } else { Some(pos + 1) };
+        match other.and_then(|o| pending.get(o)) {
+            Some(&other) => {
+                self.jobs.swap(pending[pos], other);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Drops a job that has not started yet.
+    pub fn drop_job(&mut self, id: JobId) -> bool {
+        let before = self.jobs.len();
+        self.jobs.retain(|j| !(j.id == id && j.state == JobState::Pending));
+        self.jobs.len() != before
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn states(queue: &JobQueue) -> Vec<(JobId, JobState)> {
+        queue.jobs().iter().map(|j| (j.id, j.state)).collect()
+    }
+
+    #[test]
+    fn test_jobs_run_one_at_a_time() {
+        let mut queue = JobQueue::default();
+        let first = queue.push(BackendCommand::UpgradeSystem);
+        let second = queue.push(BackendCommand::Autoremove);
+
+        assert_eq!(queue.start_next().map(|j| j.id), Some(first));
+        assert_eq!(queue.start_next(), None);
+
+        queue.finish(first, JobState::Failed);
+        assert_eq!(queue.start_next().map(|j| j.id), Some(second));
+        queue.finish(second, JobState::Done);
+        assert_eq!(
+            states(&queue),
+            vec![(first, JobState::Failed), (second, JobState::Done)]
+        );
+        assert_eq!(queue.start_next(), None);
+    }
+
+    #[test]
+    fn test_reorder_and_drop_pending_jobs() {
+        let mut queue = JobQueue::default();
+        let running = queue.push(BackendCommand::RefreshRepos);
+        let a = queue.push(BackendCommand::UpgradeSystem);
+        let b = queue.push(BackendCommand::Autoremove);
+        queue.start_next();
+
+        // The running job stays put and cannot be dropped
+        assert!(!queue.move_job(running, false));
+        assert!(!queue.move_job(a, true));
+        assert!(!queue.drop_job(running));
+
+        assert!(queue.move_job(b, true));
+        assert_eq!(queue.jobs()[1].id, b);
+        assert!(!queue.move_job(a, false));
+
+        assert!(queue.drop_job(b));
+        queue.finish(running, JobState::Cancelled);
+        assert_eq!(queue.start_next().map(|j| j.id), Some(a));
+    }
+
+    #[test]
+    fn test_finished_history_is_bounded() {
+        let mut queue = JobQueue::default();
+        for _ in 0..FINISHED_HISTORY + 3 {
+            queue.push(BackendCommand::Autoremove);
+            let job = queue.start_next().unwrap();
+            queue.finish(job.id, JobState::Done);
+        }
+        let pending = queue.push(BackendCommand::UpgradeSystem);
+
+        assert_eq!(queue.jobs().len(), FINISHED_HISTORY + 1);
+        assert_eq!(queue.jobs()[0].id, 4);
+        assert_eq!(queue.jobs().last().map(|j| j.id), Some(pending));
+    }
+}
@@ -0,0 +1,202 @@
+use crate::pkg::Package;
+use crate::version::PackageVersion;
+use serde::{Deserialize, Serialize};
+
+/// One package touched by a simulated transaction.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SimulatedChange {
+    pub name: String,
+    pub arch: String,
+    pub from: Option<PackageVersion>, // Versão instalada hoje
+    pub to: Option<PackageVersion>,   // Versão depois da transação
+}
+
+/// What `apt-get -s` says a transaction would do, grouped the way the
+/// confirmation popup shows it.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Simulation {
+    pub install: Vec<SimulatedChange>,
+    pub upgrade: Vec<SimulatedChange>,
+    pub downgrade: Vec<SimulatedChange>,
+    pub reinstall: Vec<SimulatedChange>,
+    pub remove: Vec<SimulatedChange>,
+    pub download_size: u64,
+    pub disk_delta: i64, // Bytes; negativo quando libera espaço
+}
+
+impl Simulation {
+    pub fn is_empty(&self) -> bool {
+        self.install.is_empty()
+            && self.upgrade.is_empty()
+            && self.downgrade.is_empty()
+            && self.reinstall.is_empty()
+            && self.remove.is_empty()
+    }
+
+    /// Estimates download and disk usage from the package index (candidate
+    /// `Size`/`Installed-Size`) and the installed packages. `apt-get -s`
+    /// prints no sizes itself.
+    pub fn estimate_sizes<'a>(
+        &mut self,
+        lookup_available: impl Fn(&str, &str) -> Option<&'a Package>,
+        installed: &[Package],
+    ) {
+        let installed_size = |change: &SimulatedChange| -> i64 {
+            installed
+                .iter()
+                .find(|p| p.id.name == change.name && (change.arch.is_empty() || p.id.arch == change.arch))
+                .map_or(0, |p| p.size as i64)
+        };
+
+        self.download_size = 0;
+        self.disk_delta = 0;
+        let incoming = self
+            .install
+            .iter()
+            .chain(&self.upgrade)
+            .chain(&self.downgrade)
+            .chain(&self.reinstall);
+        for change in incoming {
+            if let Some(pkg) = lookup_available(&change.name, &change.arch) {
+                self.download_size += pkg.download_size;
+                self.disk_delta += pkg.size as i64;
+            }
+            self.disk_delta -= installed_size(change);
+        }
+        for change in &self.remove {
+            self.disk_delta -= installed_size(change);
+        }
+    }
+}
+
+/// Parses the `Inst`/`Remv`/`Purg` lines of `apt-get -s` output:
+///
+/// ```text
+/// Inst vim [2:9.0-1] (2:9.0-2 Debian:12/stable [amd64])
+/// Inst libgc1 (1:8.2.2-3 Debian:12/stable [amd64]) []
+/// Remv nano [7.2-1]
+/// ```
+///
+/// `Conf` lines repeat the `Inst` ones and are skipped.
+pub fn parse(output: &str) -> Simulation {
+    let mut simulation = Simulation::default();
+
+    for line in output.lines() {
+        let Some((verb, rest)) = line.split_once(' ') else {
+            continue;
+        };
+        if !matches!(verb, "Inst" | "Remv" | "Purg") {
+            continue;
+        }
+
+        let (name, mut rest) = rest.split_once(' ').unwrap_or((rest, ""));
+        let (name, mut arch) = match name.split_once(':') {
+            Some((name, arch)) => (name.to_string(), arch.to_string()),
+            None => (name.to_string(), String::new()),
+        };
+
+        // "[old version]" is only there when the package is installed
+        let mut from = None;
+        if let Some(stripped) = rest.strip_prefix('[')
+            && let Some((version, after)) = stripped.split_once(']')
+        {
+            from = Some(PackageVersion::parse(version));
+            rest = after.trim_start();
+        }
+
+        // "(new version origins... [arch])"
+        let mut to = None;
+        if let Some(stripped) = rest.strip_prefix('(')
+            && let Some((inner, _)) = stripped.split_once(')')
+        {
+            to = inner.split_whitespace().next().map(PackageVersion::parse);
+            if arch.is_empty()
+                && let Some((_, tail)) = inner.rsplit_once('[')
+            {
+                arch = tail.trim_end_matches(']').to_string();
+            }
+        }
+
+        let change = SimulatedChange { name, arch, from, to };
+        let group = match (verb, &change.from, &change.to) {
+            ("Remv" | "Purg", _, _) => &mut simulation.remove,
+            (_, None, _) => &mut simulation.install,
+            (_, Some(from), Some(to)) if to > from => &mut simulation.upgrade,
+            (_, Some(from), Some(to)) if to < from => &mut simulation.downgrade,
+            _ => &mut simulation.reinstall,
+        };
+        group.push(change);
+    }
+
+    simulation
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pkg::{PackageId, PackageState};
+
+    const OUTPUT: &str = "NOTE: This is only a simulation!
+      apt-get needs root privileges for real execution.
+Reading package lists...
+The following packages will be REMOVED:
+  nano
+0 upgraded, 1 newly installed, 1 to remove and 97 not upgraded.
+Inst base-files [12.4+deb12u11] (12.4+deb12u14 Debian:12.14/oldstable [amd64])
+Conf base-files (12.4+deb12u14 Debian:12.14/oldstable [amd64])
+Inst emacs-el (1:28.2+1-15+deb12u4 Debian:12.14/oldstable, Debian-Security:12/oldstable-security [all]) []
+Inst adduser [3.134] (3.134 Debian:12.14/oldstable [all])
+Inst libc6:i386 [2.36-9+deb12u10] (2.36-9+deb12u4 Debian:12/stable [i386])
+Remv nano [7.2-1]
+Purg oldpkg:amd64 [1.0]
+";
+
+    #[test]
+    fn test_parse_simulation() {
+        let sim = parse(OUTPUT);
+
+        assert_eq!(sim.upgrade.len(), 1);
+        assert_eq!(sim.upgrade[0].name, "base-files");
+        assert_eq!(sim.upgrade[0].arch, "amd64");
+        assert_eq!(sim.upgrade[0].from, Some("12.4+deb12u11".into()));
+        assert_eq!(sim.upgrade[0].to, Some("12.4+deb12u14".into()));
+
+        assert_eq!(sim.install.len(), 1);
+        assert_eq!(sim.install[0].name, "emacs-el");
+        assert_eq!(sim.install[0].arch, "all");
+        assert_eq!(sim.install[0].from, None);
+
+        assert_eq!(sim.reinstall[0].name, "adduser");
+        assert_eq!(sim.downgrade[0].name, "libc6");
+        assert_eq!(sim.downgrade[0].arch, "i386");
+
+        let removed: Vec<&str> = sim.remove.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(removed, vec!["nano", "oldpkg"]);
+        assert_eq!(sim.remove[1].arch, "amd64");
+        assert!(!sim.is_empty());
+        assert!(parse("0 upgraded, 0 newly installed").is_empty());
+    }
+
+    #[test]
+    fn test_estimate_sizes() {
+        let mut sim = parse(
+            "Inst vim [1.0] (2.0 Debian:12/stable [amd64])\nInst vim-runtime (2.0 Debian:12/stable [all])\nRemv nano [7.2-1]\n",
+        );
+
+        let package = |name: &str, arch: &str, size: u64, download: u64| {
+            let mut pkg = Package::new(PackageId::new(name, "2.0", arch, ""), PackageState::Available.into(), "");
+            pkg.size = size;
+            pkg.download_size = download;
+            pkg
+        };
+        let available = [package("vim", "amd64", 4000, 1500), package("vim-runtime", "all", 30000, 7000)];
+        let installed = [package("vim", "amd64", 3500, 0), package("nano", "amd64", 2000, 0)];
+
+        sim.estimate_sizes(
+            |name, arch| available.iter().find(|p| p.id.name == name && p.id.arch == arch),
+            &installed,
+        );
+        assert_eq!(sim.download_size, 8500);
+        assert_eq!(sim.disk_delta, 4000 + 30000 - 3500 - 2000);
+    }
+}
@@ -20,7 +20,15 @@ mod tests {
         let tab = SelectedTab::Upgradable.next();
         assert_eq!(tab, SelectedTab::Online);
 
-        // Verify next tab wrap around (Online -> Installed)
+        // Verify next tab (Online -> Orphans)
+        let tab = tab.next();
+        assert_eq!(tab, SelectedTab::Orphans);
+
+        // Verify next tab (Orphans -> Held)
+        let tab = tab.next();
+        assert_eq!(tab, SelectedTab::Held);
+
+        // Verify next tab wrap around (Held -> Installed)
         let tab = tab.next();
         assert_eq!(tab, SelectedTab::Installed);
 
@@ -30,7 +38,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_online_packages_persistence() {
-        use crate::pkg::Package;
+        use crate::pkg::{Package, PackageState};
         use crate::backend::BackendEvent;
         let (tx, _) = mpsc::unbounded_channel::<BackendCommand>();
         let mut app = App::new(tx);
@@ -38,12 +46,12 @@ mod tests {
         assert!(app.online_packages.is_empty());
         
         let pkgs = vec![
-            Package::from_packagekit("pkg;1.0;all;apt", "Available", "Summary")
+            Package::from_packagekit("pkg;1.0;all;apt", PackageState::Available, "Summary")
         ];
-        app.update(crate::action::Action::BackendResponse(BackendEvent::SearchResultsFound(pkgs.clone()))).unwrap();
+        app.update(crate::action::Action::BackendResponse(BackendEvent::SearchResultsFound("pkg".to_string(), pkgs.clone()))).unwrap();
 
         assert_eq!(app.online_packages.len(), 1);
-        assert_eq!(app.online_packages[0].name, "pkg");
+        assert_eq!(app.online_packages[0].id.name, "pkg");
 
         // Verify it persists after a "tick" or tab switch (simulated)
         app.selected_tab = SelectedTab::Installed;
@@ -60,7 +68,7 @@ mod tests {
         app.search_query = "vim".to_string();
 
         // Drain initial commands (ListInstalled, ListUpgradable)
-        while let Ok(_) = rx.try_recv() {}
+        while rx.try_recv().is_ok() {}
 
         app.update(Action::TriggerOnlineSearch).unwrap();
 
@@ -72,4 +80,707 @@ mod tests {
             panic!("Expected Search command, got {:?}", cmd);
         }
     }
+
+    #[tokio::test]
+    async fn test_details_not_requested_for_dpkg_packages() {
+        use crate::action::Action;
+        use crate::backend::BackendEvent;
+        use crate::pkg::{Package, PackageState};
+        let (tx, mut rx) = mpsc::unbounded_channel::<BackendCommand>();
+        let mut app = App::new(tx);
+        while rx.try_recv().is_ok() {}
+
+        let mut known = Package::from_packagekit("vim;9.1;amd64;apt", PackageState::Installed, "Vi IMproved");
+        known.description = "Read from /var/lib/dpkg/status".to_string();
+        let unknown = Package::from_packagekit("nano;8.0;amd64;apt", PackageState::Installed, "");
+        app.update(Action::BackendResponse(BackendEvent::InstalledPackagesFound(vec![known, unknown]))).unwrap();
+
+        // Selecting the first package (with description) must not hit the backend
+        assert!(rx.try_recv().is_err());
+
+        app.update(Action::SelectNext).unwrap();
+        match rx.try_recv() {
+            Ok(BackendCommand::GetDetails(id)) => assert_eq!(id.name, "nano"),
+            other => panic!("Expected GetDetails for nano, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_online_search_as_you_type() {
+        use crate::action::Action;
+        use crate::backend::BackendEvent;
+        use crate::pkg::{Package, PackageState};
+        let (tx, mut rx) = mpsc::unbounded_channel::<BackendCommand>();
+        let mut app = App::new(tx);
+        app.selected_tab = SelectedTab::Online;
+        while rx.try_recv().is_ok() {}
+        app.is_loading = false; // Initial listing done
+
+        app.update(Action::EnterSearchMode).unwrap();
+        app.update(Action::UpdateSearchQuery('v')).unwrap();
+        // Single characters are too broad to query
+        assert!(rx.try_recv().is_err());
+
+        app.update(Action::UpdateSearchQuery('i')).unwrap();
+        assert_eq!(rx.try_recv().unwrap(), BackendCommand::Search("vi".to_string()));
+        app.update(Action::UpdateSearchQuery('m')).unwrap();
+        assert_eq!(rx.try_recv().unwrap(), BackendCommand::Search("vim".to_string()));
+        assert!(!app.is_loading, "typing must not be blocked by the loading overlay");
+
+        // Results for "vi" arriving late are stale and must be dropped
+        let stale = vec![Package::from_packagekit("vifm;1.0;amd64;apt", PackageState::Available, "")];
+        app.update(Action::BackendResponse(BackendEvent::SearchResultsFound("vi".to_string(), stale))).unwrap();
+        assert!(app.online_packages.is_empty());
+
+        // Description matches are kept even though the name doesn't fuzzy-match
+        let fresh = vec![
+            Package::from_packagekit("vim;9.1;amd64;apt", PackageState::Available, ""),
+            Package::from_packagekit("nvi;1.81;amd64;apt", PackageState::Available, "4.4BSD re-implementation of vi"),
+        ];
+        app.update(Action::BackendResponse(BackendEvent::SearchResultsFound("vim".to_string(), fresh))).unwrap();
+        assert_eq!(app.filtered_packages.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_online_results_compared_to_installed() {
+        use crate::action::Action;
+        use crate::backend::BackendEvent;
+        use crate::pkg::{Package, PackageState};
+        let (tx, _) = mpsc::unbounded_channel::<BackendCommand>();
+        let mut app = App::new(tx);
+
+        let installed = vec![
+            Package::from_packagekit("vim;2:9.0.1378-2;amd64;apt", PackageState::Installed, ""),
+            Package::from_packagekit("nano;7.2-1;amd64;apt", PackageState::Installed, ""),
+        ];
+        app.update(Action::BackendResponse(BackendEvent::InstalledPackagesFound(installed))).unwrap();
+
+        let online = vec![
+            Package::from_packagekit("vim;2:9.0.1378-2+deb12u1;amd64;apt", PackageState::Available, ""),
+            Package::from_packagekit("nano;7.2-1;amd64;apt", PackageState::Available, ""),
+            Package::from_packagekit("emacs;1:28.2+1-15;all;apt", PackageState::Available, ""),
+        ];
+        app.update(Action::BackendResponse(BackendEvent::SearchResultsFound("e".to_string(), online))).unwrap();
+
+        assert_eq!(app.online_packages[0].status.upgradable_from, Some("2:9.0.1378-2".into()));
+        assert_eq!(app.online_packages[1].status, PackageState::Installed.into());
+        assert_eq!(app.online_packages[2].status, PackageState::Available.into());
+    }
+
+    #[tokio::test]
+    async fn test_install_previews_before_confirming() {
+        use crate::action::Action;
+        use crate::backend::BackendEvent;
+        use crate::pkg::{Package, PackageState};
+        use crate::simulation;
+        let (tx, mut rx) = mpsc::unbounded_channel::<BackendCommand>();
+        let mut app = App::new(tx);
+        while rx.try_recv().is_ok() {}
+
+        let pkg = Package::from_packagekit("emacs;1:28.2;amd64;bookworm", PackageState::Available, "");
+        let install = BackendCommand::Install(pkg.id.clone());
+        app.update(Action::BackendResponse(BackendEvent::InstalledPackagesFound(vec![pkg]))).unwrap();
+        while rx.try_recv().is_ok() {}
+
+        // Asking to install only runs the simulation, nothing is confirmed yet
+        app.update(Action::RequestInstall).unwrap();
+        assert_eq!(rx.try_recv(), Ok(BackendCommand::Simulate(Box::new(install.clone()))));
+        assert!(!app.popup.visible);
+
+        let sim = simulation::parse("Inst emacs (1:28.2 Debian:12/stable [amd64])\nInst emacs-common (1:28.2 Debian:12/stable [all])\n");
+        app.update(Action::BackendResponse(BackendEvent::SimulationFound(
+            Box::new(install.clone()),
+            Box::new(sim),
+        )))
+        .unwrap();
+        assert!(app.popup.visible);
+        assert_eq!(app.popup.title, "Confirm Installation");
+        assert_eq!(app.popup.simulation.as_ref().map(|s| s.install.len()), Some(2));
+
+        app.update(Action::ScrollPopupDown).unwrap();
+        app.update(Action::ScrollPopupDown).unwrap();
+        app.update(Action::ScrollPopupUp).unwrap();
+        assert_eq!(app.popup.scroll, 1);
+
+        app.update(Action::ConfirmAction).unwrap();
+        assert_eq!(rx.try_recv(), Ok(install));
+        assert!(!app.popup.visible);
+        assert!(app.popup.simulation.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_dependency_explorer() {
+        use crate::action::Action;
+        use crate::app::{DetailsView, Panel};
+        use crate::backend::BackendEvent;
+        use crate::deps::{parse_field, RelationKind};
+        use crate::pkg::{Package, PackageState};
+        let (tx, mut rx) = mpsc::unbounded_channel::<BackendCommand>();
+        let mut app = App::new(tx);
+
+        let mut exim = Package::from_packagekit("exim4-daemon-light;4.96;amd64;installed", PackageState::Installed, "");
+        exim.relations = parse_field(RelationKind::Provides, "mail-transport-agent");
+        let mut mutt = Package::from_packagekit("mutt;2.2;amd64;installed", PackageState::Installed, "");
+        mutt.description = "Text-based mailreader".to_string();
+        mutt.relations = parse_field(RelationKind::Depends, "libc6 (>= 2.34), default-mta | mail-transport-agent");
+        mutt.relations.extend(parse_field(RelationKind::Suggests, "urlview"));
+        app.update(Action::BackendResponse(BackendEvent::InstalledPackagesFound(vec![exim, mutt]))).unwrap();
+
+        app.update(Action::SelectNext).unwrap();
+        app.update(Action::ToggleDependencies).unwrap();
+        app.update(Action::ToggleFocus).unwrap();
+        assert_eq!(app.details_view, DetailsView::Dependencies);
+        assert!(app.active_panel == Panel::Details);
+
+        let entries = app.dependency_entries();
+        let rows: Vec<(String, bool)> = entries
+            .iter()
+            .map(|e| (e.relation.to_string(), e.installed))
+            .collect();
+        assert_eq!(
+            rows,
+            vec![
+                ("libc6 (>= 2.34)".to_string(), false),
+                ("default-mta | mail-transport-agent".to_string(), true),
+                ("urlview".to_string(), false),
+            ]
+        );
+
+        // j/k now move inside the dependency list, not the package list
+        app.update(Action::SelectNext).unwrap();
+        assert_eq!(app.dependency_state.selected(), Some(1));
+        assert_eq!(app.list_state.selected(), Some(1));
+
+        // The virtual package resolves to the installed provider
+        app.update(Action::OpenDependency).unwrap();
+        assert_eq!(app.selected_tab, SelectedTab::Installed);
+        assert_eq!(app.list_state.selected(), Some(0));
+
+        // exim4 is what mutt's mail-transport-agent dependency pulls in
+        let reverse: Vec<String> = app
+            .dependency_entries()
+            .into_iter()
+            .filter(|e| e.relation.kind == RelationKind::ReverseDepends)
+            .map(|e| e.relation.to_string())
+            .collect();
+        assert_eq!(reverse, vec!["mutt"]);
+
+        // Packages that are not installed are looked up in the Online tab
+        app.update(Action::ToggleFocus).unwrap();
+        app.update(Action::SelectNext).unwrap();
+        app.update(Action::ToggleFocus).unwrap();
+        app.update(Action::SelectNext).unwrap();
+        app.update(Action::SelectNext).unwrap();
+        while rx.try_recv().is_ok() {}
+        app.update(Action::OpenDependency).unwrap();
+        assert_eq!(app.selected_tab, SelectedTab::Online);
+        assert_eq!(rx.try_recv(), Ok(BackendCommand::Search("urlview".to_string())));
+    }
+
+    #[tokio::test]
+    async fn test_reverse_recommends_keep_their_kind() {
+        use crate::action::Action;
+        use crate::backend::BackendEvent;
+        use crate::deps::{parse_field, RelationKind};
+        use crate::pkg::{Package, PackageState};
+        let (tx, _rx) = mpsc::unbounded_channel::<BackendCommand>();
+        let mut app = App::new(tx);
+
+        let mut exim = Package::from_packagekit("exim4-daemon-light;4.96;amd64;installed", PackageState::Installed, "");
+        exim.relations = parse_field(RelationKind::Provides, "mail-transport-agent");
+        let mut mailx = Package::from_packagekit("bsd-mailx;8.1;amd64;installed", PackageState::Installed, "");
+        mailx.relations = parse_field(RelationKind::Recommends, "default-mta | mail-transport-agent");
+        let mut mutt = Package::from_packagekit("mutt;2.2;amd64;installed", PackageState::Installed, "");
+        mutt.relations = parse_field(RelationKind::Depends, "mail-transport-agent");
+        mutt.relations.extend(parse_field(RelationKind::Recommends, "exim4-daemon-light"));
+        app.update(Action::BackendResponse(BackendEvent::InstalledPackagesFound(vec![exim, mailx, mutt]))).unwrap();
+
+        let reverse: Vec<(RelationKind, String)> = app
+            .dependency_entries()
+            .into_iter()
+            .filter(|e| matches!(e.relation.kind, RelationKind::ReverseDepends | RelationKind::ReverseRecommends))
+            .map(|e| (e.relation.kind, e.relation.to_string()))
+            .collect();
+        assert_eq!(
+            reverse,
+            vec![
+                (RelationKind::ReverseRecommends, "bsd-mailx".to_string()),
+                (RelationKind::ReverseDepends, "mutt".to_string()),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_why_installed_only_on_installed_tab() {
+        use crate::action::Action;
+        use crate::backend::BackendEvent;
+        use crate::deps::WhyInstalled;
+        use crate::pkg::{Package, PackageState};
+        let (tx, _) = mpsc::unbounded_channel::<BackendCommand>();
+        let mut app = App::new(tx);
+
+        let mut libfoo = Package::from_packagekit("libfoo;1.0;amd64;installed", PackageState::Installed, "");
+        libfoo.status.automatic = true;
+        app.update(Action::BackendResponse(BackendEvent::InstalledPackagesFound(vec![libfoo.clone()]))).unwrap();
+        assert_eq!(app.why_installed(), Some(WhyInstalled::Orphan));
+
+        app.update(Action::BackendResponse(BackendEvent::SearchResultsFound("libfoo".to_string(), vec![libfoo]))).unwrap();
+        app.update(Action::SwitchTabNext).unwrap();
+        app.update(Action::SwitchTabNext).unwrap();
+        assert_eq!(app.selected_tab, SelectedTab::Online);
+        assert_eq!(app.why_installed(), None);
+    }
+
+    #[tokio::test]
+    async fn test_orphans_tab_and_autoremove() {
+        use crate::action::Action;
+        use crate::backend::BackendEvent;
+        use crate::pkg::{Package, PackageState};
+        let (tx, mut rx) = mpsc::unbounded_channel::<BackendCommand>();
+        let mut app = App::new(tx);
+
+        let startup: Vec<BackendCommand> = std::iter::from_fn(|| rx.try_recv().ok()).collect();
+        assert!(startup.contains(&BackendCommand::ListOrphans));
+
+        let orphan = Package::from_packagekit("libfoo1;1.0;amd64;installed", PackageState::Installed, "");
+        app.update(Action::BackendResponse(BackendEvent::OrphanPackagesFound(vec![orphan]))).unwrap();
+        app.selected_tab = SelectedTab::Online;
+        app.update(Action::SwitchTabNext).unwrap();
+        assert_eq!(app.selected_tab, SelectedTab::Orphans);
+        assert_eq!(app.filtered_packages[0].id.name, "libfoo1");
+
+        app.update(Action::RequestAutoremove).unwrap();
+        let sent: Vec<BackendCommand> = std::iter::from_fn(|| rx.try_recv().ok()).collect();
+        assert!(sent.contains(&BackendCommand::Simulate(Box::new(BackendCommand::Autoremove))));
+
+        // Once autoremove finishes every list is refreshed, orphans included
+        app.update(Action::BackendResponse(BackendEvent::TaskFinished(BackendCommand::Autoremove))).unwrap();
+        let sent: Vec<BackendCommand> = std::iter::from_fn(|| rx.try_recv().ok()).collect();
+        assert_eq!(
+            sent,
+            vec![BackendCommand::ListInstalled, BackendCommand::ListUpgradable, BackendCommand::ListOrphans]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_mark_auto_and_manual() {
+        use crate::action::Action;
+        use crate::backend::BackendEvent;
+        use crate::pkg::{Package, PackageMark, PackageState};
+        let (tx, mut rx) = mpsc::unbounded_channel::<BackendCommand>();
+        let mut app = App::new(tx);
+        while rx.try_recv().is_ok() {}
+
+        let vim = Package::from_packagekit("vim;2:9.1;amd64;installed", PackageState::Installed, "");
+        app.update(Action::BackendResponse(BackendEvent::InstalledPackagesFound(vec![vim]))).unwrap();
+        app.selected_tab = SelectedTab::Installed;
+        app.update(Action::SelectNext).unwrap();
+        while rx.try_recv().is_ok() {}
+
+        app.update(Action::MarkAuto).unwrap();
+        let id = app.installed_packages[0].id.clone();
+        assert_eq!(rx.try_recv().unwrap(), BackendCommand::Mark(id.clone(), PackageMark::Auto));
+
+        // The flag changes everywhere as soon as apt-mark succeeds, and the
+        // autoremove set is recomputed
+        app.update(Action::BackendResponse(BackendEvent::PackageMarked(id.clone(), PackageMark::Auto))).unwrap();
+        assert!(app.installed_packages[0].status.automatic);
+        assert!(app.filtered_packages[0].status.automatic);
+        assert_eq!(rx.try_recv().unwrap(), BackendCommand::ListOrphans);
+
+        app.update(Action::BackendResponse(BackendEvent::PackageMarked(id, PackageMark::Manual))).unwrap();
+        assert!(!app.installed_packages[0].status.automatic);
+
+        // Packages that are not installed cannot be marked
+        app.selected_tab = SelectedTab::Online;
+        let online = Package::from_packagekit("emacs;1:29;amd64;stable", PackageState::Available, "");
+        app.update(Action::BackendResponse(BackendEvent::SearchResultsFound("emacs".to_string(), vec![online]))).unwrap();
+        app.update(Action::SelectNext).unwrap();
+        while rx.try_recv().is_ok() {}
+        app.update(Action::MarkManual).unwrap();
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_held_tab_and_upgradable_held_back() {
+        use crate::action::Action;
+        use crate::backend::BackendEvent;
+        use crate::pkg::{Package, PackageMark, PackageState, PackageStatus};
+        let (tx, mut rx) = mpsc::unbounded_channel::<BackendCommand>();
+        let mut app = App::new(tx);
+
+        let mut linux = Package::from_packagekit("linux-image;6.1;amd64;installed", PackageState::Installed, "");
+        linux.status.held = true;
+        let vim = Package::from_packagekit("vim;2:9.0;amd64;installed", PackageState::Installed, "");
+        app.update(Action::BackendResponse(BackendEvent::InstalledPackagesFound(vec![linux, vim]))).unwrap();
+
+        // `apt list --upgradable` knows nothing about holds
+        let upgradable = ["linux-image;6.2;amd64;stable", "vim;2:9.1;amd64;stable"].map(|id| {
+            let mut pkg = Package::from_packagekit(id, PackageState::Installed, "");
+            pkg.status = PackageStatus { upgradable_from: Some("1".into()), ..pkg.status };
+            pkg
+        });
+        app.update(Action::BackendResponse(BackendEvent::UpgradablePackagesFound(upgradable.to_vec()))).unwrap();
+        assert!(app.upgradable_packages[0].status.held);
+        assert!(!app.upgradable_packages[1].status.held);
+
+        app.selected_tab = SelectedTab::Orphans;
+        app.update(Action::SwitchTabNext).unwrap();
+        assert_eq!(app.selected_tab, SelectedTab::Held);
+        assert_eq!(app.filtered_packages.len(), 1);
+        assert_eq!(app.filtered_packages[0].id.name, "linux-image");
+
+        // H on a held package unholds it
+        while rx.try_recv().is_ok() {}
+        app.update(Action::ToggleHold).unwrap();
+        let id = app.filtered_packages[0].id.clone();
+        assert_eq!(rx.try_recv().unwrap(), BackendCommand::Mark(id.clone(), PackageMark::Unhold));
+
+        app.update(Action::BackendResponse(BackendEvent::PackageMarked(id, PackageMark::Unhold))).unwrap();
+        assert!(app.filtered_packages.is_empty());
+        assert!(!app.upgradable_packages[0].status.held);
+
+        // U mentions what is held back
+        app.update(Action::BackendResponse(BackendEvent::PackageMarked(
+            app.installed_packages[1].id.clone(),
+            PackageMark::Hold,
+        )))
+        .unwrap();
+        app.update(Action::BackendResponse(BackendEvent::SimulationFound(
+            Box::new(BackendCommand::UpgradeSystem),
+            Box::default(),
+        )))
+        .unwrap();
+        assert!(app.popup.message.ends_with("Held back: vim"));
+    }
+
+    #[tokio::test]
+    async fn test_multi_select_batches_one_transaction() {
+        use crate::action::Action;
+        use crate::backend::BackendEvent;
+        use crate::pkg::{Package, PackageState};
+        use crate::transaction::{Transaction, TransactionKind};
+        let (tx, mut rx) = mpsc::unbounded_channel::<BackendCommand>();
+        let mut app = App::new(tx);
+
+        let pkgs: Vec<Package> = ["a;1;amd64;installed", "b;1;amd64;installed", "c;1;amd64;installed", "d;1;amd64;installed"]
+            .iter()
+            .map(|id| Package::from_packagekit(id, PackageState::Installed, ""))
+            .collect();
+        app.update(Action::BackendResponse(BackendEvent::InstalledPackagesFound(pkgs))).unwrap();
+        let id = |i: usize| app.installed_packages[i].id.clone();
+        let (a, b, c, d) = (id(0), id(1), id(2), id(3));
+
+        // Space toggles and moves down; toggling again unselects
+        app.update(Action::ToggleSelection).unwrap();
+        assert_eq!(app.selection, vec![a.clone()]);
+        assert_eq!(app.list_state.selected(), Some(1));
+        app.update(Action::SelectPrev).unwrap();
+        app.update(Action::ToggleSelection).unwrap();
+        assert!(app.selection.is_empty());
+
+        // Space on a, then a visual range over b..c
+        app.update(Action::SelectPrev).unwrap();
+        app.update(Action::ToggleSelection).unwrap();
+        app.update(Action::ToggleVisualSelection).unwrap();
+        app.update(Action::SelectNext).unwrap();
+        assert_eq!(app.visual_range(), Some(1..=2));
+        assert!((0..3).all(|i| app.is_selected(i)) && !app.is_selected(3));
+        app.update(Action::ToggleVisualSelection).unwrap();
+        assert_eq!(app.visual_anchor, None);
+        assert_eq!(app.selection, vec![a.clone(), b.clone(), c.clone()]);
+
+        // Purge goes to apt as a single transaction
+        while rx.try_recv().is_ok() {}
+        app.update(Action::RequestPurge).unwrap();
+        let transaction = Transaction::new(TransactionKind::Purge, vec![a, b, c]);
+        assert_eq!(
+            rx.try_recv().unwrap(),
+            BackendCommand::Simulate(Box::new(BackendCommand::Apply(transaction.clone())))
+        );
+        app.update(Action::BackendResponse(BackendEvent::SimulationFound(
+            Box::new(BackendCommand::Apply(transaction.clone())),
+            Box::default(),
+        )))
+        .unwrap();
+        assert_eq!(app.popup.message, "Purge 3 packages: a, b, c?");
+
+        // The selection is spent once the transaction finishes
+        app.update(Action::ConfirmAction).unwrap();
+        assert_eq!(rx.try_recv().unwrap(), BackendCommand::Apply(transaction.clone()));
+        app.update(Action::BackendResponse(BackendEvent::TaskFinished(BackendCommand::Apply(transaction)))).unwrap();
+        assert!(app.selection.is_empty());
+
+        // Without a selection the cursor package is used, as before
+        while rx.try_recv().is_ok() {}
+        app.list_state.select(Some(3));
+        app.update(Action::RequestReinstall).unwrap();
+        assert_eq!(rx.try_recv().unwrap(), BackendCommand::Simulate(Box::new(BackendCommand::Reinstall(d))));
+
+        app.update(Action::ToggleVisualSelection).unwrap();
+        app.update(Action::ClearSelection).unwrap();
+        assert_eq!(app.visual_anchor, None);
+    }
+
+    #[tokio::test]
+    async fn test_job_queue_panel() {
+        use crate::action::Action;
+        use crate::app::DetailsView;
+        use crate::backend::BackendEvent;
+        use crate::scheduler::{Job, JobState};
+        let (tx, mut rx) = mpsc::unbounded_channel::<BackendCommand>();
+        let mut app = App::new(tx);
+        while rx.try_recv().is_ok() {}
+        app.is_loading = false;
+
+        let job = |id, command, state| Job { id, command, state };
+        let jobs = vec![
+            job(1, BackendCommand::UpgradeSystem, JobState::Running),
+            job(2, BackendCommand::Autoremove, JobState::Pending),
+            job(3, BackendCommand::RefreshRepos, JobState::Pending),
+        ];
+        app.update(Action::BackendResponse(BackendEvent::QueueUpdated(jobs.clone()))).unwrap();
+
+        // Queue controls only act while the queue is shown
+        app.update(Action::DropJob).unwrap();
+        assert!(rx.try_recv().is_err());
+
+        app.update(Action::ToggleQueue).unwrap();
+        assert_eq!(app.details_view, DetailsView::Queue);
+        app.update(Action::ToggleFocus).unwrap();
+
+        // The running job cannot be moved
+        app.update(Action::MoveJobDown).unwrap();
+        assert!(rx.try_recv().is_err());
+
+        app.update(Action::SelectNext).unwrap();
+        app.update(Action::SelectNext).unwrap();
+        app.update(Action::MoveJobUp).unwrap();
+        assert_eq!(rx.try_recv().unwrap(), BackendCommand::MoveJobUp(3));
+        assert!(!app.is_loading);
+
+        // The cursor follows the job to its new position
+        let reordered = vec![jobs[0].clone(), jobs[2].clone(), jobs[1].clone()];
+        app.update(Action::BackendResponse(BackendEvent::QueueUpdated(reordered))).unwrap();
+        assert_eq!(app.selected_job().map(|j| j.id), Some(3));
+
+        app.update(Action::DropJob).unwrap();
+        assert_eq!(rx.try_recv().unwrap(), BackendCommand::DropJob(3));
+        app.update(Action::BackendResponse(BackendEvent::QueueUpdated(vec![jobs[0].clone(), jobs[1].clone()])))
+            .unwrap();
+        assert_eq!(app.selected_job().map(|j| j.id), Some(1));
+
+        app.update(Action::ToggleQueue).unwrap();
+        assert_eq!(app.details_view, DetailsView::Info);
+    }
+
+    #[tokio::test]
+    async fn test_job_log_pane() {
+        use crate::action::Action;
+        use crate::app::DetailsView;
+        use crate::backend::BackendEvent;
+        use crate::joblog::LogLine;
+        use crate::scheduler::{Job, JobState};
+        let (tx, mut rx) = mpsc::unbounded_channel::<BackendCommand>();
+        let mut app = App::new(tx);
+        while rx.try_recv().is_ok() {}
+
+        let job = |id, state| Job { id, command: BackendCommand::Autoremove, state };
+        app.update(Action::BackendResponse(BackendEvent::QueueUpdated(vec![job(1, JobState::Running)])))
+            .unwrap();
+        for line in [
+            LogLine::stdout("Reading package lists..."),
+            LogLine::stdout("Removing nano (7.2-1) ..."),
+            LogLine::stderr("dpkg: error processing package nano (--remove):"),
+            LogLine::stderr("E: Sub-process /usr/bin/dpkg returned an error code (1)"),
+            LogLine::stdout("Removing vim (2:9.1) ..."),
+        ] {
+            app.update(Action::BackendResponse(BackendEvent::Output(line))).unwrap();
+        }
+        app.update(Action::BackendResponse(BackendEvent::QueueUpdated(vec![
+            job(1, JobState::Failed),
+            job(2, JobState::Running),
+        ])))
+        .unwrap();
+        app.update(Action::BackendResponse(BackendEvent::Output(LogLine::stdout("Hit:1 bookworm"))))
+            .unwrap();
+        assert_eq!(app.job_logs[&1].len(), 5);
+        assert_eq!(app.job_logs[&2].len(), 1);
+
+        // Opened from the queue, the log shows the selected job
+        app.update(Action::ToggleQueue).unwrap();
+        app.update(Action::ToggleLog).unwrap();
+        assert_eq!(app.details_view, DetailsView::Log);
+        assert_eq!(app.log_job().map(|j| j.id), Some(1));
+        assert_eq!(app.log_cursor, None);
+
+        app.update(Action::JumpToFirstError).unwrap();
+        assert_eq!(app.log_cursor, Some(2));
+
+        // Search runs while the log has focus
+        app.update(Action::ToggleFocus).unwrap();
+        app.update(Action::EnterSearchMode).unwrap();
+        for c in "removing".chars() {
+            app.update(Action::UpdateSearchQuery(c)).unwrap();
+        }
+        assert_eq!(app.log_cursor, Some(4));
+        assert_eq!(app.search_query, "");
+        app.update(Action::ExitSearchMode).unwrap();
+        app.update(Action::NextLogMatch).unwrap();
+        assert_eq!(app.log_cursor, Some(1));
+
+        app.update(Action::SelectPrev).unwrap();
+        assert_eq!(app.log_cursor, Some(0));
+
+        // Logs leave with their jobs
+        app.update(Action::BackendResponse(BackendEvent::QueueUpdated(vec![job(2, JobState::Done)])))
+            .unwrap();
+        assert!(!app.job_logs.contains_key(&1));
+    }
+
+    #[tokio::test]
+    async fn test_debconf_questions() {
+        use crate::action::Action;
+        use crate::backend::BackendEvent;
+        use crate::debconf::{Question, QuestionKind};
+        let (tx, mut rx) = mpsc::unbounded_channel::<BackendCommand>();
+        let mut app = App::new(tx);
+        while rx.try_recv().is_ok() {}
+
+        let question = |tag: &str, kind, choices: &[&str], default: &str| Question {
+            tag: tag.to_string(),
+            kind,
+            title: "Configuring postfix".to_string(),
+            description: "General mail configuration type:".to_string(),
+            extended_description: String::new(),
+            choices: choices.iter().map(|c| c.to_string()).collect(),
+            default: default.to_string(),
+        };
+
+        // The current value starts highlighted; the answer goes straight back
+        let select = question("postfix/main_mailer_type", QuestionKind::Select, &["No configuration", "Internet Site", "Local only"], "Internet Site");
+        app.update(Action::BackendResponse(BackendEvent::DebconfQuestion(7, select))).unwrap();
+        assert_eq!(app.debconf_prompt.as_ref().unwrap().selected, 1);
+        app.update(Action::DebconfNext).unwrap();
+        app.update(Action::DebconfSubmit).unwrap();
+        assert_eq!(rx.try_recv().unwrap(), BackendCommand::AnswerDebconf(7, Some("Local only".into())));
+        assert!(app.debconf_prompt.is_none());
+
+        let boolean = question("tzdata/use_utc", QuestionKind::Boolean, &[], "true");
+        app.update(Action::BackendResponse(BackendEvent::DebconfQuestion(8, boolean))).unwrap();
+        app.update(Action::DebconfPrev).unwrap();
+        app.update(Action::DebconfSubmit).unwrap();
+        assert_eq!(rx.try_recv().unwrap(), BackendCommand::AnswerDebconf(8, Some("false".into())));
+
+        let multi = question("locales/locales_to_be_generated", QuestionKind::Multiselect, &["de_DE.UTF-8 UTF-8", "en_US.UTF-8 UTF-8"], "en_US.UTF-8 UTF-8");
+        app.update(Action::BackendResponse(BackendEvent::DebconfQuestion(9, multi))).unwrap();
+        app.update(Action::DebconfPrev).unwrap();
+        app.update(Action::DebconfToggle).unwrap();
+        app.update(Action::DebconfSubmit).unwrap();
+        assert_eq!(
+            rx.try_recv().unwrap(),
+            BackendCommand::AnswerDebconf(9, Some("de_DE.UTF-8 UTF-8, en_US.UTF-8 UTF-8".into()))
+        );
+
+        let string = question("postfix/mailname", QuestionKind::String, &[], "host");
+        app.update(Action::BackendResponse(BackendEvent::DebconfQuestion(10, string))).unwrap();
+        for c in ".lan".chars() {
+            app.update(Action::DebconfInput(c)).unwrap();
+        }
+        app.update(Action::DebconfSubmit).unwrap();
+        assert_eq!(rx.try_recv().unwrap(), BackendCommand::AnswerDebconf(10, Some("host.lan".into())));
+
+        // Esc goes back; a question left open dies with its job
+        let string = question("postfix/mailname", QuestionKind::String, &[], "host");
+        app.update(Action::BackendResponse(BackendEvent::DebconfQuestion(11, string.clone()))).unwrap();
+        app.update(Action::DebconfBack).unwrap();
+        assert_eq!(rx.try_recv().unwrap(), BackendCommand::AnswerDebconf(11, None));
+
+        app.update(Action::BackendResponse(BackendEvent::DebconfQuestion(12, string))).unwrap();
+        app.update(Action::BackendResponse(BackendEvent::TaskFinished(BackendCommand::ListInstalled))).unwrap();
+        assert!(app.debconf_prompt.is_some());
+        app.update(Action::BackendResponse(BackendEvent::TaskFinished(BackendCommand::Autoremove))).unwrap();
+        assert!(app.debconf_prompt.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_conffile_review() {
+        use crate::action::Action;
+        use crate::app::{DetailsView, Panel};
+        use crate::backend::BackendEvent;
+        use crate::conffile::{Conffile, ConffileChoice, Leftover};
+        use std::path::PathBuf;
+        let (tx, mut rx) = mpsc::unbounded_channel::<BackendCommand>();
+        let mut app = App::new(tx);
+        while rx.try_recv().is_ok() {}
+
+        let conffile = |path: &str, leftover| Conffile {
+            path: PathBuf::from(path),
+            package: "openssh-server".to_string(),
+            leftover,
+        };
+        let sshd = PathBuf::from("/etc/ssh/sshd_config.dpkg-dist");
+        let ssh = PathBuf::from("/etc/ssh/ssh_config.dpkg-dist");
+        let kept = |path: &str| BackendEvent::ConffileKept(conffile(path, Leftover::DpkgDist));
+        app.update(Action::BackendResponse(kept("/etc/ssh/sshd_config"))).unwrap();
+        app.update(Action::BackendResponse(kept("/etc/ssh/ssh_config"))).unwrap();
+        app.update(Action::BackendResponse(kept("/etc/ssh/sshd_config"))).unwrap();
+        assert_eq!(app.conffiles.len(), 2);
+
+        // The job that kept them ends with a pointer to the view
+        let notifications = app.notification_queue.len();
+        app.update(Action::BackendResponse(BackendEvent::TaskFinished(BackendCommand::UpgradeSystem))).unwrap();
+        assert_eq!(app.notification_queue.len(), notifications + 1);
+        assert!(app.notification_queue.last().unwrap().message.contains("press C"));
+        while rx.try_recv().is_ok() {}
+
+        // Opening the view asks for the diff of the first one and looks for
+        // older leftovers under /etc
+        app.update(Action::ToggleConffiles).unwrap();
+        assert_eq!(app.details_view, DetailsView::Conffiles);
+        assert_eq!(rx.try_recv().unwrap(), BackendCommand::DiffConffile(sshd.clone()));
+        assert_eq!(rx.try_recv().unwrap(), BackendCommand::ScanConffiles);
+        app.update(Action::BackendResponse(BackendEvent::ConffileDiff(sshd.clone(), "-a\n+b\n".into()))).unwrap();
+        let apt_old = Conffile {
+            path: PathBuf::from("/etc/apt/sources.list"),
+            package: String::new(),
+            leftover: Leftover::DpkgOld,
+        };
+        let found = vec![
+            apt_old.clone(),
+            conffile("/etc/ssh/ssh_config", Leftover::DpkgDist),
+            conffile("/etc/ssh/sshd_config", Leftover::DpkgDist),
+        ];
+        app.update(Action::BackendResponse(BackendEvent::ConffilesFound(found))).unwrap();
+        assert_eq!(app.conffiles.len(), 3);
+        assert_eq!(rx.try_recv().unwrap(), BackendCommand::DiffConffile(apt_old.leftover_path()));
+
+        app.active_panel = Panel::Details;
+        app.update(Action::SelectNext).unwrap();
+        assert_eq!(rx.try_recv().unwrap(), BackendCommand::DiffConffile(ssh.clone()));
+
+        app.update(Action::DiscardConffile).unwrap();
+        assert_eq!(rx.try_recv().unwrap(), BackendCommand::ResolveConffile(ssh.clone(), ConffileChoice::Discard));
+        assert_eq!(app.conffiles.len(), 3);
+        app.update(Action::BackendResponse(BackendEvent::TaskFinished(BackendCommand::ResolveConffile(ssh, ConffileChoice::Discard)))).unwrap();
+        assert_eq!(app.conffiles, vec![apt_old.clone(), conffile("/etc/ssh/sshd_config", Leftover::DpkgDist)]);
+        assert_eq!(app.conffile_state.selected(), Some(1));
+        let sent: Vec<BackendCommand> = std::iter::from_fn(|| rx.try_recv().ok()).collect();
+        // Its diff is already known; the scan sees what the choice left behind
+        assert!(!sent.iter().any(|cmd| matches!(cmd, BackendCommand::DiffConffile(_))));
+        assert!(sent.contains(&BackendCommand::ScanConffiles));
+
+        // Keeping both only takes it off the list, also for later scans
+        app.update(Action::AdoptConffile).unwrap();
+        assert_eq!(rx.try_recv().unwrap(), BackendCommand::ResolveConffile(sshd.clone(), ConffileChoice::Adopt));
+        app.update(Action::KeepBothConffiles).unwrap();
+        assert_eq!(app.conffiles, vec![apt_old.clone()]);
+        let found = vec![apt_old.clone(), conffile("/etc/ssh/sshd_config", Leftover::DpkgDist)];
+        app.update(Action::BackendResponse(BackendEvent::ConffilesFound(found))).unwrap();
+        assert_eq!(app.conffiles, vec![apt_old]);
+
+        assert!(!app.conffile_unified);
+        app.update(Action::ToggleConffileDiff).unwrap();
+        assert!(app.conffile_unified);
+    }
 }
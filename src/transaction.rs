@@ -0,0 +1,33 @@
+use crate::pkg::PackageId;
+use serde::{Deserialize, Serialize};
+use strum::Display;
+
+/// What a batch transaction does to every package in it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display, Serialize, Deserialize)]
+pub enum TransactionKind {
+    Install,
+    Remove,
+    Reinstall,
+    Purge,
+}
+
+/// Several packages handed to apt in a single invocation, so dependency
+/// resolution happens once and dpkg runs once.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Transaction {
+    pub kind: TransactionKind,
+    pub packages: Vec<PackageId>,
+}
+
+impl Transaction {
+    pub fn new(kind: TransactionKind, packages: Vec<PackageId>) -> Self {
+        Self { kind, packages }
+    }
+
+    /// "Install 3 packages: vim, git, curl"
+    pub fn summary(&self) -> String {
+        let names: Vec<&str> = self.packages.iter().map(|id| id.name.as_str()).collect();
+        let noun = if names.len() == 1 { "package" } else { "packages" };
+        format!("{} {} {}: {}", self.kind, names.len(), noun, names.join(", "))
+    }
+}
@@ -1,8 +1,10 @@
-use crate::app::{App, SelectedTab};
+use crate::app::{App, DetailsView, Panel, SelectedTab};
+use crate::progress::{Progress, ProgressPhase};
+use crate::simulation::Simulation;
 use ratatui::{
     prelude::*,
     style::palette::tailwind,
-    widgets::{Block, List, ListItem, Paragraph, Tabs, Wrap},
+    widgets::{Block, LineGauge, List, ListItem, Paragraph, Tabs, Wrap},
 };
 use strum::IntoEnumIterator;
 use tachyonfx::Duration as FxDuration;
@@ -36,13 +38,23 @@ pub fn draw(frame: &mut Frame, app: &mut App) {
         render_popup(frame, area, app);
     }
 
+    // Senha do sudo
+    if let Some(prompt) = &app.password_prompt {
+        render_password_prompt(frame, area, app, prompt);
+    }
+
+    // Pergunta do debconf
+    if let Some(prompt) = &app.debconf_prompt {
+        render_debconf_prompt(frame, area, prompt);
+    }
+
     // Error/Info Notifications
     if !app.notification_queue.is_empty() {
         render_error_popup(frame, area, app);
     }
 
-    // Loading Spinner
-    if app.is_loading {
+    // Loading Spinner (a barra de progresso, a espera pelo lock ou a senha já dizem o que está acontecendo)
+    if app.is_loading && app.progress.is_none() && app.lock_holder.is_none() && app.password_prompt.is_none() && app.debconf_prompt.is_none() {
         render_loading(frame, area, app);
     }
 
@@ -73,13 +85,20 @@ fn render_header(frame: &mut Frame, area: Rect, app: &App) {
 
     frame.render_widget(tabs, tabs_area);
 
-    let title = Line::from(" LAPT - Flux ")
-        .style(
-            Style::default()
-                .fg(tailwind::SLATE.c200)
-                .add_modifier(Modifier::BOLD),
-        )
-        .right_aligned();
+    let mut spans = Vec::new();
+    if !app.privilege.can_modify() {
+        spans.push(Span::styled(
+            " READ-ONLY ",
+            Style::default().bg(tailwind::AMBER.c600).fg(tailwind::SLATE.c950),
+        ));
+    }
+    spans.push(Span::styled(
+        " LAPT - Flux ",
+        Style::default()
+            .fg(tailwind::SLATE.c200)
+            .add_modifier(Modifier::BOLD),
+    ));
+    let title = Line::from(spans).right_aligned();
     frame.render_widget(title, title_area);
 }
 
@@ -96,7 +115,28 @@ fn render_content(frame: &mut Frame, area: Rect, app: &mut App) {
     let items: Vec<ListItem> = app
         .filtered_packages
         .iter()
-        .map(|pkg| ListItem::new(format!("📦 {}", pkg.name)))
+        .enumerate()
+        .map(|(i, pkg)| {
+            // Same width as the 📦 emoji, so names stay aligned
+            let icon = if app.is_selected(i) {
+                Span::styled("◆  ", Style::default().fg(tailwind::VIOLET.c400))
+            } else {
+                Span::raw("📦 ")
+            };
+            let mut spans = vec![
+                icon,
+                Span::styled(pkg.id.name.as_str(), crate::ui_details::status_style(&pkg.status)),
+            ];
+            // Held packages are kept back by `U`, so they lose the upgrade arrow
+            if pkg.status.is_upgradable() && pkg.status.held {
+                spans.push(Span::styled(" (held back)", Style::default().fg(tailwind::SLATE.c500)));
+            } else if pkg.status.is_upgradable() {
+                spans.push(Span::styled(" ↑", Style::default().fg(tailwind::AMBER.c400)));
+            } else if pkg.status.held {
+                spans.push(Span::styled(" (held)", Style::default().fg(tailwind::SLATE.c500)));
+            }
+            ListItem::new(Line::from(spans))
+        })
         .collect();
 
     let title_top = if app.is_searching {
@@ -104,8 +144,13 @@ fn render_content(frame: &mut Frame, area: Rect, app: &mut App) {
     } else {
         format!(" {} ", app.selected_tab)
     };
-    let title_bottom =
-        Line::from(format!(" Total: {} ", app.filtered_packages.len())).right_aligned();
+    let title_bottom = if app.selection.is_empty() && app.visual_anchor.is_none() {
+        format!(" Total: {} ", app.filtered_packages.len())
+    } else {
+        let visual = if app.visual_anchor.is_some() { " VISUAL " } else { "" };
+        format!("{visual} Selected: {}  Total: {} ", app.selection.len(), app.filtered_packages.len())
+    };
+    let title_bottom = Line::from(title_bottom).right_aligned();
 
     let list = List::new(items)
         .block(
@@ -125,33 +170,127 @@ fn render_content(frame: &mut Frame, area: Rect, app: &mut App) {
     frame.render_stateful_widget(list, list_area, &mut app.list_state);
 
     // Details
-    let selected = app
-        .list_state
-        .selected()
-        .and_then(|i| app.filtered_packages.get(i));
-    
-    crate::ui_details::render_details(frame, detail_area, selected);
+    match app.details_view {
+        DetailsView::Info => {
+            let selected = app
+                .list_state
+                .selected()
+                .and_then(|i| app.filtered_packages.get(i));
+            let why = app.why_installed();
+            crate::ui_details::render_details(frame, detail_area, selected, why.as_ref());
+        }
+        DetailsView::Dependencies => {
+            let entries = app.dependency_entries();
+            let focused = app.active_panel == Panel::Details;
+            crate::ui_details::render_dependencies(
+                frame,
+                detail_area,
+                &entries,
+                &mut app.dependency_state,
+                focused,
+            );
+        }
+        DetailsView::Queue => {
+            let focused = app.active_panel == Panel::Details;
+            crate::ui_details::render_queue(frame, detail_area, &app.jobs, &mut app.queue_state, focused);
+        }
+        DetailsView::Log => {
+            let focused = app.active_panel == Panel::Details;
+            crate::ui_details::render_log(
+                frame,
+                detail_area,
+                app.log_job(),
+                app.shown_log(),
+                app.log_cursor,
+                &app.log_query,
+                focused,
+            );
+        }
+        DetailsView::Conffiles => {
+            let focused = app.active_panel == Panel::Details;
+            let diff = app
+                .conffile_diff
+                .as_ref()
+                .filter(|(path, _)| app.selected_conffile().is_some_and(|c| c.leftover_path() == *path))
+                .map(|(_, diff)| diff.as_str());
+            crate::ui_details::render_conffiles(
+                frame,
+                detail_area,
+                &app.conffiles,
+                &mut app.conffile_state,
+                diff,
+                app.conffile_scroll,
+                app.conffile_unified,
+                focused,
+            );
+        }
+    }
 }
 
 fn render_status(frame: &mut Frame, area: Rect, app: &App) {
-    if app.is_loading {
+    if let Some(holder) = &app.lock_holder {
+        let msg = format!(" 🔒 Waiting for the dpkg lock held by {}  [c] Cancel ", holder.describe());
+        frame.render_widget(Line::from(msg).fg(tailwind::AMBER.c400), area);
+    } else if let Some(progress) = &app.progress {
+        render_progress(frame, area, progress);
+    } else if app.is_loading {
         let msg = format!(" ⚙️  {} ", app.loading_msg);
         let line = Line::from(msg).fg(tailwind::AMBER.c400);
         frame.render_widget(line, area);
     }
 }
 
-fn render_footer(frame: &mut Frame, area: Rect, _app: &App) {
+/// Gauge for the running apt command: "Downloading 45% · 1.2 MB/s · 5s left
+/// — Retrieving file 2 of 3".
+fn render_progress(frame: &mut Frame, area: Rect, progress: &Progress) {
+    let (phase, color) = match progress.phase {
+        ProgressPhase::Download => ("Downloading", tailwind::SKY.c400),
+        ProgressPhase::Install => ("Installing", tailwind::EMERALD.c400),
+    };
+    let mut label = format!(" {} {:.0}%", phase, progress.percent);
+    if let Some(speed) = progress.speed {
+        label.push_str(&format!(" · {}/s", format_size(speed)));
+    }
+    if let Some(eta) = &progress.eta {
+        label.push_str(&format!(" · {} left", eta));
+    }
+    if !progress.message.is_empty() {
+        label.push_str(&format!(" — {} ", progress.message));
+    }
+
+    let gauge = LineGauge::default()
+        .ratio(progress.percent / 100.0)
+        .label(Span::styled(label, Style::default().fg(color)))
+        .filled_style(Style::default().fg(color))
+        .unfilled_style(Style::default().fg(tailwind::SLATE.c700))
+        .line_set(symbols::line::THICK);
+    frame.render_widget(gauge, area);
+}
+
+fn render_footer(frame: &mut Frame, area: Rect, app: &App) {
+    // (tecla, descrição, muda o sistema)
     let keys = [
-        ("q", "Quit"),
-        ("/", "Search"),
-        ("d", "Uninstall"),
-        ("r", "Reinstall"),
-        ("U", "Upgrade"),
+        ("q", "Quit", false),
+        ("/", "Search", false),
+        ("d", "Uninstall", true),
+        ("r", "Reinstall", true),
+        ("P", "Purge", true),
+        ("Space/v", "Select", false),
+        ("U", "Upgrade", true),
+        ("A", "Autoremove", true),
+        ("a/m", "Auto/Manual", true),
+        ("H", "Hold", true),
+        ("D", "Deps", false),
+        ("Q", "Queue", false),
+        ("L", "Log", false),
+        ("C", "Config", false),
+        ("c", "Cancel", true),
     ];
+    // Só leitura: esconde o que não teria efeito
     let spans: Vec<Span> = keys
         .iter()
-        .flat_map(|(k, v)| {
+        .filter(|(_, _, mutating)| app.privilege.can_modify() || !mutating)
+        .flat_map(|(k, v, _)| {
             vec![
                 Span::styled(
                     format!(" {} ", k),
@@ -173,6 +312,11 @@ fn render_footer(frame: &mut Frame, area: Rect, _app: &App) {
 }
 
 fn render_popup(frame: &mut Frame, area: Rect, app: &App) {
+    if let Some(simulation) = &app.popup.simulation {
+        render_simulation_popup(frame, area, app, simulation);
+        return;
+    }
+
     let popup_area = centered_rect(area, 60, 20);
     frame.render_widget(ratatui::widgets::Clear, popup_area);
     let block = Block::bordered()
@@ -185,17 +329,190 @@ fn render_popup(frame: &mut Frame, area: Rect, app: &App) {
     frame.render_widget(p, popup_area);
 }
 
+fn render_simulation_popup(frame: &mut Frame, area: Rect, app: &App, simulation: &Simulation) {
+    let popup_area = centered_rect(area, 70, 70);
+    frame.render_widget(ratatui::widgets::Clear, popup_area);
+
+    let mut lines = vec![
+        Line::from(app.popup.message.as_str()).bold().centered(),
+        Line::from(""),
+    ];
+    if simulation.is_empty() {
+        lines.push(Line::from("apt reports nothing to change.").centered());
+    }
+
+    let sections = [
+        ("Install", &simulation.install, tailwind::EMERALD.c400),
+        ("Upgrade", &simulation.upgrade, tailwind::BLUE.c400),
+        ("Downgrade", &simulation.downgrade, tailwind::AMBER.c400),
+        ("Reinstall", &simulation.reinstall, tailwind::SLATE.c400),
+        ("Remove", &simulation.remove, tailwind::RED.c400),
+    ];
+    for (label, changes, color) in sections {
+        if changes.is_empty() {
+            continue;
+        }
+        lines.push(Line::from(format!("{} ({}):", label, changes.len())).fg(color).bold());
+        for change in changes {
+            let versions = match (&change.from, &change.to) {
+                (Some(from), Some(to)) if from != to => format!("{} → {}", from, to),
+                (_, Some(version)) | (Some(version), None) => version.to_string(),
+                (None, None) => String::new(),
+            };
+            lines.push(Line::from(vec![
+                Span::raw(format!("  {} ", change.name)),
+                Span::styled(versions, Style::default().fg(tailwind::SLATE.c500)),
+            ]));
+        }
+        lines.push(Line::from(""));
+    }
+
+    let disk = if simulation.disk_delta < 0 {
+        format!("{} freed", format_size(simulation.disk_delta.unsigned_abs()))
+    } else {
+        format!("{} used", format_size(simulation.disk_delta as u64))
+    };
+    lines.push(Line::from(format!(
+        "Download: {}   Disk: {}",
+        format_size(simulation.download_size),
+        disk
+    )));
+
+    // Removals are the dangerous part: make them stand out
+    let border_color = if simulation.remove.is_empty() {
+        tailwind::BLUE.c500
+    } else {
+        tailwind::RED.c500
+    };
+    let block = Block::bordered()
+        .title_top(Line::from(app.popup.title.as_str()).centered())
+        .title_bottom(Line::from(" [y] Yes  [n] No  [j/k] Scroll ").centered())
+        .border_style(Style::default().fg(border_color))
+        .border_set(symbols::border::ROUNDED);
+    let p = Paragraph::new(lines)
+        .block(block)
+        .scroll((app.popup.scroll, 0));
+    frame.render_widget(p, popup_area);
+}
+
+fn format_size(bytes: u64) -> String {
+    if bytes >= 1024 * 1024 {
+        format!("{:.1} MB", bytes as f64 / 1024.0 / 1024.0)
+    } else {
+        format!("{:.0} kB", bytes as f64 / 1024.0)
+    }
+}
+
+fn render_password_prompt(frame: &mut Frame, area: Rect, app: &App, prompt: &crate::app::PasswordPrompt) {
+    let popup_area = centered_rect(area, 50, 30);
+    frame.render_widget(ratatui::widgets::Clear, popup_area);
+    let block = Block::bordered()
+        .title(Line::from(" sudo password ").centered())
+        .border_style(Style::default().fg(tailwind::AMBER.c500))
+        .border_set(symbols::border::ROUNDED);
+
+    let action = app
+        .pending_privileged
+        .as_ref()
+        .map(|cmd| cmd.label())
+        .unwrap_or_else(|| "this".to_string());
+    let mut lines = vec![
+        Line::from(""),
+        Line::from(format!("Root is needed to {}.", action.to_lowercase())),
+        Line::from(""),
+        Line::from(format!("Password: {}", "•".repeat(prompt.input.chars().count()))).fg(tailwind::SLATE.c200),
+    ];
+    if let Some(error) = &prompt.error {
+        lines.push(Line::from(""));
+        lines.push(Line::from(error.as_str()).fg(tailwind::RED.c400));
+    }
+    lines.push(Line::from(""));
+    lines.push(Line::from("[Enter] Authenticate   [Esc] Cancel").fg(tailwind::SLATE.c500));
+
+    let p = Paragraph::new(lines)
+        .block(block)
+        .alignment(Alignment::Center)
+        .wrap(Wrap { trim: true });
+    frame.render_widget(p, popup_area);
+}
+
+pub fn render_debconf_prompt(frame: &mut Frame, area: Rect, prompt: &crate::app::DebconfPrompt) {
+    use crate::debconf::QuestionKind;
+
+    let question = &prompt.question;
+    let popup_area = centered_rect(area, 70, 60);
+    frame.render_widget(ratatui::widgets::Clear, popup_area);
+    let title = if question.title.is_empty() { "Package configuration" } else { question.title.as_str() };
+    let border = match question.kind {
+        QuestionKind::Error => tailwind::RED.c500,
+        _ => tailwind::BLUE.c500,
+    };
+    let block = Block::bordered()
+        .title(Line::from(format!(" {} ", title)).centered())
+        .title_bottom(Line::from(format!(" {} ", question.tag)).right_aligned().fg(tailwind::SLATE.c500))
+        .border_style(Style::default().fg(border))
+        .border_set(symbols::border::ROUNDED);
+
+    let mut lines = vec![
+        Line::from(""),
+        Line::from(question.description.as_str()).add_modifier(Modifier::BOLD),
+    ];
+    if !question.extended_description.is_empty() {
+        lines.push(Line::from(""));
+        lines.extend(question.extended_description.lines().map(|l| Line::from(l.to_string()).fg(tailwind::SLATE.c300)));
+    }
+    lines.push(Line::from(""));
+
+    for (i, option) in prompt.options().iter().enumerate() {
+        let marker = match question.kind {
+            QuestionKind::Multiselect if prompt.checked[i] => "[x] ",
+            QuestionKind::Multiselect => "[ ] ",
+            _ => "",
+        };
+        let line = Line::from(format!(" {}{} ", marker, option));
+        lines.push(if i == prompt.selected {
+            line.bg(tailwind::BLUE.c700).fg(tailwind::SLATE.c50)
+        } else {
+            line.fg(tailwind::SLATE.c200)
+        });
+    }
+    let keys = match question.kind {
+        QuestionKind::String => {
+            lines.push(Line::from(format!("> {}▏", prompt.input)).fg(tailwind::SLATE.c200));
+            "[Enter] OK   [Esc] Back"
+        }
+        QuestionKind::Password => {
+            lines.push(Line::from(format!("> {}▏", "•".repeat(prompt.input.chars().count()))).fg(tailwind::SLATE.c200));
+            "[Enter] OK   [Esc] Back"
+        }
+        QuestionKind::Multiselect => "[j/k] Move   [Space] Toggle   [Enter] OK   [Esc] Back",
+        QuestionKind::Select | QuestionKind::Boolean => "[j/k] Move   [Enter] OK   [Esc] Back",
+        QuestionKind::Note | QuestionKind::Text | QuestionKind::Error => "[Enter] OK   [Esc] Back",
+    };
+    lines.push(Line::from(""));
+    lines.push(Line::from(keys).fg(tailwind::SLATE.c500).centered());
+
+    let p = Paragraph::new(lines).block(block).wrap(Wrap { trim: false });
+    frame.render_widget(p, popup_area);
+}
+
 pub fn render_error_popup(frame: &mut Frame, area: Rect, app: &App) {
     if let Some(notification) = app.notification_queue.first() {
-        let popup_area = centered_rect(area, 60, 20);
+        // Erros com a saída do apt anexada precisam de mais espaço
+        let height = if notification.message.lines().count() > 1 { 40 } else { 20 };
+        let popup_area = centered_rect(area, 60, height);
         frame.render_widget(ratatui::widgets::Clear, popup_area);
 
         let border_color = match notification.kind {
             crate::app::NotificationKind::Error => tailwind::RED.c500,
+            crate::app::NotificationKind::Warning => tailwind::AMBER.c500,
+            crate::app::NotificationKind::Info => tailwind::BLUE.c500,
         };
 
         let title = match notification.kind {
             crate::app::NotificationKind::Error => " Error ",
+            crate::app::NotificationKind::Warning => " Warning ",
+            crate::app::NotificationKind::Info => " Info ",
         };
 
         let block = Block::bordered()
@@ -203,7 +520,11 @@ pub fn render_error_popup(frame: &mut Frame, area: Rect, app: &App) {
             .border_style(Style::default().fg(border_color))
             .border_set(symbols::border::ROUNDED);
 
-        let p = Paragraph::new(format!("\n{}\n\n[Esc/Enter] Dismiss", notification.message))
+        let keys = match &notification.remedy {
+            Some(remedy) => format!("[Enter] {}   [Esc] Dismiss", remedy.label),
+            None => "[Esc/Enter] Dismiss".to_string(),
+        };
+        let p = Paragraph::new(format!("\n{}\n\n{}", notification.message, keys))
             .block(block)
             .alignment(Alignment::Center)
             .wrap(Wrap { trim: true });
@@ -1,7 +1,33 @@
 use ratatui::{prelude::*, widgets::*, style::palette::tailwind};
-use crate::pkg::Package;
+use crate::app::DependencyEntry;
+use crate::conffile::{Conffile, DiffRow};
+use crate::deps::{RelationKind, WhyInstalled};
+use crate::joblog::{JobLog, LogStream};
+use crate::pkg::{Package, PackageState, PackageStatus};
+use crate::scheduler::{Job, JobState};
 
-pub fn render_details(frame: &mut Frame, area: Rect, pkg: Option<&Package>) {
+/// Colour coding shared by the package list and the details panel.
+pub fn status_style(status: &PackageStatus) -> Style {
+    let style = match status.state {
+        PackageState::Broken => Style::default()
+            .fg(tailwind::RED.c400)
+            .add_modifier(Modifier::BOLD),
+        PackageState::ConfigFiles => Style::default()
+            .fg(tailwind::SLATE.c500)
+            .add_modifier(Modifier::ITALIC),
+        PackageState::Installed if status.is_upgradable() => Style::default().fg(tailwind::AMBER.c400),
+        PackageState::Installed if status.automatic => Style::default().fg(tailwind::EMERALD.c700),
+        PackageState::Installed => Style::default().fg(tailwind::EMERALD.c400),
+        PackageState::Available => Style::default(),
+    };
+    if status.held {
+        style.add_modifier(Modifier::UNDERLINED)
+    } else {
+        style
+    }
+}
+
+pub fn render_details(frame: &mut Frame, area: Rect, pkg: Option<&Package>, why: Option<&WhyInstalled>) {
     let block = Block::bordered()
         .title(" Details ")
         .border_style(Style::default().fg(tailwind::SLATE.c700))
@@ -17,15 +43,15 @@ pub fn render_details(frame: &mut Frame, area: Rect, pkg: Option<&Package>) {
         let mut lines = vec![
             Line::from(vec![
                 Span::styled("Name: ", Style::default().fg(tailwind::SLATE.c500)),
-                Span::styled(&pkg.name, Style::default().add_modifier(Modifier::BOLD)),
+                Span::styled(&pkg.id.name, Style::default().add_modifier(Modifier::BOLD)),
             ]),
             Line::from(vec![
                 Span::styled("Version: ", Style::default().fg(tailwind::SLATE.c500)),
-                Span::raw(&pkg.version),
+                Span::raw(pkg.id.version.as_str()),
             ]),
             Line::from(vec![
                 Span::styled("Status: ", Style::default().fg(tailwind::SLATE.c500)),
-                Span::raw(&pkg.status),
+                Span::styled(pkg.status.label(), status_style(&pkg.status)),
             ]),
             Line::from(vec![
                 Span::styled("License: ", Style::default().fg(tailwind::SLATE.c500)),
@@ -39,10 +65,34 @@ pub fn render_details(frame: &mut Frame, area: Rect, pkg: Option<&Package>) {
                 Span::styled("URL: ", Style::default().fg(tailwind::SLATE.c500)),
                 Span::styled(&pkg.url, Style::default().fg(tailwind::BLUE.c400)),
             ]),
+        ];
+
+        let extra_fields = [
+            ("Section: ", &pkg.section),
+            ("Priority: ", &pkg.priority),
+            ("Maintainer: ", &pkg.maintainer),
+            ("Source: ", &pkg.source),
+            ("Depends: ", &pkg.depends),
+        ];
+        for (label, value) in extra_fields {
+            if !value.is_empty() {
+                lines.push(Line::from(vec![
+                    Span::styled(label, Style::default().fg(tailwind::SLATE.c500)),
+                    Span::raw(value.as_str()),
+                ]));
+            }
+        }
+
+        lines.extend([
             Line::from(""),
             Line::from(Span::styled(&pkg.summary, Style::default().add_modifier(Modifier::ITALIC))),
             Line::from(""),
-        ];
+        ]);
+
+        if let Some(why) = why {
+            lines.extend(why_lines(why));
+            lines.push(Line::from(""));
+        }
 
         if !pkg.description.is_empty() {
             lines.push(Line::from("Description:"));
@@ -59,4 +109,361 @@ pub fn render_details(frame: &mut Frame, area: Rect, pkg: Option<&Package>) {
         .wrap(Wrap { trim: true });
 
     frame.render_widget(p, area);
-}
\ No newline at end of file
+}
+
+/// The "Why installed" section, one aptitude-style line per chain link
+/// (m = manual, A = automatic):
+/// `mutt  Depends  default-mta | mail-transport-agent`.
+fn why_lines(why: &WhyInstalled) -> Vec<Line<'_>> {
+    let label = Style::default().fg(tailwind::SLATE.c500);
+    match why {
+        WhyInstalled::Manual => vec![Line::from(vec![
+            Span::styled("Why installed: ", label),
+            Span::raw("installed manually"),
+        ])],
+        WhyInstalled::Orphan => vec![Line::from(vec![
+            Span::styled("Why installed: ", label),
+            Span::styled(
+                "nothing needs it anymore (autoremovable)",
+                Style::default().fg(tailwind::AMBER.c400),
+            ),
+        ])],
+        WhyInstalled::Chains(chains) => {
+            let mut lines = vec![Line::styled("Why installed:", label)];
+            for (i, chain) in chains.iter().enumerate() {
+                if i > 0 {
+                    lines.push(Line::from(""));
+                }
+                for (depth, step) in chain.iter().enumerate() {
+                    let (marker, style) = if depth == 0 {
+                        ("m ", Style::default().add_modifier(Modifier::BOLD))
+                    } else {
+                        ("A ", Style::default())
+                    };
+                    // Paragraph wrapping trims leading spaces, so depth is
+                    // drawn with a visible tree marker instead of indentation
+                    let branch = if depth == 0 { "" } else { "└ " };
+                    lines.push(Line::from(vec![
+                        Span::styled(branch, label),
+                        Span::styled(marker, label),
+                        Span::styled(step.package.as_str(), style),
+                        Span::styled(
+                            format!("  {}  ", step.relation.kind),
+                            Style::default().fg(relation_color(step.relation.kind)),
+                        ),
+                        Span::raw(step.relation.to_string()),
+                    ]));
+                }
+            }
+            lines
+        }
+    }
+}
+
+fn relation_color(kind: RelationKind) -> Color {
+    match kind {
+        RelationKind::PreDepends | RelationKind::Depends => tailwind::BLUE.c400,
+        RelationKind::Recommends | RelationKind::Suggests => tailwind::SLATE.c400,
+        RelationKind::Conflicts | RelationKind::Breaks => tailwind::RED.c400,
+        RelationKind::Provides => tailwind::VIOLET.c400,
+        RelationKind::ReverseDepends => tailwind::AMBER.c400,
+        RelationKind::ReverseRecommends => tailwind::AMBER.c200,
+    }
+}
+
+/// The Dependencies view: one row per relation, Enter on a row jumps to it.
+pub fn render_dependencies(
+    frame: &mut Frame,
+    area: Rect,
+    entries: &[DependencyEntry],
+    state: &mut ListState,
+    focused: bool,
+) {
+    let border_color = if focused {
+        tailwind::BLUE.c600
+    } else {
+        tailwind::SLATE.c700
+    };
+    let block = Block::bordered()
+        .title(" Dependencies ")
+        .title_bottom(Line::from(" [D] Details  [Enter] Go to ").right_aligned())
+        .border_style(Style::default().fg(border_color))
+        .border_set(symbols::border::ROUNDED);
+
+    if entries.is_empty() {
+        frame.render_widget(Paragraph::new("No relationships").block(block), area);
+        return;
+    }
+
+    let items: Vec<ListItem> = entries
+        .iter()
+        .map(|entry| {
+            let kind = entry.relation.kind;
+            let marker = if entry.installed { "✓ " } else { "  " };
+            ListItem::new(Line::from(vec![
+                Span::styled(format!("{:<16}", kind.to_string()), Style::default().fg(relation_color(kind))),
+                Span::styled(marker, Style::default().fg(tailwind::EMERALD.c400)),
+                Span::raw(entry.relation.to_string()),
+            ]))
+        })
+        .collect();
+
+    let list = List::new(items)
+        .block(block)
+        .highlight_style(
+            Style::default()
+                .bg(tailwind::SLATE.c800)
+                .add_modifier(Modifier::BOLD),
+        );
+    frame.render_stateful_widget(list, area, state);
+}
+
+/// The job queue: what is running, what waits for the dpkg lock, and how
+/// the last jobs ended.
+pub fn render_queue(frame: &mut Frame, area: Rect, jobs: &[Job], state: &mut ListState, focused: bool) {
+    let border_color = if focused {
+        tailwind::BLUE.c600
+    } else {
+        tailwind::SLATE.c700
+    };
+    let pending = jobs.iter().filter(|j| j.state == JobState::Pending).count();
+    let block = Block::bordered()
+        .title(format!(" Queue ({} pending) ", pending))
+        .title_bottom(Line::from(" [K/J] Move  [x] Drop  [Q] Details ").right_aligned())
+        .border_style(Style::default().fg(border_color))
+        .border_set(symbols::border::ROUNDED);
+
+    if jobs.is_empty() {
+        frame.render_widget(Paragraph::new("No jobs").block(block), area);
+        return;
+    }
+
+    let items: Vec<ListItem> = jobs
+        .iter()
+        .map(|job| {
+            let (label, color) = match job.state {
+                JobState::Pending => ("pending", tailwind::SLATE.c400),
+                JobState::Running => ("running", tailwind::AMBER.c400),
+                JobState::Done => ("done", tailwind::EMERALD.c400),
+                JobState::Failed => ("failed", tailwind::RED.c400),
+                JobState::Cancelled => ("cancelled", tailwind::SLATE.c500),
+            };
+            ListItem::new(Line::from(vec![
+                Span::styled(format!("{:<10}", label), Style::default().fg(color)),
+                Span::raw(job.label()),
+            ]))
+        })
+        .collect();
+
+    let list = List::new(items)
+        .block(block)
+        .highlight_style(
+            Style::default()
+                .bg(tailwind::SLATE.c800)
+                .add_modifier(Modifier::BOLD),
+        );
+    frame.render_stateful_widget(list, area, state);
+}
+
+/// Full output of one job. The highlighted line follows the end of the log
+/// until the user scrolls, searches or jumps to an error.
+pub fn render_log(
+    frame: &mut Frame,
+    area: Rect,
+    job: Option<&Job>,
+    log: Option<&JobLog>,
+    cursor: Option<usize>,
+    query: &str,
+    focused: bool,
+) {
+    let border_color = if focused {
+        tailwind::BLUE.c600
+    } else {
+        tailwind::SLATE.c700
+    };
+    let title = match job {
+        Some(job) => format!(" Log: {} ", job.label()),
+        None => " Log ".to_string(),
+    };
+    let mut block = Block::bordered()
+        .title(title)
+        .title_bottom(Line::from(" [/] Search  [n] Next  [E] First error  [L] Details ").right_aligned())
+        .border_style(Style::default().fg(border_color))
+        .border_set(symbols::border::ROUNDED);
+    if !query.is_empty() {
+        block = block.title_bottom(Line::from(format!(" /{} ", query)).left_aligned());
+    }
+
+    let Some(log) = log.filter(|log| !log.is_empty()) else {
+        frame.render_widget(Paragraph::new("No output yet").block(block), area);
+        return;
+    };
+
+    let query = query.to_lowercase();
+    let cursor = cursor.unwrap_or(log.len() - 1).min(log.len() - 1);
+    let lines: Vec<Line> = log
+        .lines()
+        .iter()
+        .enumerate()
+        .map(|(i, line)| {
+            let mut style = if line.is_error() {
+                Style::default()
+                    .fg(tailwind::RED.c400)
+                    .add_modifier(Modifier::BOLD)
+            } else if line.stream == LogStream::Stderr {
+                Style::default().fg(tailwind::AMBER.c400)
+            } else {
+                Style::default().fg(tailwind::SLATE.c300)
+            };
+            if !query.is_empty() && line.text.to_lowercase().contains(&query) {
+                style = style.add_modifier(Modifier::UNDERLINED);
+            }
+            if i == cursor {
+                style = style.bg(tailwind::SLATE.c800);
+            }
+            Line::styled(line.text.as_str(), style)
+        })
+        .collect();
+
+    // Mantém a linha destacada no meio do painel quando possível
+    let height = area.height.saturating_sub(2) as usize;
+    let top = cursor
+        .saturating_sub(height / 2)
+        .min(log.len().saturating_sub(height));
+    let p = Paragraph::new(lines)
+        .block(block)
+        .scroll((top as u16, 0));
+    frame.render_widget(p, area);
+}
+
+/// Config files with a leftover version next to them, from the last runs
+/// or found under /etc, with the diff of the selected one below the list.
+#[allow(clippy::too_many_arguments)]
+pub fn render_conffiles(
+    frame: &mut Frame,
+    area: Rect,
+    conffiles: &[Conffile],
+    state: &mut ListState,
+    diff: Option<&str>,
+    scroll: u16,
+    unified: bool,
+    focused: bool,
+) {
+    let border_color = if focused {
+        tailwind::BLUE.c600
+    } else {
+        tailwind::SLATE.c700
+    };
+    let block = Block::bordered()
+        .title(format!(" Config files ({}) ", conffiles.len()))
+        .title_bottom(
+            Line::from(" [t] Adopt  [o] Discard  [s] Keep both  [u] Unified/side by side  [K/J] Scroll ")
+                .right_aligned(),
+        )
+        .border_style(Style::default().fg(border_color))
+        .border_set(symbols::border::ROUNDED);
+
+    if conffiles.is_empty() {
+        frame.render_widget(Paragraph::new("No config files waiting for review").block(block), area);
+        return;
+    }
+
+    let inner = block.inner(area);
+    frame.render_widget(block, area);
+    let list_height = (conffiles.len() as u16).min(inner.height / 3).max(1);
+    let [list_area, diff_area] = Layout::vertical([
+        Constraint::Length(list_height),
+        Constraint::Min(0),
+    ])
+    .areas(inner);
+
+    let items: Vec<ListItem> = conffiles
+        .iter()
+        .map(|conffile| {
+            let package = if conffile.package.is_empty() {
+                "no package"
+            } else {
+                conffile.package.as_str()
+            };
+            ListItem::new(Line::from(vec![
+                Span::raw(conffile.leftover_path().display().to_string()),
+                Span::styled(format!("  {}", package), Style::default().fg(tailwind::SLATE.c500)),
+            ]))
+        })
+        .collect();
+    let list = List::new(items).highlight_style(
+        Style::default()
+            .bg(tailwind::SLATE.c800)
+            .add_modifier(Modifier::BOLD),
+    );
+    frame.render_stateful_widget(list, list_area, state);
+
+    let diff_block = Block::new()
+        .borders(Borders::TOP)
+        .border_style(Style::default().fg(tailwind::SLATE.c700));
+    let message = match diff {
+        None => Some("Loading diff..."),
+        Some("") => Some("The files are identical"),
+        Some(_) => None,
+    };
+    if let Some(message) = message {
+        frame.render_widget(Paragraph::new(message.fg(tailwind::SLATE.c500)).block(diff_block), diff_area);
+        return;
+    }
+    let diff = diff.unwrap_or_default();
+
+    if unified {
+        let lines: Vec<Line> = diff
+            .lines()
+            .map(|line| {
+                // Cabeçalhos antes, para o "---"/"+++" não virar linha removida/adicionada
+                let color = if line.starts_with("---") || line.starts_with("+++") {
+                    tailwind::SLATE.c400
+                } else if line.starts_with("@@") {
+                    tailwind::CYAN.c400
+                } else if line.starts_with('+') {
+                    tailwind::EMERALD.c400
+                } else if line.starts_with('-') {
+                    tailwind::RED.c400
+                } else {
+                    tailwind::SLATE.c300
+                };
+                Line::styled(line, Style::default().fg(color))
+            })
+            .collect();
+        frame.render_widget(Paragraph::new(lines).block(diff_block).scroll((scroll, 0)), diff_area);
+        return;
+    }
+
+    // Lado a lado: em uso à esquerda, o leftover à direita
+    let selected = state.selected().and_then(|i| conffiles.get(i));
+    let (mut left, mut right) = (Vec::new(), Vec::new());
+    for row in crate::conffile::side_by_side(diff) {
+        let (l, r) = match row {
+            DiffRow::Hunk(hunk) => {
+                let style = Style::default().fg(tailwind::CYAN.c400);
+                (Line::styled(hunk.clone(), style), Line::styled(hunk, style))
+            }
+            DiffRow::Same(line) => {
+                let style = Style::default().fg(tailwind::SLATE.c300);
+                (Line::styled(line.clone(), style), Line::styled(line, style))
+            }
+            DiffRow::Changed(removed, added) => (
+                Line::styled(removed.unwrap_or_default(), Style::default().fg(tailwind::RED.c400)),
+                Line::styled(added.unwrap_or_default(), Style::default().fg(tailwind::EMERALD.c400)),
+            ),
+        };
+        left.push(l);
+        right.push(r);
+    }
+    let inner = diff_block.inner(diff_area);
+    frame.render_widget(diff_block, diff_area);
+    let [left_area, right_area] = Layout::horizontal([Constraint::Percentage(50), Constraint::Percentage(50)]).areas(inner);
+    let title = |text: String| Block::new().title(text.fg(tailwind::SLATE.c400)).padding(Padding::right(1));
+    let (in_use, leftover) = match selected {
+        Some(c) => (c.path.display().to_string(), c.leftover_path().display().to_string()),
+        None => (String::new(), String::new()),
+    };
+    frame.render_widget(Paragraph::new(left).block(title(in_use)).scroll((scroll, 0)), left_area);
+    frame.render_widget(Paragraph::new(right).block(title(leftover)).scroll((scroll, 0)), right_area);
+}
@@ -26,4 +26,46 @@ mod tests {
         assert!(buffer_string.contains("Critical Failure"));
         assert!(buffer_string.contains("Error"));
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn test_render_error_popup_with_remedy() {
+        use crate::app::Remedy;
+        let (tx, _) = mpsc::unbounded_channel::<BackendCommand>();
+        let mut app = App::new(tx);
+
+        let command = BackendCommand::Repair(crate::apt::Repair::FixBroken);
+        let remedy = Remedy { label: "Fix broken packages".to_string(), command };
+        app.push_notification(Notification::error("Unmet dependencies".to_string()).with_remedy(Some(remedy)));
+
+        let backend = TestBackend::new(100, 50);
+        let mut terminal = Terminal::new(backend).unwrap();
+        terminal.draw(|f| {
+            render_error_popup(f, Rect::new(0, 0, 100, 50), &app);
+        }).unwrap();
+
+        let buffer_string = format!("{:?}", terminal.backend().buffer());
+        assert!(buffer_string.contains("[Enter] Fix broken packages   [Esc] Dismiss"));
+    }
+
+    #[tokio::test]
+    async fn test_render_password_prompt() {
+        use crate::app::PasswordPrompt;
+        let (tx, _) = mpsc::unbounded_channel::<BackendCommand>();
+        let mut app = App::new(tx);
+        app.pending_privileged = Some(BackendCommand::RefreshRepos);
+        app.password_prompt = Some(PasswordPrompt {
+            input: "hunter2".to_string(),
+            error: Some("1 incorrect password attempt".to_string()),
+        });
+
+        let backend = TestBackend::new(100, 50);
+        let mut terminal = Terminal::new(backend).unwrap();
+        terminal.draw(|f| crate::ui::draw(f, &mut app)).unwrap();
+
+        let buffer_string = format!("{:?}", terminal.backend().buffer());
+        assert!(buffer_string.contains("sudo password"));
+        assert!(buffer_string.contains("•••••••"));
+        assert!(!buffer_string.contains("hunter2"));
+        assert!(buffer_string.contains("1 incorrect password attempt"));
+    }
+}
@@ -3,7 +3,7 @@ mod tests {
     use crate::app::{App, SelectedTab};
     use crate::backend::BackendCommand;
     use crate::ui;
-    use ratatui::{backend::TestBackend, Terminal, layout::Rect};
+    use ratatui::{backend::TestBackend, Terminal};
     use tokio::sync::mpsc;
 
     #[tokio::test]
@@ -26,16 +26,16 @@ mod tests {
 
     #[tokio::test]
     async fn test_ui_online_package_list_rendering() {
-        use crate::pkg::Package;
+        use crate::pkg::{Package, PackageState};
         use crate::backend::BackendEvent;
         let (tx, _) = mpsc::unbounded_channel::<BackendCommand>();
         let mut app = App::new(tx);
         app.selected_tab = SelectedTab::Online;
         
         let pkgs = vec![
-            Package::from_packagekit("online-pkg;1.0;all;apt", "Available", "A test online package")
+            Package::from_packagekit("online-pkg;1.0;all;apt", PackageState::Available, "A test online package")
         ];
-        app.update(crate::action::Action::BackendResponse(BackendEvent::SearchResultsFound(pkgs))).unwrap();
+        app.update(crate::action::Action::BackendResponse(BackendEvent::SearchResultsFound("online-pkg".to_string(), pkgs))).unwrap();
 
         let backend = TestBackend::new(100, 50);
         let mut terminal = Terminal::new(backend).unwrap();
@@ -47,4 +47,94 @@ mod tests {
         let buffer_string = format!("{:?}", terminal.backend().buffer());
         assert!(buffer_string.contains("online-pkg"));
     }
+
+    #[tokio::test]
+    async fn test_ui_simulation_popup_rendering() {
+        use crate::backend::BackendEvent;
+        use crate::pkg::PackageId;
+        let (tx, _) = mpsc::unbounded_channel::<BackendCommand>();
+        let mut app = App::new(tx);
+        app.is_loading = false;
+
+        let mut simulation = crate::simulation::parse(
+            "Inst vim [2:9.0-1] (2:9.0-2 Debian:12/stable [amd64])\nRemv nano [7.2-1]\n",
+        );
+        simulation.download_size = 3 * 1024 * 1024;
+        simulation.disk_delta = -512 * 1024;
+        let cmd = BackendCommand::Remove(PackageId::new("nano", "7.2-1", "amd64", "installed"));
+        app.update(crate::action::Action::BackendResponse(BackendEvent::SimulationFound(
+            Box::new(cmd),
+            Box::new(simulation),
+        )))
+        .unwrap();
+
+        let backend = TestBackend::new(100, 50);
+        let mut terminal = Terminal::new(backend).unwrap();
+        terminal.draw(|f| {
+            ui::draw(f, &mut app);
+        }).unwrap();
+
+        let buffer_string = format!("{:?}", terminal.backend().buffer());
+        assert!(buffer_string.contains("Remove nano?"));
+        assert!(buffer_string.contains("Upgrade (1):"));
+        assert!(buffer_string.contains("vim 2:9.0-1 → 2:9.0-2"));
+        assert!(buffer_string.contains("Remove (1):"));
+        assert!(buffer_string.contains("Download: 3.0 MB   Disk: 512 kB freed"));
+    }
+
+    #[tokio::test]
+    async fn test_ui_upgradable_held_back_rendering() {
+        use crate::backend::BackendEvent;
+        use crate::pkg::{Package, PackageState};
+        let (tx, _) = mpsc::unbounded_channel::<BackendCommand>();
+        let mut app = App::new(tx);
+        app.is_loading = false;
+        app.selected_tab = SelectedTab::Upgradable;
+
+        let mut held = Package::from_packagekit("linux-image;6.1;amd64;installed", PackageState::Installed, "");
+        held.status.held = true;
+        app.update(crate::action::Action::BackendResponse(BackendEvent::InstalledPackagesFound(vec![held])))
+            .unwrap();
+        let mut upgradable = Package::from_packagekit("linux-image;6.2;amd64;stable", PackageState::Installed, "");
+        upgradable.status.upgradable_from = Some("6.1".into());
+        app.update(crate::action::Action::BackendResponse(BackendEvent::UpgradablePackagesFound(vec![upgradable])))
+            .unwrap();
+
+        let backend = TestBackend::new(100, 50);
+        let mut terminal = Terminal::new(backend).unwrap();
+        terminal.draw(|f| {
+            ui::draw(f, &mut app);
+        }).unwrap();
+
+        let buffer_string = format!("{:?}", terminal.backend().buffer());
+        assert!(buffer_string.contains("linux-image (held back)"));
+    }
+
+    #[tokio::test]
+    async fn test_ui_progress_gauge_rendering() {
+        use crate::backend::BackendEvent;
+        use crate::progress::parse_status_line;
+        let (tx, _) = mpsc::unbounded_channel::<BackendCommand>();
+        let mut app = App::new(tx);
+
+        let mut progress = parse_status_line("dlstatus:2:45:Retrieving file 2 of 3 (5s remaining)").unwrap();
+        progress.speed = Some(1_258_291);
+        app.update(crate::action::Action::BackendResponse(BackendEvent::Progress(progress)))
+            .unwrap();
+
+        let backend = TestBackend::new(100, 50);
+        let mut terminal = Terminal::new(backend).unwrap();
+        terminal.draw(|f| {
+            ui::draw(f, &mut app);
+        }).unwrap();
+
+        let buffer_string = format!("{:?}", terminal.backend().buffer());
+        assert!(buffer_string.contains("Downloading 45% · 1.2 MB/s · 5s left — Retrieving file 2 of 3"));
+        // The gauge replaces the loading popup
+        assert!(!buffer_string.contains("Loading"));
+
+        app.update(crate::action::Action::BackendResponse(BackendEvent::TaskFinished(BackendCommand::RefreshRepos)))
+            .unwrap();
+        assert_eq!(app.progress, None);
+    }
 }
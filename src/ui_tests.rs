@@ -1,12 +1,12 @@
 #[cfg(test)]
 mod tests {
-    use crate::pkg::Package;
+    use crate::pkg::{Package, PackageState};
     use crate::ui_details::render_details;
     use ratatui::{backend::TestBackend, Terminal, layout::Rect};
 
     #[test]
     fn test_render_details_content() {
-        let mut pkg = Package::from_packagekit("vim;8.2;x64;repo", "installed", "Vi IMproved");
+        let mut pkg = Package::from_packagekit("vim;8.2;x64;repo", PackageState::Installed, "Vi IMproved");
         pkg.update_details("A great editor", "Vim", 1024, "https://vim.org");
 
         let backend = TestBackend::new(80, 20);
@@ -14,7 +14,7 @@ mod tests {
 
         terminal.draw(|f| {
             let area = Rect::new(0, 0, 80, 20);
-            render_details(f, area, Some(&pkg));
+            render_details(f, area, Some(&pkg), None);
         }).unwrap();
 
         // Note: Exact matching might be tricky with wrapping and dynamic content, 
@@ -27,4 +27,243 @@ mod tests {
         assert!(buffer_string.contains("Version: 8.2"));
         assert!(buffer_string.contains("A great editor"));
     }
+
+    #[test]
+    fn test_render_details_dpkg_fields() {
+        let mut pkg = Package::from_packagekit("vim;8.2;x64;repo", PackageState::Installed, "Vi IMproved");
+        pkg.section = "editors".to_string();
+        pkg.maintainer = "Debian Vim Maintainers".to_string();
+
+        let backend = TestBackend::new(80, 20);
+        let mut terminal = Terminal::new(backend).unwrap();
+
+        terminal.draw(|f| {
+            let area = Rect::new(0, 0, 80, 20);
+            render_details(f, area, Some(&pkg), None);
+        }).unwrap();
+
+        let buffer_string = format!("{:?}", terminal.backend().buffer());
+        assert!(buffer_string.contains("Section: editors"));
+        assert!(buffer_string.contains("Maintainer: Debian Vim Maintainers"));
+        assert!(!buffer_string.contains("Source:"));
+    }
+
+    #[test]
+    fn test_render_details_status() {
+        let mut pkg = Package::from_packagekit("libc6;2.39;amd64;installed", PackageState::Installed, "");
+        pkg.status.automatic = true;
+
+        let backend = TestBackend::new(80, 20);
+        let mut terminal = Terminal::new(backend).unwrap();
+
+        terminal.draw(|f| {
+            let area = Rect::new(0, 0, 80, 20);
+            render_details(f, area, Some(&pkg), None);
+        }).unwrap();
+
+        let buffer = terminal.backend().buffer();
+        let buffer_string = format!("{:?}", buffer);
+        assert!(buffer_string.contains("Status: Installed (automatic)"));
+
+        // The label carries the status colour, not the default style
+        let status_row = (0..20)
+            .find(|&y| buffer[(1, y)].symbol() == "S" && buffer[(2, y)].symbol() == "t")
+            .unwrap();
+        assert_eq!(buffer[(9, status_row)].fg, ratatui::style::palette::tailwind::EMERALD.c700);
+    }
+
+    #[test]
+    fn test_render_dependencies() {
+        use crate::app::DependencyEntry;
+        use crate::deps::{parse_field, RelationKind};
+        use crate::ui_details::render_dependencies;
+        use ratatui::widgets::ListState;
+
+        let entries: Vec<DependencyEntry> = parse_field(RelationKind::PreDepends, "debconf (>= 0.5)")
+            .into_iter()
+            .chain(parse_field(RelationKind::Conflicts, "mail-transport-agent"))
+            .map(|relation| DependencyEntry { installed: relation.kind == RelationKind::PreDepends, relation })
+            .collect();
+        let mut state = ListState::default();
+        state.select(Some(0));
+
+        let backend = TestBackend::new(80, 10);
+        let mut terminal = Terminal::new(backend).unwrap();
+        terminal.draw(|f| {
+            render_dependencies(f, Rect::new(0, 0, 80, 10), &entries, &mut state, true);
+        }).unwrap();
+
+        let buffer_string = format!("{:?}", terminal.backend().buffer());
+        assert!(buffer_string.contains("Pre-Depends     ✓ debconf (>= 0.5)"));
+        assert!(buffer_string.contains("Conflicts         mail-transport-agent"));
+    }
+
+    #[test]
+    fn test_render_details_why_installed() {
+        use crate::deps::{parse_field, RelationKind, WhyInstalled, WhyStep};
+
+        let pkg = Package::from_packagekit("exim4-base;4.96;amd64;installed", PackageState::Installed, "");
+        let why = WhyInstalled::Chains(vec![vec![
+            WhyStep {
+                package: "mutt".to_string(),
+                relation: parse_field(RelationKind::Depends, "default-mta | mail-transport-agent").remove(0),
+            },
+            WhyStep {
+                package: "exim4-daemon-light".to_string(),
+                relation: parse_field(RelationKind::Depends, "exim4-base (>= 4.96)").remove(0),
+            },
+        ]]);
+
+        let backend = TestBackend::new(80, 20);
+        let mut terminal = Terminal::new(backend).unwrap();
+        terminal.draw(|f| {
+            render_details(f, Rect::new(0, 0, 80, 20), Some(&pkg), Some(&why));
+        }).unwrap();
+
+        let buffer_string = format!("{:?}", terminal.backend().buffer());
+        assert!(buffer_string.contains("Why installed:"));
+        assert!(buffer_string.contains("m mutt  Depends  default-mta | mail-transport-agent"));
+        assert!(buffer_string.contains("└ A exim4-daemon-light  Depends  exim4-base (>= 4.96)"));
+    }
+
+    #[test]
+    fn test_render_queue() {
+        use crate::backend::BackendCommand;
+        use crate::scheduler::{Job, JobState};
+        use crate::ui_details::render_queue;
+
+        let jobs = vec![
+            Job { id: 1, command: BackendCommand::UpgradeSystem, state: JobState::Failed },
+            Job { id: 2, command: BackendCommand::Autoremove, state: JobState::Running },
+            Job { id: 3, command: BackendCommand::RefreshRepos, state: JobState::Pending },
+        ];
+
+        let backend = TestBackend::new(60, 10);
+        let mut terminal = Terminal::new(backend).unwrap();
+        terminal.draw(|f| {
+            render_queue(f, Rect::new(0, 0, 60, 10), &jobs, &mut ratatui::widgets::ListState::default(), false);
+        }).unwrap();
+
+        let buffer_string = format!("{:?}", terminal.backend().buffer());
+        assert!(buffer_string.contains("Queue (1 pending)"));
+        assert!(buffer_string.contains("failed    Upgrade system"));
+        assert!(buffer_string.contains("running   Autoremove"));
+        assert!(buffer_string.contains("pending   Refresh repositories"));
+    }
+
+    #[test]
+    fn test_render_log() {
+        use crate::backend::BackendCommand;
+        use crate::joblog::{JobLog, LogLine};
+        use crate::scheduler::{Job, JobState};
+        use crate::ui_details::render_log;
+
+        let job = Job { id: 1, command: BackendCommand::Autoremove, state: JobState::Failed };
+        let mut log = JobLog::default();
+        for i in 0..20 {
+            log.push(LogLine::stdout(format!("Removing pkg{} ...", i)));
+        }
+        log.push(LogLine::stderr("E: Sub-process /usr/bin/dpkg returned an error code (1)"));
+
+        let backend = TestBackend::new(70, 10);
+        let mut terminal = Terminal::new(backend).unwrap();
+        terminal.draw(|f| {
+            render_log(f, Rect::new(0, 0, 70, 10), Some(&job), Some(&log), None, "", true);
+        }).unwrap();
+
+        // Following the end of the log
+        let buffer_string = format!("{:?}", terminal.backend().buffer());
+        assert!(buffer_string.contains("Log: Autoremove"));
+        assert!(buffer_string.contains("E: Sub-process /usr/bin/dpkg"));
+        assert!(!buffer_string.contains("Removing pkg3 "));
+
+        terminal.draw(|f| {
+            render_log(f, Rect::new(0, 0, 70, 10), Some(&job), Some(&log), Some(3), "pkg", true);
+        }).unwrap();
+        let buffer_string = format!("{:?}", terminal.backend().buffer());
+        assert!(buffer_string.contains("Removing pkg3 "));
+        assert!(buffer_string.contains("/pkg"));
+    }
+
+    #[test]
+    fn test_render_debconf_prompt() {
+        use crate::app::DebconfPrompt;
+        use crate::debconf::{Question, QuestionKind};
+        use crate::ui::render_debconf_prompt;
+
+        let question = Question {
+            tag: "tzdata/Areas".to_string(),
+            kind: QuestionKind::Select,
+            title: "Configuring tzdata".to_string(),
+            description: "Geographic area:".to_string(),
+            extended_description: "Please select the geographic area in which you live.".to_string(),
+            choices: vec!["Africa".to_string(), "America".to_string(), "Europe".to_string()],
+            default: "Europe".to_string(),
+        };
+        let backend = TestBackend::new(100, 30);
+        let mut terminal = Terminal::new(backend).unwrap();
+        terminal.draw(|f| {
+            render_debconf_prompt(f, Rect::new(0, 0, 100, 30), &DebconfPrompt::new(1, question.clone()));
+        }).unwrap();
+
+        let buffer_string = format!("{:?}", terminal.backend().buffer());
+        assert!(buffer_string.contains("Configuring tzdata"));
+        assert!(buffer_string.contains("Geographic area:"));
+        assert!(buffer_string.contains("America"));
+        assert!(buffer_string.contains("[j/k] Move   [Enter] OK   [Esc] Back"));
+
+        let mut prompt = DebconfPrompt::new(2, Question {
+            kind: QuestionKind::Password,
+            description: "MySQL root password:".to_string(),
+            choices: Vec::new(),
+            ..question
+        });
+        prompt.input = "s3cret".to_string();
+        terminal.draw(|f| {
+            render_debconf_prompt(f, Rect::new(0, 0, 100, 30), &prompt);
+        }).unwrap();
+        let buffer_string = format!("{:?}", terminal.backend().buffer());
+        assert!(buffer_string.contains("••••••"));
+        assert!(!buffer_string.contains("s3cret"));
+    }
+
+    #[test]
+    fn test_render_conffiles() {
+        use crate::conffile::Conffile;
+        use crate::ui_details::render_conffiles;
+        use std::path::PathBuf;
+
+        let conffiles = vec![Conffile {
+            path: PathBuf::from("/etc/ssh/sshd_config"),
+            package: "openssh-server".to_string(),
+            leftover: crate::conffile::Leftover::DpkgDist,
+        }];
+        let diff = "--- /etc/ssh/sshd_config\n+++ /etc/ssh/sshd_config.dpkg-dist\n@@ -1 +1 @@\n-PermitRootLogin yes\n+#PermitRootLogin prohibit-password\n";
+        let backend = TestBackend::new(100, 30);
+        let mut terminal = Terminal::new(backend).unwrap();
+        let mut state = ratatui::widgets::ListState::default().with_selected(Some(0));
+        terminal.draw(|f| {
+            render_conffiles(f, Rect::new(0, 0, 100, 30), &conffiles, &mut state, Some(diff), 0, true, true);
+        }).unwrap();
+
+        let buffer_string = format!("{:?}", terminal.backend().buffer());
+        assert!(buffer_string.contains("Config files (1)"));
+        assert!(buffer_string.contains("/etc/ssh/sshd_config.dpkg-dist  openssh-server"));
+        assert!(buffer_string.contains("-PermitRootLogin yes"));
+        assert!(buffer_string.contains("+#PermitRootLogin prohibit-password"));
+        assert!(buffer_string.contains("[t] Adopt"));
+
+        // Side by side: each version in its own column, without the markers
+        terminal.draw(|f| {
+            render_conffiles(f, Rect::new(0, 0, 100, 30), &conffiles, &mut state, Some(diff), 0, false, true);
+        }).unwrap();
+        let buffer = terminal.backend().buffer();
+        let row = (0..30)
+            .map(|y| (0..100).map(|x| buffer[(x, y)].symbol()).collect::<String>())
+            .find(|row| row.contains("PermitRootLogin yes"))
+            .unwrap();
+        assert!(row.contains("#PermitRootLogin prohibit-password"));
+        assert!(row.find("PermitRootLogin yes") < row.find("#PermitRootLogin"));
+        assert!(!row.contains("-PermitRootLogin"));
+    }
 }
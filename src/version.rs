@@ -0,0 +1,166 @@
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::fmt;
+
+/// A Debian package version (`[epoch:]upstream[-revision]`), ordered exactly
+/// like `dpkg --compare-versions`.
+///
+/// Parsing is lenient like apt's: anything is accepted and compared by the
+/// same rules, so malformed versions from third-party repositories still sort.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(into = "String", from = "String")] // Só o texto; o resto sai do parse
+pub struct PackageVersion {
+    raw: String,
+    epoch: u64,
+    upstream: String,
+    revision: String,
+}
+
+impl PackageVersion {
+    pub fn parse(raw: &str) -> Self {
+        let raw = raw.trim();
+
+        let (epoch, rest) = match raw.split_once(':') {
+            Some((epoch, rest)) if epoch.chars().all(|c| c.is_ascii_digit()) => {
+                (epoch.parse().unwrap_or(0), rest)
+            }
+            _ => (0, raw),
+        };
+
+        let (upstream, revision) = match rest.rsplit_once('-') {
+            Some((upstream, revision)) => (upstream, revision),
+            None => (rest, ""),
+        };
+
+        Self {
+            raw: raw.to_string(),
+            epoch,
+            upstream: upstream.to_string(),
+            revision: revision.to_string(),
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.raw
+    }
+}
+
+/// Sort weight of a non-digit character in dpkg's `verrevcmp`: `~` sorts
+/// before everything (even the end of the string), letters before symbols.
+fn order(c: Option<u8>) -> i32 {
+    match c {
+        None => 0,
+        Some(c) if c.is_ascii_digit() => 0,
+        Some(c) if c.is_ascii_alphabetic() => c as i32,
+        Some(b'~') => -1,
+        Some(c) => c as i32 + 256,
+    }
+}
+
+/// Port of dpkg's `verrevcmp()`: alternates between comparing non-digit
+/// prefixes character by character and digit runs numerically.
+fn compare_part(a: &str, b: &str) -> Ordering {
+    let a = a.as_bytes();
+    let b = b.as_bytes();
+    let (mut i, mut j) = (0, 0);
+    let is_digit = |s: &[u8], k: usize| s.get(k).is_some_and(u8::is_ascii_digit);
+
+    while i < a.len() || j < b.len() {
+        while (i < a.len() && !is_digit(a, i)) || (j < b.len() && !is_digit(b, j)) {
+            let ac = order(a.get(i).copied());
+            let bc = order(b.get(j).copied());
+            if ac != bc {
+                return ac.cmp(&bc);
+            }
+            i += 1;
+            j += 1;
+        }
+
+        while a.get(i) == Some(&b'0') {
+            i += 1;
+        }
+        while b.get(j) == Some(&b'0') {
+            j += 1;
+        }
+
+        let mut first_diff = Ordering::Equal;
+        while is_digit(a, i) && is_digit(b, j) {
+            if first_diff == Ordering::Equal {
+                first_diff = a[i].cmp(&b[j]);
+            }
+            i += 1;
+            j += 1;
+        }
+        if is_digit(a, i) {
+            return Ordering::Greater;
+        }
+        if is_digit(b, j) {
+            return Ordering::Less;
+        }
+        if first_diff != Ordering::Equal {
+            return first_diff;
+        }
+    }
+
+    Ordering::Equal
+}
+
+impl Ord for PackageVersion {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.epoch
+            .cmp(&other.epoch)
+            .then_with(|| compare_part(&self.upstream, &other.upstream))
+            .then_with(|| compare_part(&self.revision, &other.revision))
+    }
+}
+
+impl PartialOrd for PackageVersion {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+// Equality follows dpkg too: "1.0" and "0:1.0-0" are the same version.
+impl PartialEq for PackageVersion {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for PackageVersion {}
+
+impl PartialEq<str> for PackageVersion {
+    fn eq(&self, other: &str) -> bool {
+        *self == PackageVersion::parse(other)
+    }
+}
+
+impl PartialEq<&str> for PackageVersion {
+    fn eq(&self, other: &&str) -> bool {
+        *self == PackageVersion::parse(other)
+    }
+}
+
+impl From<&str> for PackageVersion {
+    fn from(raw: &str) -> Self {
+        Self::parse(raw)
+    }
+}
+
+impl From<String> for PackageVersion {
+    fn from(raw: String) -> Self {
+        Self::parse(&raw)
+    }
+}
+
+impl From<PackageVersion> for String {
+    fn from(version: PackageVersion) -> Self {
+        version.raw
+    }
+}
+
+impl fmt::Display for PackageVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.raw)
+    }
+}
@@ -0,0 +1,84 @@
+#[cfg(test)]
+mod tests {
+    use crate::version::PackageVersion;
+    use std::cmp::Ordering::{self, Equal, Greater, Less};
+
+    /// Expected results captured from `dpkg --compare-versions a lt|eq|gt b`.
+    const DPKG_TABLE: &[(&str, &str, Ordering)] = &[
+        ("1.0", "1.0", Equal),
+        ("1.0", "1.1", Less),
+        ("1.1", "1.0", Greater),
+        ("1.0", "0:1.0", Equal),
+        ("1.0", "1.0-0", Equal),
+        ("1:1.0", "2.0", Greater),
+        ("2:0.1", "1:9.9", Greater),
+        ("1.0~rc1", "1.0", Less),
+        ("1.0~rc1", "1.0~rc2", Less),
+        ("1.0~~", "1.0~", Less),
+        ("1.0~", "1.0", Less),
+        ("1.0~~a", "1.0~~", Greater),
+        ("1.0", "1.0a", Less),
+        ("1.0a", "1.0+", Less),
+        ("1.0+", "1.0.", Less),
+        ("1.0.", "1.0-1", Greater),
+        ("1.0-1", "1.0-2", Less),
+        ("1.0-1", "1.0-1ubuntu1", Less),
+        ("1.0-1ubuntu1", "1.0-1ubuntu2", Less),
+        ("1.0-1ubuntu10", "1.0-1ubuntu9", Greater),
+        ("1.0-1+deb12u1", "1.0-1", Greater),
+        ("1.0-1~bpo12+1", "1.0-1", Less),
+        ("1.0-1~bpo12+1", "1.0-0", Greater),
+        ("10", "9", Greater),
+        ("1.10", "1.9", Greater),
+        ("1.010", "1.10", Equal),
+        ("1.001", "1.1", Equal),
+        ("0.0", "0", Greater),
+        ("a", "b", Less),
+        ("a", "1", Greater),
+        ("1a", "1", Greater),
+        ("1.0-a", "1.0-1", Greater),
+        ("2:9.1.0016-1ubuntu7.9", "2:9.1.0016-1ubuntu7.10", Less),
+        ("3.137ubuntu1", "3.137", Greater),
+        ("7.2-1", "7.2-1+b1", Less),
+        ("1.2.3-4", "1.2.3-4.1", Less),
+        ("1:1.2.13.dfsg-1ubuntu5", "1:1.2.11.dfsg-2ubuntu9", Greater),
+        ("0.9.5-6ubuntu2", "0.9.5-6build1", Greater),
+        ("1.0-1-1", "1.0-1", Greater),
+        ("1.0+dfsg1-1", "1.0-1", Greater),
+        ("2.36-9+deb12u4", "2.36-9+deb12u10", Less),
+    ];
+
+    #[test]
+    fn test_compare_matches_dpkg() {
+        for (a, b, expected) in DPKG_TABLE {
+            let (va, vb) = (PackageVersion::parse(a), PackageVersion::parse(b));
+            assert_eq!(va.cmp(&vb), *expected, "{} vs {}", a, b);
+            assert_eq!(vb.cmp(&va), expected.reverse(), "{} vs {} (reversed)", b, a);
+        }
+    }
+
+    #[test]
+    fn test_display_keeps_original_text() {
+        let v = PackageVersion::parse(" 2:9.1.0016-1ubuntu7.9 ");
+        assert_eq!(v.to_string(), "2:9.1.0016-1ubuntu7.9");
+        assert_eq!(v.as_str(), "2:9.1.0016-1ubuntu7.9");
+
+        // Only the last hyphen starts the revision
+        assert!(PackageVersion::parse("1.0-beta-3") > PackageVersion::parse("1.0-beta"));
+        assert!(PackageVersion::parse("1.0-beta-3") < PackageVersion::parse("1.0-beta-10"));
+    }
+
+    #[test]
+    fn test_sorting_and_equality() {
+        let mut versions: Vec<PackageVersion> = ["1.0-1", "1:0.5", "1.0~rc1-1", "1.0-1+deb12u1", "0.9"]
+            .into_iter()
+            .map(PackageVersion::from)
+            .collect();
+        versions.sort();
+        let sorted: Vec<&str> = versions.iter().map(|v| v.as_str()).collect();
+        assert_eq!(sorted, vec!["0.9", "1.0~rc1-1", "1.0-1", "1.0-1+deb12u1", "1:0.5"]);
+
+        assert_eq!(PackageVersion::parse("0:1.0-0"), "1.0");
+        assert!(PackageVersion::parse("1.0") > PackageVersion::default());
+    }
+}